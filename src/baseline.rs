@@ -0,0 +1,250 @@
+use crate::{config::Config, error::ScreenshotError};
+use crate::capture::{dimension_mismatch, process_image, region::Region, scale::BASE_DPI, timelapse::grab_frame, write_png};
+use crate::diff::{DiffLayout, DiffOptions, DiffReport, ImageDiff};
+use crate::utils::atomic::{write_atomic, CollisionStrategy};
+use crate::utils::clipboard::encode_png;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use log::{debug, info};
+
+/// Comparison settings stored next to a baseline as `<name>.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BaselineSettings {
+    pub ignore: Vec<Region>,
+    pub tolerance: u8,
+    pub antialiasing: bool,
+    pub threshold: f64,
+}
+
+impl BaselineSettings {
+    /// Adds the ignore areas of `stored` that are not listed yet, returning how many.
+    pub fn keep_ignore(&mut self, stored: &BaselineSettings) -> usize {
+        let before = self.ignore.len();
+        for region in &stored.ignore {
+            if !self.ignore.contains(region) {
+                self.ignore.push(*region);
+            }
+        }
+        self.ignore.len() - before
+    }
+}
+
+/// Outcome of comparing one capture against its baseline.
+pub struct BaselineCheck {
+    pub name: String,
+    pub passed: bool,
+    pub threshold: f64,
+    pub report: DiffReport,
+    pub baseline: RgbaImage,
+    pub actual: RgbaImage,
+    pub diff: RgbaImage,
+}
+
+/// A directory of named baseline images, e.g. `login.png` plus `login.json`.
+/// Names may contain `/` to group baselines into subdirectories.
+pub struct BaselineStore {
+    directory: PathBuf,
+}
+
+impl BaselineStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self { directory: directory.into() }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn image_path(&self, name: &str) -> Result<PathBuf, ScreenshotError> {
+        validate_name(name)?;
+        Ok(self.directory.join(format!("{}.png", name)))
+    }
+
+    fn settings_path(&self, name: &str) -> Result<PathBuf, ScreenshotError> {
+        validate_name(name)?;
+        Ok(self.directory.join(format!("{}.json", name)))
+    }
+
+    /// Stores `image` as the baseline for `name`, replacing any previous one.
+    pub fn approve(
+        &self,
+        name: &str,
+        image: &RgbaImage,
+        settings: &BaselineSettings,
+    ) -> Result<PathBuf, ScreenshotError> {
+        let path = self.image_path(name)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        write_png(&path, image.as_raw(), image.width(), image.height(), BASE_DPI as u32)?;
+
+        let json = serde_json::to_vec_pretty(settings)
+            .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+        write_atomic(&self.settings_path(name)?, CollisionStrategy::Overwrite, |file| Ok(file.write_all(&json)?))?;
+
+        info!("Baseline {} approved: {}", name, path.display());
+        Ok(path)
+    }
+
+    pub fn load(&self, name: &str) -> Result<(RgbaImage, BaselineSettings), ScreenshotError> {
+        let path = self.image_path(name)?;
+        if !path.exists() {
+            return Err(ScreenshotError::BaselineNotFound(format!(
+                "{} (no {}; approve it first)", name, path.display()
+            )));
+        }
+        let image = image::open(&path)?.to_rgba8();
+        let settings = self.settings(name)?.unwrap_or_default();
+
+        Ok((image, settings))
+    }
+
+    /// The stored comparison settings of `name`, if it has any.
+    pub fn settings(&self, name: &str) -> Result<Option<BaselineSettings>, ScreenshotError> {
+        let settings_path = self.settings_path(name)?;
+        if !settings_path.exists() {
+            return Ok(None);
+        }
+
+        serde_json::from_slice(&std::fs::read(&settings_path)?)
+            .map(Some)
+            .map_err(|e| ScreenshotError::BaselineNotFound(format!(
+                "{}: invalid {}: {}", name, settings_path.display(), e
+            )))
+    }
+
+    /// Compares `actual` against the baseline `name`. `extra` ignore areas are
+    /// added to the stored ones, the larger tolerance wins, and `threshold`
+    /// overrides the stored threshold when given.
+    pub fn check(
+        &self,
+        name: &str,
+        actual: RgbaImage,
+        extra: &DiffOptions,
+        threshold: Option<f64>,
+    ) -> Result<BaselineCheck, ScreenshotError> {
+        let (baseline, settings) = self.load(name)?;
+
+        let options = DiffOptions {
+            tolerance: settings.tolerance.max(extra.tolerance),
+            ignore: settings.ignore.iter().chain(&extra.ignore).copied().collect(),
+            antialiasing: settings.antialiasing || extra.antialiasing,
+        };
+        let threshold = threshold.unwrap_or(settings.threshold);
+
+        let diff = ImageDiff::compute_with(&baseline, &actual, &options);
+        let passed = baseline.dimensions() == actual.dimensions()
+            && diff.report.changed_fraction <= threshold;
+
+        debug!("Baseline {}: {} changed pixels, passed: {}", name, diff.report.changed_pixels, passed);
+
+        Ok(BaselineCheck {
+            name: name.to_string(),
+            passed,
+            threshold,
+            diff: diff.render(&baseline, &actual, DiffLayout::Highlight),
+            report: diff.report,
+            baseline,
+            actual,
+        })
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), ScreenshotError> {
+    let valid_chars = name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'));
+    let valid_parts = name.split('/').all(|part| !part.is_empty() && part != "." && part != "..");
+
+    if name.is_empty() || !valid_chars || !valid_parts {
+        return Err(ScreenshotError::InvalidBaselineName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Captures a screen or region through the normal processing pipeline, to
+/// approve or check against a baseline.
+pub fn capture_actual(
+    screen: usize,
+    region: Option<&Region>,
+    config: &Config,
+) -> Result<RgbaImage, ScreenshotError> {
+    let image = grab_frame(screen, region, config)?;
+    let processed = process_image(&image.data, image.width, image.height, config, &image.info)?;
+    RgbaImage::from_raw(processed.width, processed.height, processed.data.into_owned())
+        .ok_or_else(dimension_mismatch)
+}
+
+/// Writes a single HTML file with every failed check (baseline, actual and
+/// highlighted difference embedded as images) and a list of the passes.
+pub fn write_report(path: &Path, checks: &[BaselineCheck]) -> Result<(), ScreenshotError> {
+    let failed = checks.iter().filter(|check| !check.passed).count();
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Baseline report</title>\n\
+         <style>body{{font-family:sans-serif;margin:2em}}.images{{display:flex;gap:1em;flex-wrap:wrap}}\
+         figure{{margin:0}}img{{max-width:32vw;border:1px solid #ccc}}.fail{{color:#c00}}.pass{{color:#080}}</style>\n\
+         </head><body>\n<h1>Baseline report</h1>\n<p>{} of {} checks failed.</p>\n",
+        failed,
+        checks.len()
+    );
+
+    for check in checks.iter().filter(|check| !check.passed) {
+        let report = &check.report;
+        let _ = write!(
+            html,
+            "<section>\n<h2 class=\"fail\">{}</h2>\n<p>{} of {} pixels changed ({:.3}%, allowed {:.3}%), \
+             {} ignored, {} anti-aliased; baseline {}x{}, actual {}x{}</p>\n",
+            escape_html(&check.name),
+            report.changed_pixels,
+            report.total_pixels,
+            report.changed_fraction * 100.0,
+            check.threshold * 100.0,
+            report.ignored_pixels,
+            report.antialiased_pixels,
+            check.baseline.width(),
+            check.baseline.height(),
+            check.actual.width(),
+            check.actual.height(),
+        );
+        if !report.regions.is_empty() {
+            let regions: Vec<String> = report.regions.iter().map(Region::to_string).collect();
+            let _ = writeln!(html, "<p>Changed regions: {}</p>", regions.join(", "));
+        }
+
+        html.push_str("<div class=\"images\">\n");
+        for (caption, image) in [("Baseline", &check.baseline), ("Actual", &check.actual), ("Difference", &check.diff)] {
+            let png = encode_png(image.as_raw(), image.width(), image.height())?;
+            let _ = writeln!(
+                html,
+                "<figure><img src=\"data:image/png;base64,{}\" alt=\"{}\"><figcaption>{}</figcaption></figure>",
+                BASE64.encode(png),
+                caption,
+                caption
+            );
+        }
+        html.push_str("</div>\n</section>\n");
+    }
+
+    let passed: Vec<String> = checks.iter().filter(|check| check.passed).map(|check| escape_html(&check.name)).collect();
+    if !passed.is_empty() {
+        let _ = writeln!(html, "<h2 class=\"pass\">Passed</h2>\n<p>{}</p>", passed.join(", "));
+    }
+    html.push_str("</body></html>\n");
+
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_atomic(path, CollisionStrategy::Overwrite, |file| Ok(file.write_all(html.as_bytes())?))?;
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
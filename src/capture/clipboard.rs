@@ -0,0 +1,46 @@
+use crate::{config::Config, error::ScreenshotError, utils::clipboard::read_image_from_clipboard};
+use crate::capture::{save_image_with_config, CaptureInfo, CapturedImage};
+use std::path::PathBuf;
+use log::{debug, info};
+
+pub fn capture(config: &Config) -> Result<PathBuf, ScreenshotError> {
+    let image = grab()?;
+    let path = save_image_with_config(
+        &image.data,
+        image.width,
+        image.height,
+        config,
+        "clipboard",
+        &image.info,
+    )?;
+
+    info!("Clipboard image saved: {}", path.display());
+    Ok(path)
+}
+
+/// Takes the image currently on the clipboard as if it had just been captured.
+pub fn grab() -> Result<CapturedImage, ScreenshotError> {
+    let (data, width, height) = read_image_from_clipboard()?;
+    clipboard_image(data, width, height)
+}
+
+/// Wraps RGBA8 pixels read from the clipboard as a capture.
+pub fn clipboard_image(data: Vec<u8>, width: u32, height: u32) -> Result<CapturedImage, ScreenshotError> {
+    debug!("Clipboard image: {}x{} pixels, {} bytes", width, height, data.len());
+
+    if width == 0 || height == 0 || data.len() != width as usize * height as usize * 4 {
+        return Err(ScreenshotError::ClipboardError(format!(
+            "Clipboard image is {} bytes, expected {}x{} RGBA", data.len(), width, height
+        )));
+    }
+
+    Ok(CapturedImage {
+        data,
+        width,
+        height,
+        info: CaptureInfo {
+            mode: "clipboard".to_string(),
+            ..CaptureInfo::default()
+        },
+    })
+}
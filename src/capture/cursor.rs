@@ -0,0 +1,265 @@
+use crate::error::ScreenshotError;
+use image::{Rgba, RgbaImage};
+use log::debug;
+
+/// Straight-alpha RGBA cursor sprite with its hotspot.
+#[derive(Debug, Clone)]
+pub struct CursorImage {
+    pub width: u32,
+    pub height: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Pointer position in global screen coordinates, plus its sprite when known.
+#[derive(Debug, Clone)]
+pub struct CursorState {
+    pub x: i32,
+    pub y: i32,
+    pub image: Option<CursorImage>,
+}
+
+pub trait CursorBackend {
+    fn cursor(&self) -> Result<CursorState, ScreenshotError>;
+}
+
+/// Reports a fixed cursor, for tests and headless use.
+#[derive(Debug, Clone)]
+pub struct MockCursorBackend {
+    pub state: CursorState,
+}
+
+impl MockCursorBackend {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self {
+            state: CursorState { x, y, image: None },
+        }
+    }
+}
+
+impl CursorBackend for MockCursorBackend {
+    fn cursor(&self) -> Result<CursorState, ScreenshotError> {
+        Ok(self.state.clone())
+    }
+}
+
+/// Reads the cursor through XFixes, falling back to the pointer position alone.
+pub struct XFixesCursorBackend;
+
+impl CursorBackend for XFixesCursorBackend {
+    fn cursor(&self) -> Result<CursorState, ScreenshotError> {
+        #[cfg(target_os = "linux")]
+        {
+            xfixes_cursor()
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err(ScreenshotError::PlatformNotSupported(
+                "Cursor capture is only supported on X11".to_string()
+            ))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn xfixes_cursor() -> Result<CursorState, ScreenshotError> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xfixes::ConnectionExt as _;
+    use x11rb::protocol::xproto::ConnectionExt as _;
+
+    let x11_error = |e: &dyn std::fmt::Display| {
+        ScreenshotError::CaptureFailed(format!("X11 cursor query failed: {}", e))
+    };
+
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| x11_error(&e))?;
+
+    let xfixes = conn.xfixes_query_version(4, 0)
+        .map_err(|e| x11_error(&e))?
+        .reply();
+
+    if xfixes.is_ok() {
+        let reply = conn.xfixes_get_cursor_image()
+            .map_err(|e| x11_error(&e))?
+            .reply()
+            .map_err(|e| x11_error(&e))?;
+
+        let pixels = reply.cursor_image
+            .iter()
+            .flat_map(|&argb| unpremultiply(argb))
+            .collect();
+
+        return Ok(CursorState {
+            x: reply.x as i32,
+            y: reply.y as i32,
+            image: Some(CursorImage {
+                width: reply.width as u32,
+                height: reply.height as u32,
+                xhot: reply.xhot as u32,
+                yhot: reply.yhot as u32,
+                pixels,
+            }),
+        });
+    }
+
+    debug!("XFixes unavailable, using pointer position with fallback sprite");
+
+    let root = conn.setup().roots[screen_num].root;
+    let pointer = conn.query_pointer(root)
+        .map_err(|e| x11_error(&e))?
+        .reply()
+        .map_err(|e| x11_error(&e))?;
+
+    Ok(CursorState {
+        x: pointer.root_x as i32,
+        y: pointer.root_y as i32,
+        image: None,
+    })
+}
+
+/// Converts XFixes premultiplied ARGB into straight RGBA bytes.
+#[cfg(target_os = "linux")]
+fn unpremultiply(argb: u32) -> [u8; 4] {
+    let a = (argb >> 24) & 0xff;
+    let channel = |shift: u32| {
+        let c = (argb >> shift) & 0xff;
+        (c * 255 + a / 2).checked_div(a).unwrap_or(0).min(255) as u8
+    };
+    [channel(16), channel(8), channel(0), a as u8]
+}
+
+const FALLBACK_ARROW: [&str; 19] = [
+    "X",
+    "XX",
+    "X.X",
+    "X..X",
+    "X...X",
+    "X....X",
+    "X.....X",
+    "X......X",
+    "X.......X",
+    "X........X",
+    "X.........X",
+    "X......XXXXX",
+    "X...X..X",
+    "X..XX..X",
+    "X.X  X..X",
+    "XX   X..X",
+    "X     X..X",
+    "      X..X",
+    "       XX",
+];
+
+/// Built-in arrow used when the platform cannot supply the real cursor sprite.
+pub fn fallback_arrow() -> CursorImage {
+    let width = FALLBACK_ARROW.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+    let height = FALLBACK_ARROW.len() as u32;
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+
+    for (y, row) in FALLBACK_ARROW.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let color = match c {
+                'X' => [0, 0, 0, 255],
+                '.' => [255, 255, 255, 255],
+                _ => continue,
+            };
+            let offset = (y * width as usize + x) * 4;
+            pixels[offset..offset + 4].copy_from_slice(&color);
+        }
+    }
+
+    CursorImage { width, height, xhot: 0, yhot: 0, pixels }
+}
+
+/// Draws the cursor onto a capture whose top-left corner sits at `origin` in
+/// global screen coordinates. `ratio` maps screen coordinates to image pixels.
+pub fn overlay_cursor(
+    image: &mut RgbaImage,
+    origin: (i32, i32),
+    ratio: f32,
+    backend: &dyn CursorBackend,
+    highlight: bool,
+) -> Result<(), ScreenshotError> {
+    let state = backend.cursor()?;
+
+    let x = ((state.x - origin.0) as f32 * ratio).round() as i32;
+    let y = ((state.y - origin.1) as f32 * ratio).round() as i32;
+
+    debug!("Compositing cursor at ({}, {}) in {}x{} image", x, y, image.width(), image.height());
+
+    if highlight {
+        let radius = 18.0 * ratio;
+        draw_halo(image, x, y, radius, 4.0 * ratio, Rgba([255, 200, 0, 160]));
+    }
+
+    let sprite = state.image.unwrap_or_else(fallback_arrow);
+    draw_sprite(image, &sprite, x - sprite.xhot as i32, y - sprite.yhot as i32);
+
+    Ok(())
+}
+
+/// Draws an anti-aliased ring centred on (`cx`, `cy`).
+pub fn draw_halo(image: &mut RgbaImage, cx: i32, cy: i32, radius: f32, thickness: f32, color: Rgba<u8>) {
+    let outer = radius + thickness / 2.0;
+    let reach = outer.ceil() as i32 + 1;
+
+    for dy in -reach..=reach {
+        for dx in -reach..=reach {
+            let (px, py) = (cx + dx, cy + dy);
+            if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+                continue;
+            }
+
+            let distance = ((dx * dx + dy * dy) as f32).sqrt();
+            let coverage = (thickness / 2.0 + 0.5 - (distance - radius).abs()).clamp(0.0, 1.0);
+            if coverage <= 0.0 {
+                continue;
+            }
+
+            let alpha = (color[3] as f32 * coverage).round() as u8;
+            blend(image.get_pixel_mut(px as u32, py as u32), [color[0], color[1], color[2], alpha]);
+        }
+    }
+}
+
+fn draw_sprite(image: &mut RgbaImage, sprite: &CursorImage, left: i32, top: i32) {
+    for sy in 0..sprite.height {
+        for sx in 0..sprite.width {
+            let (px, py) = (left + sx as i32, top + sy as i32);
+            if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+                continue;
+            }
+
+            let offset = ((sy * sprite.width + sx) * 4) as usize;
+            let Some(src) = sprite.pixels.get(offset..offset + 4) else {
+                return;
+            };
+            blend(image.get_pixel_mut(px as u32, py as u32), [src[0], src[1], src[2], src[3]]);
+        }
+    }
+}
+
+/// Source-over blend of a straight-alpha colour onto `dst`.
+fn blend(dst: &mut Rgba<u8>, src: [u8; 4]) {
+    let sa = src[3] as u32;
+    if sa == 0 {
+        return;
+    }
+    if sa == 255 {
+        *dst = Rgba(src);
+        return;
+    }
+
+    let da = dst[3] as u32;
+    let out_a = sa + da * (255 - sa) / 255;
+    if out_a == 0 {
+        return;
+    }
+
+    for i in 0..3 {
+        let c = (src[i] as u32 * sa + dst[i] as u32 * da * (255 - sa) / 255) / out_a;
+        dst[i] = c.min(255) as u8;
+    }
+    dst[3] = out_a as u8;
+}
@@ -0,0 +1,78 @@
+use crate::{config::Config, error::ScreenshotError, utils::path::sanitize_filename};
+use crate::capture::{save_image_with_config, CaptureInfo, CapturedImage};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use log::{debug, info};
+
+/// Runs an existing image file through the save pipeline.
+pub fn process(input: &Path, config: &Config) -> Result<PathBuf, ScreenshotError> {
+    process_as(input, &file_prefix(input), config)
+}
+
+/// Processes several files in parallel, returning one result per input in order.
+/// Inputs sharing a stem get `_2`, `_3`, ... appended so their outputs never
+/// share a name, whatever `on_collision` says.
+pub fn process_all(
+    inputs: &[PathBuf],
+    config: &Config,
+) -> Vec<(PathBuf, Result<PathBuf, ScreenshotError>)> {
+    let mut seen: HashMap<String, u32> = HashMap::new();
+    let prefixes: Vec<String> = inputs
+        .iter()
+        .map(|input| {
+            let prefix = file_prefix(input);
+            let count = seen.entry(prefix.to_lowercase()).or_insert(0);
+            *count += 1;
+            match *count {
+                1 => prefix,
+                n => format!("{}_{}", prefix, n),
+            }
+        })
+        .collect();
+
+    inputs
+        .par_iter()
+        .zip(prefixes.par_iter())
+        .map(|(input, prefix)| (input.clone(), process_as(input, prefix, config)))
+        .collect()
+}
+
+fn file_prefix(input: &Path) -> String {
+    input.file_stem()
+        .map(|stem| sanitize_filename(&stem.to_string_lossy()))
+        .unwrap_or_else(|| "file".to_string())
+}
+
+fn process_as(input: &Path, prefix: &str, config: &Config) -> Result<PathBuf, ScreenshotError> {
+    let image = grab(input)?;
+
+    let path = save_image_with_config(
+        &image.data,
+        image.width,
+        image.height,
+        config,
+        prefix,
+        &image.info,
+    )?;
+
+    info!("Processed {} -> {}", input.display(), path.display());
+    Ok(path)
+}
+
+/// Loads an image file as if it had just been captured.
+pub fn grab(input: &Path) -> Result<CapturedImage, ScreenshotError> {
+    debug!("Loading {}", input.display());
+
+    let img = image::open(input)?.to_rgba8();
+
+    Ok(CapturedImage {
+        width: img.width(),
+        height: img.height(),
+        data: img.into_raw(),
+        info: CaptureInfo {
+            mode: "file".to_string(),
+            ..CaptureInfo::default()
+        },
+    })
+}
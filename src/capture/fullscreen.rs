@@ -1,21 +1,40 @@
-use screenshots::Screen;
-use crate::{config::Config, error::ScreenshotError, capture::save_image_with_config};
-use std::path::PathBuf;
-use log::{debug, warn};
+use screenshots::{Image, Screen};
+use crate::{config::Config, error::ScreenshotError, capture::{save_image_with_config, CaptureInfo, CapturedImage}};
+use crate::utils::path::generate_filename;
+use crate::utils::template::FilenameValues;
+use crate::capture::cursor::{overlay_cursor, XFixesCursorBackend};
+use crate::capture::region::Region;
+use crate::capture::timelapse::slot_deadline;
+use image::RgbaImage;
+use std::borrow::Cow;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use chrono::Local;
+use log::{debug, info, warn};
 
 pub fn capture(screen_id: usize, config: &Config) -> Result<PathBuf, ScreenshotError> {
-    debug!("Starting fullscreen capture for screen {}", screen_id);
+    let image = grab(screen_id, config)?;
 
-    let screens = Screen::all()
-        .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+    save_image_with_config(
+        &image.data,
+        image.width,
+        image.height,
+        config,
+        "screenshot",
+        &image.info,
+    )
+}
 
-    if screens.is_empty() {
-        return Err(ScreenshotError::NoScreensFound);
-    }
+/// Captures a screen into memory, with the cursor composited if enabled.
+pub fn grab(screen_id: usize, config: &Config) -> Result<CapturedImage, ScreenshotError> {
+    debug!("Starting fullscreen capture for screen {}", screen_id);
 
-    let screen = screens.get(screen_id)
-        .ok_or(ScreenshotError::ScreenNotFound(screen_id))?;
+    grab_screen(&find_screen(screen_id)?, screen_id, config)
+}
 
+/// Like [`grab`], for a screen that was already looked up.
+pub fn grab_screen(screen: &Screen, screen_id: usize, config: &Config) -> Result<CapturedImage, ScreenshotError> {
     debug!("Capturing screen: {}x{}",
            screen.display_info.width,
            screen.display_info.height);
@@ -23,20 +42,171 @@ pub fn capture(screen_id: usize, config: &Config) -> Result<PathBuf, ScreenshotE
     let image = screen.capture()
         .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
 
+    let info = &screen.display_info;
+    Ok(finish_grab(image, (info.x, info.y), info.width, screen_id, info.scale_factor, config))
+}
+
+/// Captures `region` of a screen into memory; coordinates are relative to the screen.
+pub fn grab_region(
+    screen_id: usize,
+    region: &Region,
+    config: &Config,
+) -> Result<CapturedImage, ScreenshotError> {
+    debug!("Starting region capture {:?} on screen {}", region, screen_id);
+
+    grab_screen_region(&find_screen(screen_id)?, screen_id, region, config)
+}
+
+/// Like [`grab_region`], for a screen that was already looked up.
+pub fn grab_screen_region(
+    screen: &Screen,
+    screen_id: usize,
+    region: &Region,
+    config: &Config,
+) -> Result<CapturedImage, ScreenshotError> {
+    let info = &screen.display_info;
+
+    let (x, y, width, height) = region.clamp_to(info.width, info.height)
+        .ok_or_else(|| ScreenshotError::InvalidRegion(format!(
+            "{},{},{}x{} is outside screen {} ({}x{})",
+            region.x, region.y, region.width, region.height, screen_id, info.width, info.height
+        )))?;
+
+    let image = screen.capture_area(x as i32, y as i32, width, height)
+        .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+
+    let origin = (info.x + x as i32, info.y + y as i32);
+    Ok(finish_grab(image, origin, width, screen_id, info.scale_factor, config))
+}
+
+/// Captures `count` frames of a screen, `interval` apart (as fast as possible
+/// when zero), keeping them in memory and only encoding once the burst is
+/// over. Frames are saved in parallel as `<name>_01`, `<name>_02`, ... where
+/// `<name>` is the usual file name, so all of them share one timestamp.
+/// Copying to the clipboard is skipped for bursts.
+pub fn burst(
+    screen_id: usize,
+    count: u32,
+    interval: Duration,
+    config: &Config,
+) -> Result<Vec<PathBuf>, ScreenshotError> {
+    let screen = find_screen(screen_id)?;
+    let info = &screen.display_info;
+
+    let started = Instant::now();
+    let mut frames = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let Some(due) = slot_deadline(started, interval, i) else {
+            warn!("Burst frame {} is too far ahead to schedule, stopping", i + 1);
+            break;
+        };
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+
+        match screen.capture() {
+            Ok(image) => frames.push((
+                i + 1,
+                finish_grab(image, (info.x, info.y), info.width, screen_id, info.scale_factor, config),
+            )),
+            Err(e) => warn!("Burst frame {} failed: {}", i + 1, e),
+        }
+    }
+    debug!("Captured {} burst frames in {:?}", frames.len(), started.elapsed());
+
+    save_burst(&frames, count, config)
+}
+
+/// Saves burst frames, each numbered by its place in a burst of `count`, under
+/// one shared name. Frames that failed to save are skipped.
+pub fn save_burst(
+    frames: &[(u32, CapturedImage)],
+    count: u32,
+    config: &Config,
+) -> Result<Vec<PathBuf>, ScreenshotError> {
+    let Some((_, first)) = frames.first() else {
+        return Err(ScreenshotError::CaptureFailed("No burst frames captured".to_string()));
+    };
+    let values = FilenameValues {
+        mode: "fullscreen",
+        screen: first.info.screen,
+        width: first.width,
+        height: first.height,
+        ..FilenameValues::default()
+    };
+    let directory = config.output_directory_for("fullscreen", &Local::now())?;
+    let name = generate_filename(config, "screenshot", &values, &directory)?;
+    let stem = Path::new(&name).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or(name);
+    let digits = count.to_string().len().max(2);
+
+    let paths: Vec<PathBuf> = frames
+        .par_iter()
+        .filter_map(|(number, image)| {
+            let mut frame_config = config.clone();
+            frame_config.custom_filename = Some(format!("{}_{:0width$}", stem, number, width = digits));
+            frame_config.copy_to_clipboard = false;
+            frame_config.skip_duplicates = false;
+
+            save_image_with_config(&image.data, image.width, image.height, &frame_config, "", &image.info)
+                .map_err(|e| warn!("Failed to save burst frame {}: {}", number, e))
+                .ok()
+        })
+        .collect();
+
+    if paths.is_empty() {
+        return Err(ScreenshotError::CaptureFailed("No burst frames captured".to_string()));
+    }
+
+    info!("Saved {} of {} burst frames", paths.len(), count);
+    Ok(paths)
+}
+
+pub(crate) fn find_screen(screen_id: usize) -> Result<Screen, ScreenshotError> {
+    let mut screens = Screen::all()
+        .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+
+    if screens.is_empty() {
+        return Err(ScreenshotError::NoScreensFound);
+    }
+
+    if screen_id >= screens.len() {
+        return Err(ScreenshotError::ScreenNotFound(screen_id));
+    }
+
+    Ok(screens.swap_remove(screen_id))
+}
+
+/// Composites the cursor and wraps the pixels of a raw capture whose top-left
+/// corner is at `origin` and which spans `logical_width` screen units.
+fn finish_grab(
+    image: Image,
+    origin: (i32, i32),
+    logical_width: u32,
+    screen_id: usize,
+    scale_factor: f32,
+    config: &Config,
+) -> CapturedImage {
     let width = image.width();
     let height = image.height();
-    let rgba_data = image.rgba();
+    let rgba_data = match with_cursor(image.rgba(), width, height, origin, logical_width, config) {
+        Cow::Owned(composited) => composited,
+        Cow::Borrowed(_) => image.into(),
+    };
 
     debug!("Image captured: {}x{} pixels, {} bytes",
            width, height, rgba_data.len());
 
-    save_image_with_config(
-        rgba_data,
+    CapturedImage {
+        data: rgba_data,
         width,
         height,
-        config,
-        &format!("screen_{}", screen_id),
-    )
+        info: CaptureInfo {
+            mode: "fullscreen".to_string(),
+            screen: Some(screen_id),
+            scale_factor,
+            ..CaptureInfo::default()
+        },
+    }
 }
 
 pub fn capture_all_screens(config: &Config) -> Result<Vec<PathBuf>, ScreenshotError> {
@@ -52,12 +222,27 @@ pub fn capture_all_screens(config: &Config) -> Result<Vec<PathBuf>, ScreenshotEr
     for (i, screen) in screens.iter().enumerate() {
         match screen.capture() {
             Ok(image) => {
-                match save_image_with_config(
+                let info = &screen.display_info;
+                let rgba_data = with_cursor(
                     image.rgba(),
                     image.width(),
                     image.height(),
+                    (info.x, info.y),
+                    info.width,
+                    config,
+                );
+                match save_image_with_config(
+                    &rgba_data,
+                    image.width(),
+                    image.height(),
                     config,
                     &format!("screen_{}", i),
+                    &CaptureInfo {
+                        mode: "fullscreen".to_string(),
+                        screen: Some(i),
+                        scale_factor: screen.display_info.scale_factor,
+                        ..CaptureInfo::default()
+                    },
                 ) {
                     Ok(path) => paths.push(path),
                     Err(e) => warn!("Failed to save screen {}: {}", i, e),
@@ -73,3 +258,39 @@ pub fn capture_all_screens(config: &Config) -> Result<Vec<PathBuf>, ScreenshotEr
 
     Ok(paths)
 }
+
+/// Composites the pointer onto a capture when `cursor` or `highlight_cursor`
+/// is enabled. Failures are logged and the capture is kept as-is.
+fn with_cursor<'a>(
+    rgba_data: &'a [u8],
+    width: u32,
+    height: u32,
+    origin: (i32, i32),
+    logical_width: u32,
+    config: &Config,
+) -> Cow<'a, [u8]> {
+    if !config.cursor && !config.highlight_cursor {
+        return Cow::Borrowed(rgba_data);
+    }
+
+    let Some(mut buffer) = RgbaImage::from_raw(width, height, rgba_data.to_vec()) else {
+        warn!("Capture buffer does not match {}x{}, skipping cursor", width, height);
+        return Cow::Borrowed(rgba_data);
+    };
+
+    let ratio = if logical_width > 0 { width as f32 / logical_width as f32 } else { 1.0 };
+
+    match overlay_cursor(
+        &mut buffer,
+        origin,
+        ratio,
+        &XFixesCursorBackend,
+        config.highlight_cursor,
+    ) {
+        Ok(()) => Cow::Owned(buffer.into_raw()),
+        Err(e) => {
+            warn!("Failed to composite cursor: {}", e);
+            Cow::Borrowed(rgba_data)
+        },
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{error::ScreenshotError, hash::ImageHashes};
+use crate::utils::atomic::{write_atomic, CollisionStrategy};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// What is known about a saved capture, written next to it as `<stem>.meta.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureMetadata {
+    /// File name of the image, relative to the sidecar.
+    pub file: String,
+    pub mode: String,
+    pub screen: Option<usize>,
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub captured_at: DateTime<Local>,
+    pub thumbnail: Option<String>,
+    pub hashes: ImageHashes,
+}
+
+/// `<stem>.meta.json` next to `image_path`.
+pub fn sidecar_path(image_path: &Path) -> PathBuf {
+    let stem = image_path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "screenshot".to_string());
+    image_path.with_file_name(format!("{}.meta.json", stem))
+}
+
+/// Whether `path` is a sidecar or thumbnail written by the save pipeline
+/// rather than a capture of its own.
+pub fn is_companion_file(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    name.ends_with(".meta.json") || name.contains(".thumb.")
+}
+
+impl CaptureMetadata {
+    pub fn write(&self, image_path: &Path) -> Result<PathBuf, ScreenshotError> {
+        let path = sidecar_path(image_path);
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+        write_atomic(&path, CollisionStrategy::Overwrite, |file| Ok(file.write_all(&json)?))
+    }
+
+    /// Reads the sidecar of `image_path`, if there is one.
+    pub fn read(image_path: &Path) -> Result<Option<Self>, ScreenshotError> {
+        let path = sidecar_path(image_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        serde_json::from_slice(&std::fs::read(&path)?)
+            .map(Some)
+            .map_err(|e| ScreenshotError::SaveError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )))
+    }
+}
+
+/// The most recent capture, kept so that an identical next one can be skipped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastCapture {
+    pub path: PathBuf,
+    pub hashes: ImageHashes,
+}
+
+impl LastCapture {
+    fn state_path() -> Result<PathBuf, ScreenshotError> {
+        Ok(crate::config::Config::config_dir()?.join("last_capture.json"))
+    }
+
+    pub fn load() -> Option<Self> {
+        let path = Self::state_path().ok()?;
+        serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+    }
+
+    pub fn store(&self) -> Result<(), ScreenshotError> {
+        let path = Self::state_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_vec(self)
+            .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+        write_atomic(&path, CollisionStrategy::Overwrite, |file| Ok(file.write_all(&json)?))?;
+        Ok(())
+    }
+}
+
+/// The sidecar and thumbnails saved alongside `image_path` that exist on disk.
+pub fn companion_files(image_path: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Some(sidecar_path(image_path)).filter(|p| p.exists()).into_iter().collect();
+
+    let (Some(parent), Some(stem)) = (image_path.parent(), image_path.file_stem()) else {
+        return files;
+    };
+    let prefix = format!("{}.thumb.", stem.to_string_lossy());
+    let directory = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+    if let Ok(entries) = std::fs::read_dir(directory) {
+        files.extend(
+            entries
+                .flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+                .map(|entry| parent.join(entry.file_name())),
+        );
+    }
+    files
+}
@@ -1,11 +1,131 @@
+pub mod clipboard;
+pub mod cursor;
+pub mod file;
 pub mod fullscreen;
+pub mod metadata;
+pub mod record;
+pub mod region;
+pub mod scale;
+pub mod scroll;
 pub mod selection;
+pub mod source;
+pub mod timelapse;
+pub mod transform;
+pub mod watch;
+pub mod watermark;
 pub mod window;
+pub mod y4m;
 
-use crate::{config::Config, error::ScreenshotError, utils::path::generate_filename};
-use std::path::PathBuf;
-use image::{ImageFormat, ImageBuffer, ColorType};
-use log::{debug, info};
+use crate::{config::{Config, ThumbnailConfig}, error::ScreenshotError, utils::path::{generate_filename, validate_output_path}};
+use crate::utils::atomic::{write_atomic, CollisionStrategy};
+use crate::utils::template::{filename_tokens, FilenameValues};
+use crate::utils::clipboard::{offer_capture, ClipboardTarget};
+use crate::hash::ImageHashes;
+use crate::history::{History, HistoryEntry};
+use metadata::{CaptureMetadata, LastCapture};
+use std::borrow::Cow;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::Local;
+use uuid::Uuid;
+use image::{ImageFormat, ImageBuffer, Rgba, RgbaImage, DynamicImage};
+use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+use image::imageops::FilterType;
+use log::{debug, info, warn};
+
+/// Describes where a captured buffer came from.
+#[derive(Debug, Clone)]
+pub struct CaptureInfo {
+    pub mode: String,
+    pub screen: Option<usize>,
+    pub scale_factor: f32,
+    /// Title and class of the captured window, when known.
+    pub window_title: Option<String>,
+    pub window_class: Option<String>,
+}
+
+impl Default for CaptureInfo {
+    fn default() -> Self {
+        Self {
+            mode: "capture".to_string(),
+            screen: None,
+            scale_factor: 1.0,
+            window_title: None,
+            window_class: None,
+        }
+    }
+}
+
+/// A captured RGBA8 frame that has not been written anywhere yet.
+#[derive(Debug, Clone)]
+pub struct CapturedImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub info: CaptureInfo,
+}
+
+/// Pixels after scaling and overlays, ready to encode or copy.
+#[derive(Debug, Clone)]
+pub struct ProcessedImage<'a> {
+    pub data: Cow<'a, [u8]>,
+    pub width: u32,
+    pub height: u32,
+    pub dpi: u32,
+}
+
+/// Applies the configured redactions, trim, scale and watermark to a raw
+/// RGBA8 capture, in that order.
+pub fn process_image<'a>(
+    image_data: &'a [u8],
+    width: u32,
+    height: u32,
+    config: &Config,
+    info: &CaptureInfo,
+) -> Result<ProcessedImage<'a>, ScreenshotError> {
+    let mut image_data = Cow::Borrowed(image_data);
+    let (mut width, mut height) = (width, height);
+
+    if !config.redact.is_empty() || config.trim {
+        let mut buffer = RgbaImage::from_raw(width, height, image_data.into_owned())
+            .ok_or_else(dimension_mismatch)?;
+        transform::redact(&mut buffer, &config.redact);
+        if config.trim {
+            if let Some(trimmed) = transform::trim(&buffer) {
+                debug!("Trimmed {}x{} to {}x{}", width, height, trimmed.width(), trimmed.height());
+                buffer = trimmed;
+            }
+        }
+        (width, height) = buffer.dimensions();
+        image_data = Cow::Owned(buffer.into_raw());
+    }
+
+    let scale_mode = config.scale_mode()?;
+    let factor = scale_mode.resample_factor(info.scale_factor);
+    let dpi = scale_mode.output_dpi(info.scale_factor);
+    let (mut data, width, height) = match image_data {
+        Cow::Borrowed(borrowed) => scale::resample(borrowed, width, height, factor)?,
+        Cow::Owned(owned) => {
+            let (resampled, width, height) = scale::resample(&owned, width, height, factor)?;
+            let resampled = match resampled {
+                Cow::Owned(resampled) => Some(resampled),
+                Cow::Borrowed(_) => None,
+            };
+            (Cow::Owned(resampled.unwrap_or(owned)), width, height)
+        },
+    };
+
+    if let Some(ref wm) = config.watermark {
+        let mut buffer = RgbaImage::from_raw(width, height, data.into_owned())
+            .ok_or_else(dimension_mismatch)?;
+        watermark::apply(&mut buffer, wm, &Local::now())?;
+        data = Cow::Owned(buffer.into_raw());
+    }
+
+    debug!("Output {}x{} at {} DPI (display scale {})", width, height, dpi, info.scale_factor);
+
+    Ok(ProcessedImage { data, width, height, dpi })
+}
 
 pub fn save_image_with_config(
     image_data: &[u8],
@@ -13,31 +133,50 @@ pub fn save_image_with_config(
     height: u32,
     config: &Config,
     prefix: &str,
+    info: &CaptureInfo,
 ) -> Result<PathBuf, ScreenshotError> {
-    std::fs::create_dir_all(&config.output_directory)?;
+    let directory = config.output_directory_for(&info.mode, &Local::now())?;
+    let directory = validate_output_path(&directory, &config.allowed_roots)?;
 
-    let filename = generate_filename(config, prefix)?;
-    let path = config.output_directory.join(filename);
+    let format = parse_format(&config.default_format)?;
 
-    debug!("Saving image to: {}", path.display());
+    let ProcessedImage { data: image_data, width, height, dpi } =
+        process_image(image_data, width, height, config, info)?;
 
-    let format = match config.default_format.to_lowercase().as_str() {
-        "png" => ImageFormat::Png,
-        "jpg" | "jpeg" => ImageFormat::Jpeg,
-        "webp" => ImageFormat::WebP,
-        _ => return Err(ScreenshotError::InvalidFormat(config.default_format.clone())),
+    let named_by_hash = config.custom_filename.is_none() && filename_tokens(&config.filename_template).contains(&"hash8");
+    let hashes = if config.write_metadata || config.skip_duplicates || named_by_hash {
+        let view = ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(width, height, &image_data)
+            .ok_or_else(dimension_mismatch)?;
+        Some(ImageHashes::compute(&view))
+    } else {
+        None
     };
 
-    match format {
+    if let (true, Some(hashes)) = (config.skip_duplicates, hashes) {
+        if let Some(last) = LastCapture::load().filter(|last| last.path.exists()) {
+            if last.hashes.max_distance(&hashes) <= config.duplicate_distance {
+                return Err(ScreenshotError::DuplicateCapture(last.path));
+            }
+        }
+    }
+
+    let values = FilenameValues {
+        mode: &info.mode,
+        screen: info.screen,
+        width,
+        height,
+        window_title: info.window_title.as_deref(),
+        window_class: info.window_class.as_deref(),
+        hash: hashes.map(|hashes| hashes.phash),
+    };
+    let path = directory.join(generate_filename(config, prefix, &values, &directory)?);
+    let collision = config.collision_strategy()?;
+
+    debug!("Saving image to: {}", path.display());
+
+    let path = match format {
         ImageFormat::Png => {
-            image::save_buffer_with_format(
-                &path,
-                image_data,
-                width,
-                height,
-                ColorType::Rgba8,
-                ImageFormat::Png,
-            )?;
+            write_atomic(&path, collision, |file| encode_png(file, &image_data, width, height, dpi))?
         },
         ImageFormat::Jpeg => {
             let rgb_data: Vec<u8> = image_data
@@ -53,8 +192,15 @@ pub fn save_image_with_config(
                     ))
                 ))?;
 
-            let dynamic_img = image::DynamicImage::ImageRgb8(img);
-            dynamic_img.save_with_format(&path, ImageFormat::Jpeg)?;
+            write_atomic(&path, collision, |file| {
+                let mut encoder = JpegEncoder::new_with_quality(file, config.default_quality);
+                encoder.set_pixel_density(PixelDensity::dpi(dpi.min(u16::MAX as u32) as u16));
+                Ok(image::DynamicImage::ImageRgb8(img).write_with_encoder(encoder)?)
+            })?
+        },
+        ImageFormat::Avif => {
+            let avif = encode_avif(&image_data, width, height, config.default_quality)?;
+            write_atomic(&path, collision, |file| Ok(file.write_all(&avif)?))?
         },
         ImageFormat::WebP => {
             return Err(ScreenshotError::PlatformNotSupported(
@@ -62,8 +208,258 @@ pub fn save_image_with_config(
             ));
         },
         _ => unreachable!(),
-    }
+    };
 
     info!("Image saved successfully: {}", path.display());
+
+    let saved_at = Local::now();
+    let absolute_path = path.canonicalize().unwrap_or_else(|_| path.clone());
+
+    let mut thumbnail_name = None;
+    if let Some(ref thumbnail) = config.thumbnail {
+        match save_thumbnail(&path, &image_data, width, height, thumbnail) {
+            Ok(thumb_path) => {
+                info!("Thumbnail saved: {}", thumb_path.display());
+                thumbnail_name = thumb_path.file_name().map(|n| n.to_string_lossy().into_owned());
+            },
+            Err(e) => warn!("Failed to save thumbnail for {}: {}", path.display(), e),
+        }
+    }
+
+    if let Some(hashes) = hashes {
+        if config.write_metadata {
+            let metadata = CaptureMetadata {
+                file: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+                mode: info.mode.clone(),
+                screen: info.screen,
+                width,
+                height,
+                format: config.default_format.to_lowercase(),
+                captured_at: saved_at,
+                thumbnail: thumbnail_name,
+                hashes,
+            };
+            if let Err(e) = metadata.write(&path) {
+                warn!("Failed to write metadata for {}: {}", path.display(), e);
+            }
+        }
+        if config.skip_duplicates {
+            let last = LastCapture { path: absolute_path.clone(), hashes };
+            if let Err(e) = last.store() {
+                warn!("Failed to remember the last capture: {}", e);
+            }
+        }
+    }
+
+    if config.history {
+        let entry = HistoryEntry {
+            path: absolute_path,
+            captured_at: saved_at,
+            mode: info.mode.clone(),
+            screen: info.screen,
+            window: info.window_title.clone(),
+            width,
+            height,
+            tags: config.tags.clone(),
+        };
+        if let Err(e) = History::open().and_then(|history| history.append(&entry)) {
+            warn!("Failed to record {} in the history: {}", path.display(), e);
+        }
+    }
+
+    if config.copy_to_clipboard {
+        let copied = config.clipboard_target().and_then(|target| {
+            offer_capture(&image_data, width, height, Some(&path), target, config.clipboard_helper.as_deref())
+        });
+        match copied {
+            Ok(()) => info!("Image copied to clipboard"),
+            Err(e) => warn!("Failed to copy {} to the clipboard: {}", path.display(), e),
+        }
+    }
+
     Ok(path)
 }
+
+/// Temporary PNG path for an external tool to write into before the capture
+/// is run through the save pipeline.
+pub(crate) fn external_capture_path(mode: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("screenshot_{}_{}.png", mode, Uuid::new_v4()))
+}
+
+/// Loads a file written by an external capture tool and removes it.
+pub(crate) fn load_external_capture(
+    temp_path: &Path,
+    mode: &str,
+) -> Result<CapturedImage, ScreenshotError> {
+    let loaded = image::open(temp_path);
+    if let Err(e) = std::fs::remove_file(temp_path) {
+        warn!("Failed to remove temporary capture {}: {}", temp_path.display(), e);
+    }
+    let img = loaded?.to_rgba8();
+
+    Ok(CapturedImage {
+        width: img.width(),
+        height: img.height(),
+        data: img.into_raw(),
+        info: CaptureInfo {
+            mode: mode.to_string(),
+            ..CaptureInfo::default()
+        },
+    })
+}
+
+/// PNG text keyword holding the time a capture was written, as RFC 3339.
+pub const PNG_CREATION_TIME: &str = "Creation Time";
+
+/// Writes an RGBA8 PNG with a pHYs chunk carrying `dpi` and the current time
+/// as its creation time, replacing `path` once it is complete.
+pub(crate) fn write_png(
+    path: &Path,
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    dpi: u32,
+) -> Result<(), ScreenshotError> {
+    write_atomic(path, CollisionStrategy::Overwrite, |file| encode_png(file, image_data, width, height, dpi))?;
+    Ok(())
+}
+
+fn encode_png<W: std::io::Write>(
+    writer: W,
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    dpi: u32,
+) -> Result<(), ScreenshotError> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let pixels_per_meter = (dpi as f64 / 0.0254).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: pixels_per_meter,
+        yppu: pixels_per_meter,
+        unit: png::Unit::Meter,
+    }));
+    encoder.add_text_chunk(PNG_CREATION_TIME.to_string(), Local::now().to_rfc3339())
+        .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+
+    let mut writer = encoder.write_header()
+        .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+    writer.write_image_data(image_data)
+        .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+    writer.finish()
+        .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+
+    Ok(())
+}
+
+/// Encodes RGBA8 pixels as AVIF; `quality` is 1-100 like for JPEG.
+fn encode_avif(image_data: &[u8], width: u32, height: u32, quality: u8) -> Result<Vec<u8>, ScreenshotError> {
+    let pixels: Vec<ravif::RGBA8> = image_data
+        .chunks_exact(4)
+        .map(|px| ravif::RGBA8::new(px[0], px[1], px[2], px[3]))
+        .collect();
+    if pixels.len() != width as usize * height as usize {
+        return Err(dimension_mismatch());
+    }
+
+    let encoded = ravif::Encoder::new()
+        .with_quality(quality.clamp(1, 100) as f32)
+        .with_speed(6)
+        .encode_rgba(ravif::Img::new(pixels.as_slice(), width as usize, height as usize))
+        .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+    Ok(encoded.avif_file)
+}
+
+/// Processes a capture like `save_image_with_config` but only places it on
+/// the clipboard, without touching the disk. With no file, the `path` and
+/// `uri` targets are refused and `all` offers just the image.
+pub fn copy_image_with_config(
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    config: &Config,
+    info: &CaptureInfo,
+) -> Result<(), ScreenshotError> {
+    let target = config.clipboard_target()?;
+    if matches!(target, ClipboardTarget::Path | ClipboardTarget::Uri) {
+        return Err(ScreenshotError::ClipboardError(format!(
+            "Nothing is saved with --clipboard-only, so there is no {} to copy", target.as_str()
+        )));
+    }
+
+    let processed = process_image(image_data, width, height, config, info)?;
+    offer_capture(
+        &processed.data,
+        processed.width,
+        processed.height,
+        None,
+        target,
+        config.clipboard_helper.as_deref(),
+    )?;
+
+    info!("Image copied to clipboard ({}x{})", processed.width, processed.height);
+    Ok(())
+}
+
+pub(crate) fn dimension_mismatch() -> ScreenshotError {
+    ScreenshotError::ImageError(
+        image::ImageError::Parameter(image::error::ParameterError::from_kind(
+            image::error::ParameterErrorKind::DimensionMismatch
+        ))
+    )
+}
+
+fn parse_format(name: &str) -> Result<ImageFormat, ScreenshotError> {
+    match name.to_lowercase().as_str() {
+        "png" => Ok(ImageFormat::Png),
+        "jpg" | "jpeg" => Ok(ImageFormat::Jpeg),
+        "webp" => Ok(ImageFormat::WebP),
+        "avif" => Ok(ImageFormat::Avif),
+        _ => Err(ScreenshotError::InvalidFormat(name.to_string())),
+    }
+}
+
+/// Writes `<name>.thumb.<ext>` next to `path`, scaled so its longest edge is
+/// at most `thumbnail.max_edge`. Smaller images are kept at their own size.
+pub fn save_thumbnail(
+    path: &Path,
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    thumbnail: &ThumbnailConfig,
+) -> Result<PathBuf, ScreenshotError> {
+    let format = parse_format(&thumbnail.format)?;
+
+    let stem = path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "screenshot".to_string());
+    let extension = format.extensions_str().first().copied().unwrap_or("png");
+    let thumb_path = path.with_file_name(format!("{}.thumb.{}", stem, extension));
+
+    let buffer = RgbaImage::from_raw(width, height, image_data.to_vec())
+        .ok_or_else(dimension_mismatch)?;
+    let mut img = DynamicImage::ImageRgba8(buffer);
+
+    if width.max(height) > thumbnail.max_edge {
+        img = img.resize(thumbnail.max_edge, thumbnail.max_edge, FilterType::Lanczos3);
+    }
+
+    debug!("Saving {}x{} thumbnail to: {}", img.width(), img.height(), thumb_path.display());
+
+    match format {
+        ImageFormat::Png => {
+            write_atomic(&thumb_path, CollisionStrategy::Overwrite, |file| Ok(img.write_to(file, ImageFormat::Png)?))?;
+        },
+        ImageFormat::Jpeg => {
+            write_atomic(&thumb_path, CollisionStrategy::Overwrite, |file| {
+                let encoder = JpegEncoder::new_with_quality(file, thumbnail.quality);
+                Ok(DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(encoder)?)
+            })?;
+        },
+        _ => return Err(ScreenshotError::InvalidFormat(format!("{} (thumbnails are png or jpg)", thumbnail.format))),
+    }
+
+    Ok(thumb_path)
+}
@@ -0,0 +1,415 @@
+use crate::{config::Config, error::ScreenshotError, utils::path::{generate_filename, validate_output_path}};
+use crate::utils::atomic::{write_atomic, AtomicDir, AtomicFile, CollisionStrategy};
+use crate::utils::template::FilenameValues;
+use crate::capture::{dimension_mismatch, process_image, write_png, CapturedImage};
+use crate::capture::{source::FrameSource, timelapse::{sleep_until, slot_deadline}, y4m::Y4mWriter};
+use crate::diff::{changed_bounds, changed_pixels};
+use chrono::Local;
+use image::RgbaImage;
+use serde::Serialize;
+use std::io::{BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+
+/// Output path that stands for standard output.
+pub const STDOUT: &str = "-";
+
+/// NeuQuant speed used for frames with more than 256 colours (1 best, 30 fastest).
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+/// Most frame data GIF and APNG recordings keep in memory before encoding.
+/// Recording stops early once the next frame would not fit.
+pub const MAX_BUFFERED_BYTES: usize = 1 << 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Gif,
+    Apng,
+    Y4m,
+    Frames,
+}
+
+impl FromStr for RecordFormat {
+    type Err = ScreenshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gif" => Ok(Self::Gif),
+            "apng" => Ok(Self::Apng),
+            "y4m" => Ok(Self::Y4m),
+            "frames" => Ok(Self::Frames),
+            _ => Err(ScreenshotError::InvalidFormat(format!("{} (expected gif, apng, y4m or frames)", s))),
+        }
+    }
+}
+
+impl RecordFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+            Self::Y4m => "y4m",
+            Self::Frames => "png",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordOptions {
+    pub duration: Duration,
+    pub fps: u32,
+    pub format: RecordFormat,
+    /// Where to write the recording; `-` is standard output (Y4M only).
+    /// Defaults to a generated name in the output directory.
+    pub output: Option<PathBuf>,
+}
+
+/// A captured frame and how long it stays on screen.
+#[derive(Debug, Clone)]
+pub struct RecordedFrame {
+    pub image: CapturedImage,
+    /// Capture time relative to the start of the recording.
+    pub at: Duration,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RecordSummary {
+    pub path: PathBuf,
+    pub captured: u32,
+    pub duplicates: u32,
+    pub failed: u32,
+    pub frames: u32,
+}
+
+/// Grabs frames from `source` at `fps` for `duration` (or until `stop`) and
+/// hands each distinct frame to `sink` once its on-screen duration is known.
+/// A frame identical to the previous one only extends that frame's duration,
+/// and durations come from the actual capture times rather than the nominal rate.
+/// When `sink` breaks, the frame it was handed is dropped and recording ends.
+pub fn capture_frames(
+    source: &mut dyn FrameSource,
+    options: &RecordOptions,
+    stop: &AtomicBool,
+    summary: &mut RecordSummary,
+    sink: &mut dyn FnMut(RecordedFrame) -> Result<ControlFlow<()>, ScreenshotError>,
+) -> Result<(), ScreenshotError> {
+    if options.fps == 0 {
+        return Err(ScreenshotError::CaptureFailed("Frame rate must be at least 1".to_string()));
+    }
+
+    let interval = Duration::from_secs(1) / options.fps;
+    let start = Instant::now();
+    let mut pending: Option<RecordedFrame> = None;
+    let mut slot: u32 = 0;
+
+    loop {
+        let due = match slot_deadline(start, interval, slot) {
+            Some(due) if due - start < options.duration => due,
+            _ => break,
+        };
+        if !sleep_until(due, stop) {
+            break;
+        }
+        let at = start.elapsed();
+
+        match source.grab() {
+            Ok(image) => {
+                summary.captured += 1;
+                match pending.take() {
+                    Some(last) if (last.image.width, last.image.height) != (image.width, image.height) => {
+                        warn!("Frame size changed to {}x{}, dropping frame", image.width, image.height);
+                        summary.failed += 1;
+                        pending = Some(last);
+                    },
+                    Some(last) if changed_pixels(&last.image.data, &image.data, 0) == 0 => {
+                        summary.duplicates += 1;
+                        pending = Some(last);
+                    },
+                    last => {
+                        if let Some(mut last) = last {
+                            last.duration = at.saturating_sub(last.at);
+                            if sink(last)?.is_break() {
+                                break;
+                            }
+                            summary.frames += 1;
+                        }
+                        pending = Some(RecordedFrame { image, at, duration: interval });
+                    },
+                }
+            },
+            Err(e) => {
+                warn!("Frame capture failed, skipping: {}", e);
+                summary.failed += 1;
+            },
+        }
+
+        // Skip slots the capture fell behind on instead of bursting to catch up.
+        let next = (start.elapsed().as_secs_f64() / interval.as_secs_f64()).floor() as u32 + 1;
+        slot = next.max(slot.saturating_add(1));
+    }
+
+    if let Some(mut last) = pending {
+        // The last frame stays up until the end of its slot.
+        let end = start.elapsed().max(interval.saturating_mul(slot)).min(options.duration);
+        last.duration = end.saturating_sub(last.at).max(interval);
+        if sink(last)?.is_continue() {
+            summary.frames += 1;
+        }
+    }
+
+    debug!("Captured {} frames, {} distinct", summary.captured, summary.frames);
+    if summary.frames == 0 {
+        return Err(ScreenshotError::CaptureFailed("No frames were captured".to_string()));
+    }
+    Ok(())
+}
+
+/// Records `source` in the requested format. Frames go through the same
+/// redaction, scaling and watermarking as single captures; trimming is
+/// skipped so every frame keeps the same size. GIF and APNG are encoded once
+/// recording ends, with at most [`MAX_BUFFERED_BYTES`] of frames held until
+/// then; Y4M and frame sequences are written as frames arrive.
+pub fn record(
+    source: &mut dyn FrameSource,
+    options: &RecordOptions,
+    config: &Config,
+    stop: &AtomicBool,
+) -> Result<RecordSummary, ScreenshotError> {
+    let mut summary = RecordSummary::default();
+
+    let mut frame_config = config.clone();
+    frame_config.trim = false;
+    frame_config.default_format = options.format.extension().to_string();
+
+    let process = |frame: &RecordedFrame| -> Result<(RgbaImage, u32), ScreenshotError> {
+        let image = &frame.image;
+        let processed = process_image(&image.data, image.width, image.height, &frame_config, &image.info)?;
+        let buffer = RgbaImage::from_raw(processed.width, processed.height, processed.data.into_owned())
+            .ok_or_else(dimension_mismatch)?;
+        Ok((buffer, processed.dpi))
+    };
+
+    // Files named on the command line are replaced (frame directories never are),
+    // generated names follow `on_collision`
+    let collision = match options.output {
+        Some(_) => CollisionStrategy::Overwrite,
+        None => config.collision_strategy()?,
+    };
+    let mut path = match &options.output {
+        Some(path) if path.as_os_str() == STDOUT => path.clone(),
+        Some(path) => {
+            let name = path.file_name().ok_or_else(|| ScreenshotError::PermissionDenied(
+                format!("{} does not name a file", path.display())
+            ))?;
+            let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            validate_output_path(parent, &config.allowed_roots)?.join(name)
+        },
+        None => {
+            let directory = config.output_directory_for("recording", &Local::now())?;
+            let directory = validate_output_path(&directory, &config.allowed_roots)?;
+            let values = FilenameValues { mode: "recording", ..FilenameValues::default() };
+            let mut path = directory.join(generate_filename(&frame_config, "recording", &values, &directory)?);
+            if options.format == RecordFormat::Frames {
+                path.set_extension("");
+            }
+            path
+        },
+    };
+
+    if path.as_os_str() == STDOUT && options.format != RecordFormat::Y4m {
+        return Err(ScreenshotError::InvalidFormat(
+            "only y4m recordings can be written to standard output".to_string()
+        ));
+    }
+
+    info!("Recording {:?} at {} fps", options.duration, options.fps);
+
+    match options.format {
+        RecordFormat::Gif | RecordFormat::Apng => {
+            let mut processed = Vec::new();
+            let mut buffered = 0;
+            capture_frames(source, options, stop, &mut summary, &mut |frame| {
+                let image = process(&frame)?.0;
+                buffered += image.as_raw().len();
+                if buffered > MAX_BUFFERED_BYTES {
+                    warn!("Recording stopped after {} frames: GIF and APNG keep at most {} MiB of frames \
+                           in memory, use --format y4m or frames for longer recordings",
+                          processed.len(), MAX_BUFFERED_BYTES >> 20);
+                    return Ok(ControlFlow::Break(()));
+                }
+                processed.push((image, frame.duration));
+                Ok(ControlFlow::Continue(()))
+            })?;
+
+            path = write_atomic(&path, collision, |file| {
+                if options.format == RecordFormat::Gif {
+                    encode_gif(file, &processed)
+                } else {
+                    encode_apng(file, &processed)
+                }
+            })?;
+        },
+        RecordFormat::Y4m => {
+            let mut file = match path.as_os_str() == STDOUT {
+                true => None,
+                false => Some(AtomicFile::create(&path)?),
+            };
+            let output: Box<dyn Write> = match file.as_mut() {
+                Some(file) => Box::new(file),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            let mut writer = Y4mWriter::new(BufWriter::new(output), options.fps);
+            capture_frames(source, options, stop, &mut summary, &mut |frame| {
+                writer.write_frame(&process(&frame)?.0, frame.at + frame.duration)?;
+                Ok(ControlFlow::Continue(()))
+            })?;
+            writer.finish()?;
+            if let Some(file) = file {
+                path = file.commit(collision)?;
+            }
+        },
+        RecordFormat::Frames => {
+            let mut writer = FrameSequenceWriter::create(&path, options.fps)?;
+            capture_frames(source, options, stop, &mut summary, &mut |frame| {
+                let (image, dpi) = process(&frame)?;
+                writer.write_frame(&image, dpi, &frame)?;
+                Ok(ControlFlow::Continue(()))
+            })?;
+            path = writer.finish(collision)?;
+        },
+    }
+
+    info!("Recording saved: {}", path.display());
+    summary.path = path;
+    Ok(summary)
+}
+
+/// Writes frames as `frame_00001.png`, `frame_00002.png`, ... into a
+/// directory, plus a `manifest.json` with each frame's capture time and
+/// duration so external encoders can reproduce the real timing. Frames go to
+/// a temporary directory that only gets its name once the manifest is written.
+pub struct FrameSequenceWriter {
+    directory: AtomicDir,
+    manifest: FrameManifest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameManifest {
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<ManifestFrame>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestFrame {
+    pub file: String,
+    pub at_ms: u64,
+    pub duration_ms: u64,
+}
+
+impl FrameSequenceWriter {
+    pub fn create(directory: &Path, fps: u32) -> Result<Self, ScreenshotError> {
+        Ok(Self {
+            directory: AtomicDir::create(directory)?,
+            manifest: FrameManifest { fps, width: 0, height: 0, frames: Vec::new() },
+        })
+    }
+
+    pub fn write_frame(&mut self, image: &RgbaImage, dpi: u32, frame: &RecordedFrame) -> Result<(), ScreenshotError> {
+        let file = format!("frame_{:05}.png", self.manifest.frames.len() + 1);
+        write_png(&self.directory.temp_path().join(&file), image.as_raw(), image.width(), image.height(), dpi)?;
+
+        (self.manifest.width, self.manifest.height) = image.dimensions();
+        self.manifest.frames.push(ManifestFrame {
+            file,
+            at_ms: frame.at.as_millis() as u64,
+            duration_ms: frame.duration.as_millis() as u64,
+        });
+        Ok(())
+    }
+
+    /// Writes the manifest and moves the directory into place as `collision`
+    /// decides; an existing directory is never replaced. Returns its path.
+    pub fn finish(self, collision: CollisionStrategy) -> Result<PathBuf, ScreenshotError> {
+        let json = serde_json::to_vec_pretty(&self.manifest)
+            .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+        let manifest = self.directory.temp_path().join("manifest.json");
+        write_atomic(&manifest, CollisionStrategy::Overwrite, |file| Ok(file.write_all(&json)?))?;
+        self.directory.commit(collision)
+    }
+}
+
+/// Writes a looping GIF. After the first frame only the box that changed is
+/// stored, and each frame gets its own palette (exact when it has at most 256
+/// colours, quantized otherwise).
+pub fn encode_gif<W: Write>(writer: W, frames: &[(RgbaImage, Duration)]) -> Result<(), ScreenshotError> {
+    let gif_error = |e: gif::EncodingError| ScreenshotError::SaveError(std::io::Error::other(e));
+    let Some((first, _)) = frames.first() else {
+        return Err(ScreenshotError::CaptureFailed("No frames to encode".to_string()));
+    };
+    let (width, height) = gif_dimensions(first.width(), first.height())?;
+
+    let mut encoder = gif::Encoder::new(writer, width, height, &[]).map_err(gif_error)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(gif_error)?;
+
+    let mut previous: Option<&RgbaImage> = None;
+    for (image, duration) in frames {
+        let (x, y, mut pixels) = match previous {
+            Some(previous) => {
+                let (x, y, width, height) = changed_bounds(previous.as_raw(), image.as_raw(), image.width(), 0)
+                    .unwrap_or((0, 0, 1, 1));
+                (x, y, image::imageops::crop_imm(image, x, y, width, height).to_image())
+            },
+            None => (0, 0, image.clone()),
+        };
+
+        let (width, height) = gif_dimensions(pixels.width(), pixels.height())?;
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, GIF_QUANTIZE_SPEED);
+        frame.left = x as u16;
+        frame.top = y as u16;
+        frame.delay = (duration.as_millis() / 10).min(u16::MAX as u128) as u16;
+        frame.dispose = gif::DisposalMethod::Keep;
+        encoder.write_frame(&frame).map_err(gif_error)?;
+
+        previous = Some(image);
+    }
+
+    encoder.into_inner()?;
+    Ok(())
+}
+
+fn gif_dimensions(width: u32, height: u32) -> Result<(u16, u16), ScreenshotError> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(dimension_mismatch()),
+    }
+}
+
+/// Writes a looping APNG with millisecond frame delays.
+pub fn encode_apng<W: Write>(writer: W, frames: &[(RgbaImage, Duration)]) -> Result<(), ScreenshotError> {
+    let png_error = |e: png::EncodingError| ScreenshotError::SaveError(std::io::Error::other(e));
+    let Some((first, _)) = frames.first() else {
+        return Err(ScreenshotError::CaptureFailed("No frames to encode".to_string()));
+    };
+
+    let mut encoder = png::Encoder::new(writer, first.width(), first.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0).map_err(png_error)?;
+
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    for (image, duration) in frames {
+        let millis = duration.as_millis().min(u16::MAX as u128) as u16;
+        writer.set_frame_delay(millis, 1000).map_err(png_error)?;
+        writer.write_image_data(image.as_raw()).map_err(png_error)?;
+    }
+    writer.finish().map_err(png_error)?;
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::error::ScreenshotError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A rectangle in image pixels, written on the command line as `X,Y,WxH`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Region {
+    type Err = ScreenshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ScreenshotError::InvalidRegion(s.to_string());
+
+        let parts: Vec<&str> = s.split([',', 'x', 'X']).map(str::trim).collect();
+        if parts.len() != 4 {
+            return Err(invalid());
+        }
+
+        let region = Region {
+            x: parts[0].parse().map_err(|_| invalid())?,
+            y: parts[1].parse().map_err(|_| invalid())?,
+            width: parts[2].parse().map_err(|_| invalid())?,
+            height: parts[3].parse().map_err(|_| invalid())?,
+        };
+
+        if region.width == 0 || region.height == 0 {
+            return Err(invalid());
+        }
+
+        Ok(region)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}x{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl Region {
+    /// The part of this region that lies inside a `width` x `height` image,
+    /// as `(x, y, width, height)`, or `None` if they do not overlap.
+    pub fn clamp_to(&self, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        let left = self.x.max(0) as i64;
+        let top = self.y.max(0) as i64;
+        let right = (self.x as i64 + self.width as i64).min(width as i64);
+        let bottom = (self.y as i64 + self.height as i64).min(height as i64);
+
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some((left as u32, top as u32, (right - left) as u32, (bottom - top) as u32))
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{capture::dimension_mismatch, error::ScreenshotError};
+use image::{imageops::FilterType, RgbaImage};
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// DPI that one logical pixel corresponds to.
+pub const BASE_DPI: f32 = 96.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScaleMode {
+    Physical,
+    Logical,
+    Factor(f32),
+    Dpi(u32),
+}
+
+impl FromStr for ScaleMode {
+    type Err = ScreenshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_lowercase();
+        match value.as_str() {
+            "physical" => return Ok(Self::Physical),
+            "logical" => return Ok(Self::Logical),
+            _ => {},
+        }
+
+        if let Some(dpi) = value.strip_suffix("dpi") {
+            return match dpi.trim().parse::<u32>() {
+                Ok(dpi) if dpi > 0 => Ok(Self::Dpi(dpi)),
+                _ => Err(ScreenshotError::InvalidScale(s.to_string())),
+            };
+        }
+
+        match value.trim_end_matches('x').parse::<f32>() {
+            Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(Self::Factor(factor)),
+            _ => Err(ScreenshotError::InvalidScale(s.to_string())),
+        }
+    }
+}
+
+impl ScaleMode {
+    /// Resample factor for a capture taken on a display with `scale_factor`.
+    pub fn resample_factor(&self, scale_factor: f32) -> f32 {
+        let scale_factor = if scale_factor > 0.0 { scale_factor } else { 1.0 };
+        match *self {
+            Self::Physical => 1.0,
+            Self::Logical => 1.0 / scale_factor,
+            Self::Factor(factor) => factor,
+            Self::Dpi(dpi) => dpi as f32 / (BASE_DPI * scale_factor),
+        }
+    }
+
+    /// DPI of the output image, so that it prints at the on-screen logical size.
+    pub fn output_dpi(&self, scale_factor: f32) -> u32 {
+        let scale_factor = if scale_factor > 0.0 { scale_factor } else { 1.0 };
+        match *self {
+            Self::Dpi(dpi) => dpi,
+            _ => (BASE_DPI * scale_factor * self.resample_factor(scale_factor)).round() as u32,
+        }
+    }
+}
+
+/// Resamples an RGBA buffer by `factor`, borrowing the input when no resize is needed.
+pub fn resample(
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    factor: f32,
+) -> Result<(Cow<'_, [u8]>, u32, u32), ScreenshotError> {
+    let new_width = ((width as f32 * factor).round() as u32).max(1);
+    let new_height = ((height as f32 * factor).round() as u32).max(1);
+
+    if new_width == width && new_height == height {
+        return Ok((Cow::Borrowed(image_data), width, height));
+    }
+
+    let buffer = RgbaImage::from_raw(width, height, image_data.to_vec())
+        .ok_or_else(dimension_mismatch)?;
+
+    let resized = image::imageops::resize(&buffer, new_width, new_height, FilterType::Lanczos3);
+    Ok((Cow::Owned(resized.into_raw()), new_width, new_height))
+}
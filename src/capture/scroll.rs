@@ -0,0 +1,220 @@
+use crate::{config::Config, error::ScreenshotError};
+use crate::capture::{dimension_mismatch, save_image_with_config, source::FrameSource, timelapse::sleep_until, CaptureInfo};
+use image::RgbaImage;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+
+/// Share of overlapping rows that must match for an offset to be accepted.
+const MATCH_RATIO: f64 = 0.98;
+
+/// Fewest distinct rows two frames must share to be stitched together.
+const MIN_OVERLAP: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct ScrollOptions {
+    /// Time between frames.
+    pub interval: Duration,
+    /// Stop after this many frames.
+    pub max_frames: u32,
+    /// Stop once the content has not moved for this long.
+    pub idle: Duration,
+    /// Scroll by sending this many wheel clicks before each frame.
+    pub auto_scroll: Option<u32>,
+}
+
+/// One 64-bit FNV-1a hash per row of an RGBA8 image.
+pub fn row_hashes(image: &RgbaImage) -> Vec<u64> {
+    image
+        .as_raw()
+        .chunks_exact(image.width() as usize * 4)
+        .map(|row| {
+            row.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+            })
+        })
+        .collect()
+}
+
+/// How many rows the content moved up between two frames with the given row
+/// hashes, i.e. the `d` for which `previous[d..]` lines up with the top of
+/// `next`. `Some(0)` means nothing moved and `None` that no offset matched.
+///
+/// Offsets are ranked by how many distinct rows they match, so that runs of
+/// identical rows cannot pull the match to a wrong position; an overlap made
+/// only of such rows is too ambiguous to use.
+pub fn find_scroll_offset(previous: &[u64], next: &[u64]) -> Option<usize> {
+    let mut best: Option<(usize, usize)> = None;
+
+    for offset in 0..previous.len().saturating_sub(MIN_OVERLAP - 1) {
+        let overlap = (previous.len() - offset).min(next.len());
+        if overlap < MIN_OVERLAP {
+            break;
+        }
+
+        // Rows equal to the row above them (blank space, borders) still
+        // count towards the ratio but not towards the score.
+        let (mut matched, mut distinct) = (0, 0);
+        for i in 0..overlap {
+            if previous[offset + i] == next[i] {
+                matched += 1;
+                if i == 0 || next[i - 1] != next[i] {
+                    distinct += 1;
+                }
+            }
+        }
+
+        if distinct < MIN_OVERLAP || (matched as f64) < overlap as f64 * MATCH_RATIO {
+            continue;
+        }
+        if best.is_none_or(|(_, score)| distinct > score) {
+            best = Some((offset, distinct));
+        }
+    }
+
+    best.map(|(offset, _)| offset)
+}
+
+/// Builds a tall image out of frames of a region that scrolls downwards.
+pub struct Stitcher {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    last_hashes: Vec<u64>,
+}
+
+impl Stitcher {
+    pub fn new(first: &RgbaImage) -> Self {
+        Self {
+            width: first.width(),
+            height: first.height(),
+            data: first.as_raw().clone(),
+            last_hashes: row_hashes(first),
+        }
+    }
+
+    /// Appends the part of `frame` that scrolled into view and returns the
+    /// number of rows added. Frames that share no rows with the previous one
+    /// are appended whole.
+    pub fn push(&mut self, frame: &RgbaImage) -> Result<u32, ScreenshotError> {
+        if frame.width() != self.width {
+            return Err(dimension_mismatch());
+        }
+
+        let hashes = row_hashes(frame);
+        let new_rows = match find_scroll_offset(&self.last_hashes, &hashes) {
+            Some(offset) => {
+                let overlap = (self.last_hashes.len() - offset).min(hashes.len());
+                hashes.len() - overlap
+            },
+            None => {
+                warn!("No overlap with the previous frame, appending it whole");
+                hashes.len()
+            },
+        };
+
+        let row_bytes = self.width as usize * 4;
+        let start = (hashes.len() - new_rows) * row_bytes;
+        self.data.extend_from_slice(&frame.as_raw()[start..]);
+        self.height += new_rows as u32;
+        self.last_hashes = hashes;
+
+        Ok(new_rows as u32)
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn finish(self) -> RgbaImage {
+        RgbaImage::from_raw(self.width, self.height, self.data)
+            .expect("stitched buffer matches its dimensions")
+    }
+}
+
+/// Stitches frames taken top to bottom while scrolling into one image.
+pub fn stitch(frames: &[RgbaImage]) -> Result<RgbaImage, ScreenshotError> {
+    let (first, rest) = frames.split_first()
+        .ok_or_else(|| ScreenshotError::CaptureFailed("No frames to stitch".to_string()))?;
+
+    let mut stitcher = Stitcher::new(first);
+    for frame in rest {
+        stitcher.push(frame)?;
+    }
+    Ok(stitcher.finish())
+}
+
+/// Grabs frames from `source` while the content scrolls and saves the stitched
+/// result. Ends at `max_frames`, once nothing has moved for `idle`, or on `stop`.
+pub fn capture(
+    source: &mut dyn FrameSource,
+    options: &ScrollOptions,
+    config: &Config,
+    stop: &AtomicBool,
+) -> Result<PathBuf, ScreenshotError> {
+    let first = source.grab()?;
+    let info = CaptureInfo { mode: "scroll".to_string(), ..first.info };
+    let first = RgbaImage::from_raw(first.width, first.height, first.data).ok_or_else(dimension_mismatch)?;
+
+    let mut stitcher = Stitcher::new(&first);
+    let mut last_moved = Instant::now();
+
+    info!("Scrolling capture started, {}x{} per frame", first.width(), first.height());
+
+    for number in 2..=options.max_frames {
+        if let Some(clicks) = options.auto_scroll {
+            scroll_down(clicks)?;
+        }
+        let Some(next) = Instant::now().checked_add(options.interval) else {
+            warn!("The next frame is too far ahead to schedule, finishing");
+            break;
+        };
+        if !sleep_until(next, stop) {
+            break;
+        }
+
+        let frame = source.grab()?;
+        let frame = RgbaImage::from_raw(frame.width, frame.height, frame.data).ok_or_else(dimension_mismatch)?;
+
+        let added = stitcher.push(&frame)?;
+        debug!("Frame {} added {} rows ({} total)", number, added, stitcher.height());
+
+        if added > 0 {
+            last_moved = Instant::now();
+        } else if last_moved.elapsed() >= options.idle {
+            info!("Content stopped moving, finishing");
+            break;
+        }
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+
+    let image = stitcher.finish();
+    save_image_with_config(image.as_raw(), image.width(), image.height(), config, "scroll", &info)
+}
+
+/// Sends `clicks` wheel-down clicks to the window under the pointer.
+fn scroll_down(clicks: u32) -> Result<(), ScreenshotError> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::process::Command::new("xdotool")
+            .args(["click", "--repeat", &clicks.to_string(), "--delay", "10", "5"])
+            .status()
+            .map_err(|e| ScreenshotError::PlatformNotSupported(format!("xdotool is required for --auto-scroll: {}", e)))?;
+
+        if !status.success() {
+            return Err(ScreenshotError::CaptureFailed("xdotool failed to scroll".to_string()));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = clicks;
+        Err(ScreenshotError::PlatformNotSupported(
+            "--auto-scroll is only supported on Linux".to_string()
+        ))
+    }
+}
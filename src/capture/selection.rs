@@ -1,37 +1,52 @@
 use crate::{config::Config, error::ScreenshotError};
+use crate::capture::{external_capture_path, load_external_capture, save_image_with_config, CapturedImage};
 use std::path::PathBuf;
 use std::process::Command;
 use log::{debug, info};
 
 pub fn capture(config: &Config) -> Result<PathBuf, ScreenshotError> {
+    let image = grab()?;
+    let path = save_image_with_config(
+        &image.data,
+        image.width,
+        image.height,
+        config,
+        "selection",
+        &image.info,
+    )?;
+
+    info!("Selection capture saved: {}", path.display());
+    Ok(path)
+}
+
+/// Runs the interactive selection tool and loads its result into memory.
+pub fn grab() -> Result<CapturedImage, ScreenshotError> {
     debug!("Starting selection capture");
-    
-    std::fs::create_dir_all(&config.output_directory)?;
 
-    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("selection_{}.{}", timestamp, config.default_format);
-    let path = config.output_directory.join(filename);
+    let temp_path = external_capture_path("selection");
 
     let success = if cfg!(target_os = "linux") {
-        capture_linux_selection(&path)?
+        capture_linux_selection(&temp_path)?
     } else if cfg!(target_os = "macos") {
-        capture_macos_selection(&path)?
+        capture_macos_selection(&temp_path)?
     } else if cfg!(target_os = "windows") {
-        capture_windows_selection(&path)?
+        capture_windows_selection(&temp_path)?
     } else {
         return Err(ScreenshotError::PlatformNotSupported(
             "Selection capture not supported on this platform".to_string()
         ));
     };
 
-    if success && path.exists() {
-        info!("Selection capture saved: {}", path.display());
-        Ok(path)
+    if success && temp_path.exists() {
+        load_external_capture(&temp_path, "selection")
     } else {
+        let _ = std::fs::remove_file(&temp_path);
         Err(ScreenshotError::CaptureFailed("Selection capture failed".to_string()))
     }
 }
 
+#[allow(clippy::ptr_arg)]
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
 fn capture_linux_selection(path: &PathBuf) -> Result<bool, ScreenshotError> {
     #[cfg(target_os = "linux")]
     {
@@ -122,6 +137,8 @@ fn capture_linux_selection(path: &PathBuf) -> Result<bool, ScreenshotError> {
 //     }
 // }
 
+#[allow(clippy::ptr_arg)]
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
 fn capture_macos_selection(path: &PathBuf) -> Result<bool, ScreenshotError> {
     #[cfg(target_os = "macos")]
     {
@@ -174,6 +191,7 @@ fn capture_macos_selection(path: &PathBuf) -> Result<bool, ScreenshotError> {
 //     }
 // }
 
+#[allow(clippy::ptr_arg)]
 fn capture_windows_selection(_path: &PathBuf) -> Result<bool, ScreenshotError> {
     #[cfg(target_os = "windows")]
     {
@@ -0,0 +1,66 @@
+use crate::{config::Config, error::ScreenshotError};
+use crate::capture::{fullscreen, region::Region, CapturedImage};
+use screenshots::Screen;
+
+/// Something that produces successive frames, so polling loops can be driven
+/// by the real screen or by synthetic frames.
+pub trait FrameSource {
+    fn grab(&mut self) -> Result<CapturedImage, ScreenshotError>;
+}
+
+/// Grabs a screen, or a region of it, on every call. The screen is looked up
+/// once and again only after a failed capture, since listing the screens is
+/// slow compared to a frame interval.
+pub struct ScreenFrameSource<'a> {
+    pub screen: usize,
+    pub region: Option<Region>,
+    pub config: &'a Config,
+    handle: Option<Screen>,
+}
+
+impl<'a> ScreenFrameSource<'a> {
+    pub fn new(screen: usize, region: Option<Region>, config: &'a Config) -> Self {
+        Self { screen, region, config, handle: None }
+    }
+}
+
+impl FrameSource for ScreenFrameSource<'_> {
+    fn grab(&mut self) -> Result<CapturedImage, ScreenshotError> {
+        let screen = match self.handle {
+            Some(screen) => screen,
+            None => *self.handle.insert(fullscreen::find_screen(self.screen)?),
+        };
+
+        let result = match self.region {
+            Some(ref region) => fullscreen::grab_screen_region(&screen, self.screen, region, self.config),
+            None => fullscreen::grab_screen(&screen, self.screen, self.config),
+        };
+        if result.is_err() {
+            // The displays may have changed
+            self.handle = None;
+        }
+        result
+    }
+}
+
+/// Replays a fixed list of frames, repeating the last one once exhausted.
+pub struct MockFrameSource {
+    frames: Vec<CapturedImage>,
+    next: usize,
+}
+
+impl MockFrameSource {
+    pub fn new(frames: Vec<CapturedImage>) -> Self {
+        Self { frames, next: 0 }
+    }
+}
+
+impl FrameSource for MockFrameSource {
+    fn grab(&mut self) -> Result<CapturedImage, ScreenshotError> {
+        let index = self.next.min(self.frames.len().saturating_sub(1));
+        self.next += 1;
+        self.frames.get(index).cloned().ok_or_else(|| {
+            ScreenshotError::CaptureFailed("Mock frame source is empty".to_string())
+        })
+    }
+}
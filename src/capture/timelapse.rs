@@ -0,0 +1,157 @@
+use crate::{config::Config, error::ScreenshotError, utils::path::validate_output_path};
+use crate::capture::{fullscreen, region::Region, save_image_with_config, CapturedImage};
+use chrono::{DateTime, Local};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+
+/// Longest single sleep, so a stop request is noticed promptly.
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// Failed frames in a row after which a timelapse gives up.
+const MAX_CONSECUTIVE_FAILURES: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct TimelapseOptions {
+    pub interval: Duration,
+    pub count: Option<u32>,
+    pub until: Option<DateTime<Local>>,
+    pub screen: usize,
+    pub region: Option<Region>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimelapseSummary {
+    pub directory: PathBuf,
+    pub captured: u32,
+    pub failed: u32,
+    pub skipped: u32,
+}
+
+/// Grabs the configured screen or region.
+pub fn grab_frame(
+    screen: usize,
+    region: Option<&Region>,
+    config: &Config,
+) -> Result<CapturedImage, ScreenshotError> {
+    match region {
+        Some(region) => fullscreen::grab_region(screen, region, config),
+        None => fullscreen::grab(screen, config),
+    }
+}
+
+/// Captures a frame every `interval` into a dated subfolder of the output
+/// directory as `frame_00001`, `frame_00002`, ... until `count` frames have
+/// been saved, the `until` time, or `stop` is set. Failed frames do not count
+/// and are retried at the next slot, but too many failures in a row end the run.
+/// Frames are scheduled from the start time so slow captures do not accumulate
+/// drift; slots missed entirely are skipped.
+pub fn run(
+    options: &TimelapseOptions,
+    config: &Config,
+    stop: &AtomicBool,
+) -> Result<TimelapseSummary, ScreenshotError> {
+    let started_at = Local::now();
+    let directory = config.output_directory_for("timelapse", &started_at)?
+        .join(format!("timelapse_{}", started_at.format("%Y%m%d_%H%M%S")));
+    // Fail before the first frame rather than on every one
+    let directory = validate_output_path(&directory, &config.allowed_roots)?;
+
+    let mut frame_config = config.clone();
+    frame_config.output_directory = directory.clone();
+    frame_config.skip_duplicates = false;
+
+    info!("Timelapse every {:?} into {}", options.interval, directory.display());
+
+    let mut summary = TimelapseSummary { directory, captured: 0, failed: 0, skipped: 0 };
+    let start = Instant::now();
+    let mut slot: u32 = 0;
+    let mut failures_in_a_row: u32 = 0;
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            info!("Timelapse stopped");
+            break;
+        }
+        if options.count.is_some_and(|count| summary.captured >= count) {
+            break;
+        }
+        if options.until.is_some_and(|until| Local::now() >= until) {
+            break;
+        }
+
+        let Some(due) = slot_deadline(start, options.interval, slot) else {
+            info!("The next frame is too far ahead to schedule, stopping");
+            break;
+        };
+        if !sleep_until(due, stop) {
+            continue;
+        }
+        if options.until.is_some_and(|until| Local::now() >= until) {
+            break;
+        }
+
+        let sequence = summary.captured + 1;
+        frame_config.custom_filename = Some(format!("frame_{:05}", sequence));
+
+        match grab_frame(options.screen, options.region.as_ref(), &frame_config).and_then(|image| {
+            save_image_with_config(&image.data, image.width, image.height, &frame_config, "", &image.info)
+        }) {
+            Ok(path) => {
+                debug!("Frame {} saved: {}", sequence, path.display());
+                summary.captured += 1;
+                failures_in_a_row = 0;
+            },
+            Err(e) => {
+                warn!("Frame {} failed, retrying at the next slot: {}", sequence, e);
+                summary.failed += 1;
+                failures_in_a_row += 1;
+                if failures_in_a_row >= MAX_CONSECUTIVE_FAILURES {
+                    return Err(ScreenshotError::CaptureFailed(format!(
+                        "{} frames failed in a row, giving up after {} saved: {}",
+                        failures_in_a_row, summary.captured, e
+                    )));
+                }
+            },
+        }
+
+        let (next, missed) = next_slot(slot, start.elapsed(), options.interval);
+        if missed > 0 {
+            warn!("Capture fell behind, skipping {} slot(s)", missed);
+            summary.skipped += missed;
+        }
+        slot = next;
+    }
+
+    Ok(summary)
+}
+
+/// When `slot` is due, or `None` once that is further ahead than an
+/// `Instant` can represent.
+pub fn slot_deadline(start: Instant, interval: Duration, slot: u32) -> Option<Instant> {
+    interval.checked_mul(slot).and_then(|offset| start.checked_add(offset))
+}
+
+/// The slot to wait for after `slot`, given the time since the start: the first
+/// one still in the future, or simply the next one. Also returns how many
+/// slots were missed entirely.
+pub fn next_slot(slot: u32, elapsed: Duration, interval: Duration) -> (u32, u32) {
+    let due = (elapsed.as_secs_f64() / interval.as_secs_f64()).floor() as u32 + 1;
+    let next = due.max(slot.saturating_add(1));
+    (next, next.saturating_sub(slot + 1))
+}
+
+/// Sleeps until `deadline` in short steps. Returns `false` if `stop` was set.
+pub(crate) fn sleep_until(deadline: Instant, stop: &AtomicBool) -> bool {
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        std::thread::sleep((deadline - now).min(STOP_POLL));
+    }
+}
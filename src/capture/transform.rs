@@ -0,0 +1,49 @@
+use crate::capture::region::Region;
+use image::{Rgba, RgbaImage};
+
+/// Largest per-channel difference from the border colour still treated as border.
+const TRIM_TOLERANCE: u8 = 8;
+
+/// Paints each region solid black.
+pub fn redact(image: &mut RgbaImage, regions: &[Region]) {
+    for region in regions {
+        let Some((x, y, width, height)) = region.clamp_to(image.width(), image.height()) else {
+            continue;
+        };
+        for py in y..y + height {
+            for px in x..x + width {
+                image.put_pixel(px, py, Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
+}
+
+/// Crops away a uniform border matching the top-left pixel. Returns `None`
+/// when there is nothing to trim or the whole image is that colour.
+pub fn trim(image: &RgbaImage) -> Option<RgbaImage> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let border = *image.get_pixel(0, 0);
+    let is_border = |p: &Rgba<u8>| {
+        p.0.iter().zip(border.0.iter()).all(|(a, b)| a.abs_diff(*b) <= TRIM_TOLERANCE)
+    };
+
+    let (mut left, mut top, mut right, mut bottom) = (width, height, 0, 0);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if !is_border(pixel) {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+    }
+
+    if left > right || (left == 0 && top == 0 && right == width - 1 && bottom == height - 1) {
+        return None;
+    }
+
+    Some(image::imageops::crop_imm(image, left, top, right - left + 1, bottom - top + 1).to_image())
+}
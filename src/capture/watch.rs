@@ -0,0 +1,163 @@
+use crate::{config::Config, error::ScreenshotError};
+use crate::capture::{save_image_with_config, source::FrameSource, timelapse::sleep_until, CapturedImage};
+use crate::diff::{changed_fraction, DEFAULT_TOLERANCE};
+use chrono::Local;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// How often the screen is polled.
+    pub poll: Duration,
+    /// Fraction of pixels (0.0-1.0) that must differ from the last saved frame.
+    pub threshold: f64,
+    /// Shortest time between two saves.
+    pub min_interval: Duration,
+    /// How long a change must stay unchanged before it is saved.
+    pub settle: Duration,
+    /// Stop after this many saved frames.
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WatchSummary {
+    pub polled: u32,
+    pub captured: u32,
+    pub failed: u32,
+}
+
+/// A change that has been seen but not saved yet.
+struct Pending {
+    frame: CapturedImage,
+    since: Instant,
+}
+
+/// Decides which polled frames are worth saving. The first frame is always
+/// saved; after that a frame is saved once it differs from the last saved one
+/// by more than the threshold, has stopped changing for the settle period, and
+/// the minimum interval since the previous save has passed.
+pub struct ChangeDetector {
+    threshold: f64,
+    min_interval: Duration,
+    settle: Duration,
+    tolerance: u8,
+    last_saved: Option<(CapturedImage, Instant)>,
+    pending: Option<Pending>,
+}
+
+impl ChangeDetector {
+    pub fn new(threshold: f64, min_interval: Duration, settle: Duration) -> Self {
+        Self {
+            threshold,
+            min_interval,
+            settle,
+            tolerance: DEFAULT_TOLERANCE,
+            last_saved: None,
+            pending: None,
+        }
+    }
+
+    /// Per-channel difference ignored when comparing pixels.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    fn differs(&self, a: &CapturedImage, b: &CapturedImage) -> bool {
+        changed_fraction(&a.data, (a.width, a.height), &b.data, (b.width, b.height), self.tolerance)
+            > self.threshold
+    }
+
+    /// Feeds a frame polled at `now`, returning the frame to save, if any.
+    pub fn observe(&mut self, frame: CapturedImage, now: Instant) -> Option<CapturedImage> {
+        let Some((saved, saved_at)) = &self.last_saved else {
+            return Some(self.accept(frame, now));
+        };
+        let saved_at = *saved_at;
+
+        if !self.differs(&frame, saved) {
+            self.pending = None;
+            return None;
+        }
+
+        let since = match self.pending.take() {
+            Some(pending) if !self.differs(&frame, &pending.frame) => pending.since,
+            _ => now,
+        };
+
+        if now.duration_since(since) >= self.settle && now.duration_since(saved_at) >= self.min_interval {
+            return Some(self.accept(frame, now));
+        }
+
+        self.pending = Some(Pending { frame, since });
+        None
+    }
+
+    fn accept(&mut self, frame: CapturedImage, now: Instant) -> CapturedImage {
+        self.pending = None;
+        self.last_saved = Some((frame.clone(), now));
+        frame
+    }
+}
+
+/// Polls `source` every `poll` and saves frames picked by a [`ChangeDetector`]
+/// as `watch_<start>_0001`, `watch_<start>_0002`, ... until `count` frames
+/// are saved or `stop` is set.
+pub fn run(
+    source: &mut dyn FrameSource,
+    options: &WatchOptions,
+    config: &Config,
+    stop: &AtomicBool,
+) -> Result<WatchSummary, ScreenshotError> {
+    let started_at = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let mut frame_config = config.clone();
+    frame_config.skip_duplicates = false;
+    let mut detector = ChangeDetector::new(options.threshold, options.min_interval, options.settle);
+    let mut summary = WatchSummary::default();
+
+    info!("Watching for changes above {:.2}% every {:?}", options.threshold * 100.0, options.poll);
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            info!("Watch stopped");
+            break;
+        }
+        if options.count.is_some_and(|count| summary.captured >= count) {
+            break;
+        }
+
+        let tick = Instant::now();
+        summary.polled += 1;
+
+        match source.grab() {
+            Ok(frame) => {
+                if let Some(mut frame) = detector.observe(frame, Instant::now()) {
+                    let sequence = summary.captured + summary.failed + 1;
+                    frame_config.custom_filename = Some(format!("watch_{}_{:04}", started_at, sequence));
+                    frame.info.mode = "watch".to_string();
+
+                    match save_image_with_config(&frame.data, frame.width, frame.height, &frame_config, "", &frame.info) {
+                        Ok(path) => {
+                            debug!("Change {} saved: {}", sequence, path.display());
+                            summary.captured += 1;
+                        },
+                        Err(e) => {
+                            warn!("Saving change {} failed: {}", sequence, e);
+                            summary.failed += 1;
+                        },
+                    }
+                }
+            },
+            Err(e) => warn!("Poll failed, retrying: {}", e),
+        }
+
+        let Some(next) = tick.checked_add(options.poll) else {
+            info!("The next poll is too far ahead to schedule, stopping");
+            break;
+        };
+        sleep_until(next, stop);
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,279 @@
+use crate::{config::WatermarkConfig, error::ScreenshotError, utils::template::expand_text};
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use chrono::{DateTime, Local};
+use image::{imageops, Rgba, RgbaImage};
+use log::{debug, warn};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl FromStr for WatermarkPosition {
+    type Err = ScreenshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "top-left" => Ok(Self::TopLeft),
+            "top" => Ok(Self::Top),
+            "top-right" => Ok(Self::TopRight),
+            "left" => Ok(Self::Left),
+            "center" | "centre" => Ok(Self::Center),
+            "right" => Ok(Self::Right),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom" => Ok(Self::Bottom),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => Err(ScreenshotError::ConfigError(config::ConfigError::Message(
+                format!("Unknown watermark position: {}", s)
+            ))),
+        }
+    }
+}
+
+impl WatermarkPosition {
+    /// Top-left corner of a `stamp`-sized box inside `canvas`, `margin` pixels from the edges.
+    fn place(&self, canvas: (u32, u32), stamp: (u32, u32), margin: u32) -> (i64, i64) {
+        let (cw, ch) = (canvas.0 as i64, canvas.1 as i64);
+        let (sw, sh) = (stamp.0 as i64, stamp.1 as i64);
+        let m = margin as i64;
+
+        let x = match self {
+            Self::TopLeft | Self::Left | Self::BottomLeft => m,
+            Self::Top | Self::Center | Self::Bottom => (cw - sw) / 2,
+            Self::TopRight | Self::Right | Self::BottomRight => cw - sw - m,
+        };
+        let y = match self {
+            Self::TopLeft | Self::Top | Self::TopRight => m,
+            Self::Left | Self::Center | Self::Right => (ch - sh) / 2,
+            Self::BottomLeft | Self::Bottom | Self::BottomRight => ch - sh - m,
+        };
+
+        (x, y)
+    }
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(value: &str) -> Result<Rgba<u8>, ScreenshotError> {
+    let invalid = || ScreenshotError::ConfigError(config::ConfigError::Message(
+        format!("Invalid colour: {} (expected #rrggbb or #rrggbbaa)", value)
+    ));
+
+    let hex = value.trim().trim_start_matches('#');
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, alpha]))
+}
+
+/// Stamps the configured watermark onto `image`.
+pub fn apply(
+    image: &mut RgbaImage,
+    watermark: &WatermarkConfig,
+    now: &DateTime<Local>,
+) -> Result<(), ScreenshotError> {
+    let mut stamp = build_stamp(watermark, now)?;
+
+    if watermark.opacity < 1.0 {
+        for pixel in stamp.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * watermark.opacity).round() as u8;
+        }
+    }
+
+    let canvas = image.dimensions();
+    let size = stamp.dimensions();
+
+    if watermark.tile {
+        let step_x = (size.0 + watermark.margin.max(1)) as usize;
+        let step_y = (size.1 + watermark.margin.max(1)) as usize;
+        for y in (watermark.margin..canvas.1).step_by(step_y) {
+            for x in (watermark.margin..canvas.0).step_by(step_x) {
+                imageops::overlay(image, &stamp, x as i64, y as i64);
+            }
+        }
+    } else {
+        let position = WatermarkPosition::from_str(&watermark.position)?;
+        let (x, y) = position.place(canvas, size, watermark.margin);
+        imageops::overlay(image, &stamp, x, y);
+    }
+
+    debug!("Applied {}x{} watermark (tiled: {})", size.0, size.1, watermark.tile);
+    Ok(())
+}
+
+/// Renders the text and/or image parts of the watermark into one stamp.
+fn build_stamp(watermark: &WatermarkConfig, now: &DateTime<Local>) -> Result<RgbaImage, ScreenshotError> {
+    let logo = match watermark.image {
+        Some(ref path) => Some(image::open(path)?.to_rgba8()),
+        None => None,
+    };
+
+    let text = match watermark.text {
+        Some(ref template) => {
+            let color = parse_color(&watermark.color)?;
+            let text = expand_text(template, now)?;
+            Some(render_text(&text, watermark, color))
+        },
+        None => None,
+    };
+
+    match (logo, text) {
+        (Some(logo), Some(text)) => {
+            // Logo on top, text centred beneath it.
+            let gap = watermark.text_size / 4;
+            let width = logo.width().max(text.width());
+            let height = logo.height() + gap + text.height();
+            let mut stamp = RgbaImage::new(width, height);
+            imageops::overlay(&mut stamp, &logo, ((width - logo.width()) / 2) as i64, 0);
+            imageops::overlay(
+                &mut stamp,
+                &text,
+                ((width - text.width()) / 2) as i64,
+                (logo.height() + gap) as i64,
+            );
+            Ok(stamp)
+        },
+        (Some(logo), None) => Ok(logo),
+        (None, Some(text)) => Ok(text),
+        (None, None) => Err(ScreenshotError::ConfigError(config::ConfigError::Message(
+            "watermark needs either text or image".to_string()
+        ))),
+    }
+}
+
+fn render_text(text: &str, watermark: &WatermarkConfig, color: Rgba<u8>) -> RgbaImage {
+    if let Some(ref font_path) = watermark.font {
+        match std::fs::read(font_path).map(FontVec::try_from_vec) {
+            Ok(Ok(font)) => return render_font_text(&font, text, watermark.text_size, color),
+            Ok(Err(e)) => warn!("Invalid font {}: {}, using built-in font", font_path.display(), e),
+            Err(e) => warn!("Cannot read font {}: {}, using built-in font", font_path.display(), e),
+        }
+    }
+
+    render_bitmap_text(text, watermark.text_size, color)
+}
+
+fn render_font_text(font: &FontVec, text: &str, size: u32, color: Rgba<u8>) -> RgbaImage {
+    let scaled = font.as_scaled(PxScale::from(size as f32));
+
+    let mut glyphs = Vec::new();
+    let mut caret = 0.0f32;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = previous {
+            caret += scaled.kern(prev, id);
+        }
+        glyphs.push(id.with_scale_and_position(scaled.scale(), ab_glyph::point(caret, scaled.ascent())));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+    }
+
+    let width = caret.ceil().max(1.0) as u32;
+    let height = (scaled.ascent() - scaled.descent()).ceil().max(1.0) as u32;
+    let mut stamp = RgbaImage::new(width, height);
+
+    for glyph in glyphs {
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let x = bounds.min.x as i32 + gx as i32;
+            let y = bounds.min.y as i32 + gy as i32;
+            if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                return;
+            }
+            let alpha = (color[3] as f32 * coverage.clamp(0.0, 1.0)).round() as u8;
+            let pixel = stamp.get_pixel_mut(x as u32, y as u32);
+            if alpha > pixel[3] {
+                *pixel = Rgba([color[0], color[1], color[2], alpha]);
+            }
+        });
+    }
+
+    stamp
+}
+
+/// Renders with the built-in 5x7 font, scaled to roughly `size` pixels tall.
+fn render_bitmap_text(text: &str, size: u32, color: Rgba<u8>) -> RgbaImage {
+    const CELL_W: u32 = 6;
+    const CELL_H: u32 = 8;
+
+    let scale = (size / CELL_H).max(1);
+    let count = text.chars().count().max(1) as u32;
+    let mut stamp = RgbaImage::new(count * CELL_W * scale, CELL_H * scale);
+
+    for (i, c) in text.chars().enumerate() {
+        let columns = glyph(c);
+        for (col, bits) in columns.iter().enumerate() {
+            for row in 0..7 {
+                if bits & (1 << row) == 0 {
+                    continue;
+                }
+                let x0 = (i as u32 * CELL_W + col as u32) * scale;
+                let y0 = row * scale;
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        stamp.put_pixel(x0 + dx, y0 + dy, color);
+                    }
+                }
+            }
+        }
+    }
+
+    stamp
+}
+
+/// Column-major 5x7 glyphs for printable ASCII; bit 0 is the top row.
+const FONT_5X7: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5F, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], [0x24, 0x2A, 0x7F, 0x2A, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x56, 0x20, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00], [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00], [0x14, 0x08, 0x3E, 0x08, 0x14], [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02], [0x3E, 0x51, 0x49, 0x45, 0x3E], [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4B, 0x31], [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39], [0x3C, 0x4A, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1E], [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00], [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06], [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], [0x7F, 0x49, 0x49, 0x49, 0x36], [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], [0x7F, 0x49, 0x49, 0x49, 0x41], [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], [0x7F, 0x08, 0x08, 0x08, 0x7F], [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01], [0x7F, 0x08, 0x14, 0x22, 0x41], [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], [0x7F, 0x04, 0x08, 0x10, 0x7F], [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06], [0x3E, 0x41, 0x51, 0x21, 0x5E], [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31], [0x01, 0x01, 0x7F, 0x01, 0x01], [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], [0x3F, 0x40, 0x38, 0x40, 0x3F], [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07], [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7F, 0x00], [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40], [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20], [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18], [0x08, 0x7E, 0x09, 0x01, 0x02], [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7D, 0x40, 0x00], [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00], [0x00, 0x41, 0x7F, 0x40, 0x00], [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38], [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C], [0x7C, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20], [0x3C, 0x40, 0x40, 0x20, 0x7C], [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], [0x44, 0x28, 0x10, 0x28, 0x44], [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00], [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00], [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+fn glyph(c: char) -> &'static [u8; 5] {
+    let index = (c as u32).wrapping_sub(0x20) as usize;
+    FONT_5X7.get(index).unwrap_or(&FONT_5X7[b'?' as usize - 0x20])
+}
@@ -1,37 +1,81 @@
 use crate::{config::Config, error::ScreenshotError};
+use crate::capture::{external_capture_path, load_external_capture, save_image_with_config, CapturedImage};
 use std::path::PathBuf;
 use std::process::Command;
 use log::{debug, info};
 
 pub fn capture(config: &Config) -> Result<PathBuf, ScreenshotError> {
+    let image = grab()?;
+    let path = save_image_with_config(
+        &image.data,
+        image.width,
+        image.height,
+        config,
+        "window",
+        &image.info,
+    )?;
+
+    info!("Window capture saved: {}", path.display());
+    Ok(path)
+}
+
+/// Runs the interactive window tool and loads its result into memory.
+pub fn grab() -> Result<CapturedImage, ScreenshotError> {
     debug!("Starting window capture");
-    
-    std::fs::create_dir_all(&config.output_directory)?;
 
-    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("window_{}.{}", timestamp, config.default_format);
-    let path = config.output_directory.join(filename);
+    let temp_path = external_capture_path("window");
+    let (window_title, window_class) = active_window();
 
     let success = if cfg!(target_os = "linux") {
-        capture_linux_window(&path)?
+        capture_linux_window(&temp_path)?
     } else if cfg!(target_os = "macos") {
-        capture_macos_window(&path)?
+        capture_macos_window(&temp_path)?
     } else if cfg!(target_os = "windows") {
-        capture_windows_window(&path)?
+        capture_windows_window(&temp_path)?
     } else {
         return Err(ScreenshotError::PlatformNotSupported(
             "Window capture not supported on this platform".to_string()
         ));
     };
 
-    if success && path.exists() {
-        info!("Window capture saved: {}", path.display());
-        Ok(path)
+    if success && temp_path.exists() {
+        let mut image = load_external_capture(&temp_path, "window")?;
+        image.info.window_title = window_title;
+        image.info.window_class = window_class;
+        Ok(image)
     } else {
+        let _ = std::fs::remove_file(&temp_path);
         Err(ScreenshotError::CaptureFailed("Window capture failed".to_string()))
     }
 }
 
+/// Title and class of the focused window, which is the one the window tools
+/// capture. Either is `None` when it cannot be found out.
+fn active_window() -> (Option<String>, Option<String>) {
+    #[cfg(target_os = "linux")]
+    {
+        let query = |command: &str| {
+            Command::new("xdotool")
+                .args(["getactivewindow", command])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let window = (query("getwindowname"), query("getwindowclassname"));
+        debug!("Active window: {:?}", window);
+        window
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        (None, None)
+    }
+}
+
+#[allow(clippy::ptr_arg)]
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
 fn capture_linux_window(path: &PathBuf) -> Result<bool, ScreenshotError> {
     #[cfg(target_os = "linux")]
     {
@@ -57,6 +101,8 @@ fn capture_linux_window(path: &PathBuf) -> Result<bool, ScreenshotError> {
     }
 }
 
+#[allow(clippy::ptr_arg)]
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
 fn capture_macos_window(path: &PathBuf) -> Result<bool, ScreenshotError> {
     #[cfg(target_os = "macos")]
     {
@@ -82,6 +128,7 @@ fn capture_macos_window(path: &PathBuf) -> Result<bool, ScreenshotError> {
     }
 }
 
+#[allow(clippy::ptr_arg)]
 fn capture_windows_window(_path: &PathBuf) -> Result<bool, ScreenshotError> {
     #[cfg(target_os = "windows")]
     {
@@ -0,0 +1,96 @@
+use crate::error::ScreenshotError;
+use image::RgbaImage;
+use std::io::Write;
+use std::time::Duration;
+
+/// Streams RGBA frames as a constant-rate YUV4MPEG2 (4:2:0, BT.601) video.
+///
+/// Y4M has no per-frame timestamps, so each frame is repeated or dropped to
+/// land on the frame grid at the time it was actually shown. Slow captures
+/// then stretch in place instead of shifting everything after them.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    fps: u32,
+    size: Option<(u32, u32)>,
+    written: u64,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(writer: W, fps: u32) -> Self {
+        Self { writer, fps: fps.max(1), size: None, written: 0, buffer: Vec::new() }
+    }
+
+    /// Number of frames written to the stream so far.
+    pub fn frames_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Writes `image` as many times as needed to fill the grid up to `until`,
+    /// the time the next frame replaces it.
+    pub fn write_frame(&mut self, image: &RgbaImage, until: Duration) -> Result<(), ScreenshotError> {
+        let (width, height) = image.dimensions();
+        match self.size {
+            None => {
+                writeln!(self.writer, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg", width, height, self.fps)?;
+                self.size = Some((width, height));
+            },
+            Some(size) if size != (width, height) => {
+                return Err(ScreenshotError::CaptureFailed(format!(
+                    "Frame size changed from {}x{} to {}x{}", size.0, size.1, width, height
+                )));
+            },
+            Some(_) => {},
+        }
+
+        let target = (until.as_secs_f64() * self.fps as f64).round() as u64;
+        if target <= self.written {
+            return Ok(());
+        }
+
+        rgba_to_i420(image, &mut self.buffer);
+        for _ in self.written..target {
+            self.writer.write_all(b"FRAME\n")?;
+            self.writer.write_all(&self.buffer)?;
+        }
+        self.written = target;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), ScreenshotError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Converts RGBA to planar Y, U, V with chroma averaged over 2x2 blocks.
+/// Odd edges get a half-size chroma sample of their own.
+pub fn rgba_to_i420(image: &RgbaImage, out: &mut Vec<u8>) {
+    let (width, height) = image.dimensions();
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+
+    out.clear();
+    out.reserve((width * height + 2 * chroma_width * chroma_height) as usize);
+
+    for pixel in image.pixels() {
+        let [r, g, b, _] = pixel.0.map(f32::from);
+        out.push((16.0 + 0.257 * r + 0.504 * g + 0.098 * b).round() as u8);
+    }
+
+    let mut v_plane = Vec::with_capacity((chroma_width * chroma_height) as usize);
+    for cy in 0..chroma_height {
+        for cx in 0..chroma_width {
+            let (mut r, mut g, mut b, mut n) = (0.0, 0.0, 0.0, 0.0);
+            for y in cy * 2..(cy * 2 + 2).min(height) {
+                for x in cx * 2..(cx * 2 + 2).min(width) {
+                    let [pr, pg, pb, _] = image.get_pixel(x, y).0.map(f32::from);
+                    (r, g, b, n) = (r + pr, g + pg, b + pb, n + 1.0);
+                }
+            }
+            let (r, g, b) = (r / n, g / n, b / n);
+            out.push((128.0 - 0.148 * r - 0.291 * g + 0.439 * b).round() as u8);
+            v_plane.push((128.0 + 0.439 * r - 0.368 * g - 0.071 * b).round() as u8);
+        }
+    }
+    out.extend_from_slice(&v_plane);
+}
@@ -1,6 +1,18 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use crate::error::ScreenshotError;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::{capture::{region::Region, scale::ScaleMode}, error::ScreenshotError};
+use crate::capture::watermark::{parse_color, WatermarkPosition};
+use crate::config_file::{failing_key, locate};
+use crate::utils::atomic::{write_atomic, CollisionStrategy};
+use crate::utils::clipboard::ClipboardTarget;
+use crate::utils::path::{check_writable, output_root, resolve_output_directory, validate_output_path};
+use crate::utils::size::parse_size;
+use crate::utils::template::validate_filename_template;
+use chrono::{DateTime, Local};
+use std::io::Write;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -11,51 +23,383 @@ pub struct Config {
     pub custom_filename: Option<String>,
     pub auto_open: bool,
     pub cleanup_after_days: Option<u32>,
+    pub scale: Option<String>,
+    #[serde(default)]
+    pub cursor: bool,
+    #[serde(default)]
+    pub highlight_cursor: bool,
+    #[serde(default)]
+    pub trim: bool,
+    #[serde(default)]
+    pub redact: Vec<Region>,
+    #[serde(default)]
+    pub copy_to_clipboard: bool,
+    pub clipboard_target: Option<String>,
+    /// Executable re-run as a detached clipboard server; set by the CLI at runtime.
+    #[serde(skip)]
+    pub clipboard_helper: Option<PathBuf>,
+    pub thumbnail: Option<ThumbnailConfig>,
+    pub watermark: Option<WatermarkConfig>,
+    /// Write a `<stem>.meta.json` sidecar with size, time and image hashes.
+    #[serde(default)]
+    pub write_metadata: bool,
+    /// Do not save a capture that matches the previous one.
+    #[serde(default)]
+    pub skip_duplicates: bool,
+    /// Largest hash distance, in bits, still counted as the same capture.
+    #[serde(default)]
+    pub duplicate_distance: u32,
+    /// Record every saved capture in the history index.
+    #[serde(default = "default_true")]
+    pub history: bool,
+    /// Tags stored with each capture in the history.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Largest total size of captures kept in `output_directory`, e.g. `2GB`.
+    pub cleanup_max_size: Option<String>,
+    /// Most captures kept in `output_directory`.
+    pub cleanup_max_files: Option<usize>,
+    /// Move expired captures here instead of deleting them.
+    pub cleanup_archive: Option<PathBuf>,
+    /// Apply the cleanup limits after each capture.
+    #[serde(default)]
+    pub auto_clean: bool,
+    /// What to do when a file name is taken: counter, uuid, overwrite or error.
+    pub on_collision: Option<String>,
+    /// When set, output may only be written below these directories.
+    #[serde(default)]
+    pub allowed_roots: Vec<PathBuf>,
 }
 
-impl Default for Config {
+fn default_true() -> bool {
+    true
+}
+
+/// Built-in defaults for everything except `output_directory`, which depends
+/// on the platform.
+pub(crate) const DEFAULT_CONFIG: &str = include_str!("../config/default.toml");
+
+/// Prefix of environment variables that override settings.
+pub const ENV_PREFIX: &str = "SCREENSHOT_";
+
+/// Per-project config file, looked up from the current directory upwards.
+pub const PROJECT_CONFIG: &str = ".screenshot.toml";
+
+/// `/etc/screenshot/config.toml`, on systems that have one.
+pub fn system_config_path() -> Option<PathBuf> {
+    cfg!(unix).then(|| PathBuf::from("/etc/screenshot/config.toml"))
+}
+
+/// The setting an environment variable overrides, e.g. `thumbnail.max_edge`
+/// for `SCREENSHOT_THUMBNAIL__MAX_EDGE`.
+fn env_key(name: &str) -> Option<String> {
+    name.strip_prefix(ENV_PREFIX)
+        .filter(|key| !key.is_empty())
+        .map(|key| key.to_lowercase().replace("__", "."))
+}
+
+/// `X,Y,WxH` regions separated by `;`, as config tables.
+fn parse_env_regions(value: &str) -> Result<Vec<config::Map<String, i64>>, ScreenshotError> {
+    value
+        .split(';')
+        .filter(|region| !region.trim().is_empty())
+        .map(|region| {
+            let region: Region = region.parse()?;
+            Ok([
+                ("x", region.x as i64),
+                ("y", region.y as i64),
+                ("width", region.width as i64),
+                ("height", region.height as i64),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect())
+        })
+        .collect()
+}
+
+fn flatten(prefix: &str, table: &toml::Table, out: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(table) => flatten(&key, table, out),
+            value => out.push((key, value.clone())),
+        }
+    }
+}
+
+/// A layer of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Project(PathBuf),
+    /// The variable that set the value.
+    Environment(String),
+    CommandLine,
+}
+
+impl ConfigSource {
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::System(path) | Self::User(path) | Self::Project(path) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::System(path) => write!(f, "system config {}", path.display()),
+            Self::User(path) => write!(f, "user config {}", path.display()),
+            Self::Project(path) => write!(f, "project config {}", path.display()),
+            Self::Environment(name) => write!(f, "environment {}", name),
+            Self::CommandLine => f.write_str("command line"),
+        }
+    }
+}
+
+/// The source that set each effective value, by dotted key.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOrigins(BTreeMap<String, ConfigSource>);
+
+impl ConfigOrigins {
+    pub fn set(&mut self, key: &str, source: ConfigSource) {
+        // A whole table replaces what was recorded for the keys inside it
+        let nested = format!("{}.", key);
+        self.0.retain(|existing, _| !existing.starts_with(&nested));
+        self.0.insert(key.to_string(), source);
+    }
+
+    /// Where `key` was set, falling back to the table containing it.
+    pub fn get(&self, key: &str) -> Option<&ConfigSource> {
+        let mut key = key;
+        loop {
+            if let Some(source) = self.0.get(key) {
+                return Some(source);
+            }
+            key = &key[..key.rfind('.')?];
+        }
+    }
+
+    fn record_toml(&mut self, text: &str, source: &ConfigSource) -> Result<(), ScreenshotError> {
+        let table: toml::Table = toml::from_str(text).map_err(|e| ScreenshotError::ConfigError(
+            config::ConfigError::Message(match source.path() {
+                Some(path) => format!("{}: {}", path.display(), e),
+                None => e.to_string(),
+            })
+        ))?;
+        let mut values = Vec::new();
+        flatten("", &table, &mut values);
+        for (key, _) in values {
+            self.0.insert(key, source.clone());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThumbnailConfig {
+    pub max_edge: u32,
+    pub format: String,
+    pub quality: u8,
+}
+
+impl Default for ThumbnailConfig {
     fn default() -> Self {
         Self {
-            output_directory: get_default_screenshot_dir(),
-            default_format: "png".to_string(),
-            default_quality: 90,
-            filename_template: "screenshot_%Y%m%d_%H%M%S".to_string(),
-            custom_filename: None,
-            auto_open: false,
-            cleanup_after_days: None,
+            max_edge: 320,
+            format: "png".to_string(),
+            quality: 85,
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatermarkConfig {
+    pub text: Option<String>,
+    pub image: Option<PathBuf>,
+    pub font: Option<PathBuf>,
+    pub text_size: u32,
+    pub color: String,
+    pub position: String,
+    pub opacity: f32,
+    pub margin: u32,
+    pub tile: bool,
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            text: None,
+            image: None,
+            font: None,
+            text_size: 24,
+            color: "#ffffff".to_string(),
+            position: "bottom-right".to_string(),
+            opacity: 0.5,
+            margin: 16,
+            tile: false,
+        }
+    }
+}
+
+impl Default for Config {
+    /// The embedded defaults alone.
+    fn default() -> Self {
+        Self::load_layers(&[], config::Map::new())
+            .map(|(config, _)| config)
+            .expect("embedded default config is valid")
+    }
+}
+
 impl Config {
-    pub fn load() -> Result<Self, ScreenshotError> {
-        let config_dir = dirs::config_dir()
+    /// `<config dir>/screenshot`, which holds the config file and saved state.
+    pub fn config_dir() -> Result<PathBuf, ScreenshotError> {
+        dirs::config_dir()
+            .map(|dir| dir.join("screenshot"))
             .ok_or_else(|| ScreenshotError::ConfigError(
                 config::ConfigError::Message("No config directory found".to_string())
-            ))?
-            .join("screenshot");
+            ))
+    }
 
-        let config_path = config_dir.join("config.toml");
+    /// The user config file, `<config dir>/config.toml`.
+    pub fn user_config_path() -> Result<PathBuf, ScreenshotError> {
+        Ok(Self::config_dir()?.join("config.toml"))
+    }
 
-        if !config_path.exists() {
-            let default_config = Self::default();
-            default_config.save()?;
-            return Ok(default_config);
+    pub fn load() -> Result<Self, ScreenshotError> {
+        Self::load_with_origins().map(|(config, _)| config)
+    }
+
+    /// Loads every layer that applies in the current directory and
+    /// environment, and records which one set each value.
+    pub fn load_with_origins() -> Result<(Self, ConfigOrigins), ScreenshotError> {
+        let files = Self::config_files(&std::env::current_dir()?)?;
+        let env = std::env::vars().filter(|(name, _)| name.starts_with(ENV_PREFIX)).collect();
+        Self::load_layers(&files, env)
+    }
+
+    /// The config files that exist, lowest priority first: the system config,
+    /// the user config and the nearest `.screenshot.toml` at or above `dir`.
+    pub fn config_files(dir: &Path) -> Result<Vec<ConfigSource>, ScreenshotError> {
+        let mut files = Vec::new();
+        if let Some(system) = system_config_path().filter(|path| path.is_file()) {
+            files.push(ConfigSource::System(system));
+        }
+        let user = Self::user_config_path()?;
+        if user.is_file() {
+            files.push(ConfigSource::User(user));
         }
+        if let Some(project) = dir.ancestors().map(|dir| dir.join(PROJECT_CONFIG)).find(|path| path.is_file()) {
+            files.push(ConfigSource::Project(project));
+        }
+        Ok(files)
+    }
 
-        let settings = config::Config::builder()
-            .add_source(config::File::with_name(config_path.to_str().unwrap()))
-            .build()?;
+    /// Builds the config from the embedded defaults, then `files` in order,
+    /// then `SCREENSHOT_*` variables from `env`. List variables are split on
+    /// `,`, except `SCREENSHOT_REDACT`, which takes `X,Y,WxH` regions split on `;`.
+    pub fn load_layers(
+        files: &[ConfigSource],
+        env: config::Map<String, String>,
+    ) -> Result<(Self, ConfigOrigins), ScreenshotError> {
+        let texts = files
+            .iter()
+            .filter_map(|source| source.path().map(|path| (source, path)))
+            .map(|(source, path)| Ok((source.clone(), std::fs::read_to_string(path)?)))
+            .collect::<Result<Vec<_>, ScreenshotError>>()?;
+        Self::load_texts(&texts, env)
+    }
+
+    /// Like `load_layers`, with the contents of each file already read.
+    /// Values of the wrong type are reported with the file and line that set them.
+    pub fn load_texts(
+        files: &[(ConfigSource, String)],
+        mut env: config::Map<String, String>,
+    ) -> Result<(Self, ConfigOrigins), ScreenshotError> {
+        let mut origins = ConfigOrigins::default();
 
-        Ok(settings.try_deserialize()?)
+        let default_directory = get_default_screenshot_dir();
+        let mut builder = config::Config::builder()
+            .set_default("output_directory", default_directory.to_string_lossy().into_owned())?
+            .add_source(config::File::from_str(DEFAULT_CONFIG, config::FileFormat::Toml));
+        origins.set("output_directory", ConfigSource::Default);
+        origins.record_toml(DEFAULT_CONFIG, &ConfigSource::Default)?;
+
+        for (source, text) in files {
+            origins.record_toml(text, source)?;
+            builder = builder.add_source(config::File::from_str(text, config::FileFormat::Toml));
+        }
+
+        for (name, value) in &env {
+            if let Some(key) = env_key(name).filter(|_| !value.is_empty()) {
+                origins.set(&key, ConfigSource::Environment(name.clone()));
+            }
+        }
+        // Regions contain the `,` list separator, so `redact` is parsed here
+        let redact = env.keys().find(|name| env_key(name).as_deref() == Some("redact")).cloned();
+        if let Some((name, value)) = redact.and_then(|name| env.remove_entry(&name)) {
+            if !value.is_empty() {
+                let regions = parse_env_regions(&value).map_err(|e| ScreenshotError::ConfigError(
+                    config::ConfigError::Message(format!("{}: `redact`: {}", ConfigSource::Environment(name), e))
+                ))?;
+                builder = builder.set_override("redact", regions)?;
+            }
+        }
+        builder = builder.add_source(
+            config::Environment::with_prefix(ENV_PREFIX.trim_end_matches('_'))
+                .prefix_separator("_")
+                .separator("__")
+                .ignore_empty(true)
+                .try_parsing(true)
+                .list_separator(",")
+                .with_list_parse_key("tags")
+                .with_list_parse_key("allowed_roots")
+                .source(Some(env)),
+        );
+
+        let settings = builder.build()?;
+        let config = settings.clone().try_deserialize().map_err(|e| {
+            let (key, message) = match e {
+                config::ConfigError::Type { unexpected, expected, key: Some(key), .. } => {
+                    (Some(key), format!("invalid type: {}, expected {}", unexpected, expected))
+                },
+                // Errors raised while deserializing a value do not say which key it was
+                config::ConfigError::Message(message) => {
+                    (settings.try_deserialize().ok().as_ref().and_then(failing_key), message)
+                },
+                e => return e.into(),
+            };
+            let message = match key {
+                Some(key) => locate(&key, &message, &origins, files),
+                None => message,
+            };
+            ScreenshotError::ConfigError(config::ConfigError::Message(message))
+        })?;
+        Ok((config, origins))
+    }
+
+    /// Every set value as a dotted key and its TOML value, sorted by key.
+    pub fn values(&self) -> Result<Vec<(String, toml::Value)>, ScreenshotError> {
+        let value = toml::Value::try_from(self).map_err(|e| ScreenshotError::ConfigError(
+            config::ConfigError::Message(format!("Failed to serialize config: {}", e))
+        ))?;
+        let mut values = Vec::new();
+        if let toml::Value::Table(table) = value {
+            flatten("", &table, &mut values);
+        }
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(values)
     }
 
     pub fn save(&self) -> Result<(), ScreenshotError> {
-        let config_dir = dirs::config_dir()
-            .ok_or_else(|| ScreenshotError::ConfigError(
-                config::ConfigError::Message("No config directory found".to_string())
-            ))?
-            .join("screenshot");
+        let config_dir = Self::config_dir()?;
 
         std::fs::create_dir_all(&config_dir)?;
 
@@ -65,18 +409,127 @@ impl Config {
                 config::ConfigError::Message(format!("Failed to serialize config: {}", e))
             ))?;
 
-        std::fs::write(config_path, toml_string)?;
+        write_atomic(&config_path, CollisionStrategy::Overwrite, |file| Ok(file.write_all(toml_string.as_bytes())?))?;
         Ok(())
     }
 
     pub fn validate(&self) -> Result<(), ScreenshotError> {
-        match self.default_format.to_lowercase().as_str() {
-            "png" | "jpg" | "jpeg" | "webp" => {},
-            _ => return Err(ScreenshotError::InvalidFormat(self.default_format.clone())),
+        match self.field_errors().into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Every invalid setting, with the key it is stored under.
+    pub fn field_errors(&self) -> Vec<(&'static str, ScreenshotError)> {
+        let format = match self.default_format.to_lowercase().as_str() {
+            "png" | "jpg" | "jpeg" | "avif" => Ok(()),
+            "webp" => Err(ScreenshotError::InvalidFormat(
+                "webp (WebP cannot be encoded yet; use png, jpg or avif)".to_string()
+            )),
+            _ => Err(ScreenshotError::InvalidFormat(self.default_format.clone())),
+        };
+        let quality = match (1..=100).contains(&self.default_quality) {
+            true => Ok(()),
+            false => Err(ScreenshotError::InvalidQuality(self.default_quality)),
+        };
+
+        let checks = [
+            ("default_format", format),
+            ("default_quality", quality),
+            ("scale", self.scale_mode().map(|_| ())),
+            ("clipboard_target", self.clipboard_target().map(|_| ())),
+            ("on_collision", self.collision_strategy().map(|_| ())),
+            ("cleanup_max_size", self.cleanup_max_size().map(|_| ())),
+            ("filename_template", validate_filename_template(&self.filename_template)),
+            ("output_directory", self.output_directory_for("capture", &Local::now()).map(|_| ())),
+            ("thumbnail", self.thumbnail.as_ref().map_or(Ok(()), ThumbnailConfig::validate)),
+            ("watermark", self.watermark.as_ref().map_or(Ok(()), WatermarkConfig::validate)),
+        ];
+        checks
+            .into_iter()
+            .filter_map(|(key, result)| result.err().map(|e| (key, e)))
+            .collect()
+    }
+
+    pub fn clipboard_target(&self) -> Result<ClipboardTarget, ScreenshotError> {
+        match self.clipboard_target {
+            Some(ref target) => target.parse(),
+            None => Ok(ClipboardTarget::Image),
+        }
+    }
+
+    pub fn collision_strategy(&self) -> Result<CollisionStrategy, ScreenshotError> {
+        match self.on_collision {
+            Some(ref strategy) => strategy.parse(),
+            None => Ok(CollisionStrategy::default()),
+        }
+    }
+
+    /// The directory a capture of `mode` taken at `now` is saved in, with the
+    /// `output_directory` template expanded.
+    pub fn output_directory_for(&self, mode: &str, now: &DateTime<Local>) -> Result<PathBuf, ScreenshotError> {
+        resolve_output_directory(&self.output_directory, mode, now)
+    }
+
+    /// The fixed part of `output_directory`, which holds every capture.
+    pub fn output_root(&self) -> PathBuf {
+        output_root(&self.output_directory)
+    }
+
+    /// Creates the output root if needed and checks once, before capturing,
+    /// that it is inside `allowed_roots` and writable.
+    pub fn check_output_writable(&self) -> Result<(), ScreenshotError> {
+        check_writable(&validate_output_path(&self.output_root(), &self.allowed_roots)?)
+    }
+
+    pub fn cleanup_max_size(&self) -> Result<Option<u64>, ScreenshotError> {
+        self.cleanup_max_size.as_deref().map(parse_size).transpose()
+    }
+
+    pub fn scale_mode(&self) -> Result<ScaleMode, ScreenshotError> {
+        match self.scale {
+            Some(ref scale) => scale.parse(),
+            None => Ok(ScaleMode::Physical),
+        }
+    }
+}
+
+impl ThumbnailConfig {
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        // Thumbnails are only written as PNG or JPEG
+        match self.format.to_lowercase().as_str() {
+            "png" | "jpg" | "jpeg" => {},
+            _ => return Err(ScreenshotError::InvalidFormat(format!("{} (thumbnails are png or jpg)", self.format))),
+        }
+        if !(1..=100).contains(&self.quality) {
+            return Err(ScreenshotError::InvalidQuality(self.quality));
+        }
+        if self.max_edge == 0 {
+            return Err(ScreenshotError::ConfigError(
+                config::ConfigError::Message("thumbnail.max_edge must be greater than 0".to_string())
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl WatermarkConfig {
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        let invalid = |msg: String| ScreenshotError::ConfigError(config::ConfigError::Message(msg));
+
+        if self.text.is_none() && self.image.is_none() {
+            return Err(invalid("watermark needs either text or image".to_string()));
+        }
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(invalid(format!("watermark.opacity must be 0.0-1.0, got {}", self.opacity)));
         }
-        if !(1..=100).contains(&self.default_quality) {
-            return Err(ScreenshotError::InvalidQuality(self.default_quality));
+        if self.text_size == 0 {
+            return Err(invalid("watermark.text_size must be greater than 0".to_string()));
         }
+        WatermarkPosition::from_str(&self.position)?;
+        parse_color(&self.color)?;
 
         Ok(())
     }
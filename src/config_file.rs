@@ -0,0 +1,344 @@
+use crate::config::{Config, ConfigOrigins, ConfigSource, ThumbnailConfig, WatermarkConfig, DEFAULT_CONFIG};
+use crate::error::ScreenshotError;
+use crate::utils::atomic::{write_atomic, CollisionStrategy};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use std::fmt;
+use std::io::Write;
+use toml_edit::{DocumentMut, ImDocument, Item, Table, TableLike, Value};
+
+/// Settings that are tables, whose fields are set as `<table>.<field>`.
+const TABLES: [&str; 2] = ["thumbnail", "watermark"];
+
+fn config_error(message: String) -> ScreenshotError {
+    ScreenshotError::ConfigError(config::ConfigError::Message(message))
+}
+
+/// A config file opened for editing. Changes keep the comments, order and
+/// formatting of everything they do not touch.
+pub struct ConfigFile {
+    source: ConfigSource,
+    document: DocumentMut,
+}
+
+impl ConfigFile {
+    /// Opens the file behind `source`; a missing file opens empty.
+    pub fn open(source: ConfigSource) -> Result<Self, ScreenshotError> {
+        let path = source.path().ok_or_else(|| config_error(format!("{} is not a file", source)))?;
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Self::parse(source, &text)
+    }
+
+    pub fn parse(source: ConfigSource, text: &str) -> Result<Self, ScreenshotError> {
+        let document = text.parse::<DocumentMut>().map_err(|e| config_error(format!("{}: {}", source, e)))?;
+        Ok(Self { source, document })
+    }
+
+    pub fn source(&self) -> &ConfigSource {
+        &self.source
+    }
+
+    /// Sets `key` to `value`, read as a TOML value when it is one (`90`,
+    /// `true`, `["a", "b"]`) and as a string otherwise.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ScreenshotError> {
+        check_key(key)?;
+        if TABLES.contains(&key) {
+            let example = known_keys().into_iter().find(|known| known.starts_with(&format!("{}.", key))).unwrap_or_default();
+            return Err(config_error(format!("`{}` is a table, set its fields such as `{}`", key, example)));
+        }
+        let mut value = value.parse::<Value>().unwrap_or_else(|_| Value::from(value));
+        value.decor_mut().clear();
+
+        let (parents, name) = split_key(key);
+        // Root values are written before every table, so the first one would
+        // otherwise land above the file's opening comments
+        let root = self.document.as_table();
+        let first = root.is_empty() || (parents.is_empty() && !root.iter().any(|(_, item)| item.is_value()));
+        let mut table: &mut dyn TableLike = self.document.as_table_mut();
+        for parent in parents {
+            table = table
+                .entry(parent)
+                .or_insert_with(|| Item::Table(Table::new()))
+                .as_table_like_mut()
+                .ok_or_else(|| config_error(format!("`{}` is not a table", parent)))?;
+        }
+        match table.get_mut(name) {
+            Some(item) => {
+                // Keep the comment and spacing around the old value
+                if let Some(decor) = item.as_value().map(|old| old.decor().clone()) {
+                    *value.decor_mut() = decor;
+                }
+                *item = Item::Value(value);
+            },
+            None => {
+                table.insert(name, Item::Value(value));
+            },
+        }
+        if first {
+            self.move_leading_comments(key.split('.').next().unwrap_or(key));
+        }
+        Ok(())
+    }
+
+    /// Moves the comments at the top of the file, such as those of
+    /// `init_template`, in front of `first`, the setting just added.
+    fn move_leading_comments(&mut self, first: &str) {
+        let comments = if self.document.as_table().len() == 1 {
+            // Nothing else is set, so the comments all trail the document
+            let trailing = self.document.trailing().as_str().unwrap_or_default().to_string();
+            self.document.set_trailing("");
+            trailing
+        } else {
+            let root = self.document.as_table_mut();
+            let Some(table) = root
+                .iter_mut()
+                .filter(|(key, _)| key.get() != first)
+                .filter_map(|(_, item)| item.as_table_mut())
+                .min_by_key(|table| table.position())
+            else {
+                return;
+            };
+            let prefix = table.decor().prefix().and_then(|prefix| prefix.as_str()).unwrap_or_default().to_string();
+            table.decor_mut().set_prefix("\n");
+            prefix
+        };
+
+        let root = self.document.as_table_mut();
+        match root.get_mut(first) {
+            Some(Item::Table(table)) => table.decor_mut().set_prefix(comments),
+            _ => {
+                if let Some(mut key) = root.key_mut(first) {
+                    key.leaf_decor_mut().set_prefix(comments);
+                }
+            },
+        }
+    }
+
+    /// Removes `key`, returning whether it was set.
+    pub fn unset(&mut self, key: &str) -> Result<bool, ScreenshotError> {
+        check_key(key)?;
+        let (parents, name) = split_key(key);
+        let mut table: &mut dyn TableLike = self.document.as_table_mut();
+        for parent in parents {
+            match table.get_mut(parent).and_then(Item::as_table_like_mut) {
+                Some(inner) => table = inner,
+                None => return Ok(false),
+            }
+        }
+        Ok(table.remove(name).is_some())
+    }
+
+    /// Checks the file on top of the defaults and writes it back.
+    pub fn save(&self) -> Result<(), ScreenshotError> {
+        let text = self.to_string();
+        let problems = check(&[(self.source.clone(), text.clone())], config::Map::new());
+        if !problems.is_empty() {
+            return Err(config_error(problems.join("\n")));
+        }
+
+        let path = self.source.path().expect("opened from a file");
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, CollisionStrategy::Overwrite, |file| Ok(file.write_all(text.as_bytes())?))?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for ConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+/// A starting config file: every default, commented out.
+pub fn init_template() -> String {
+    let mut text = String::from(
+        "# screenshot configuration. Uncomment a line to change its default;\n\
+         # `screenshot config validate` checks the file.\n",
+    );
+    // Skip the header of the defaults file, it describes the built-in layer
+    for line in DEFAULT_CONFIG.lines().skip_while(|line| !line.is_empty()) {
+        match line.is_empty() || line.starts_with('#') {
+            true => text.push_str(line),
+            false => {
+                text.push_str("# ");
+                text.push_str(line);
+            },
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Every key that may be set, with table fields as `<table>.<field>`.
+pub fn known_keys() -> Vec<String> {
+    let mut keys = Vec::new();
+    for field in fields_of::<Config>() {
+        let fields = match *field {
+            "thumbnail" => fields_of::<ThumbnailConfig>(),
+            "watermark" => fields_of::<WatermarkConfig>(),
+            field => {
+                keys.push(field.to_string());
+                continue;
+            },
+        };
+        keys.extend(fields.iter().map(|nested| format!("{}.{}", field, nested)));
+    }
+    keys
+}
+
+fn is_known_key(key: &str) -> bool {
+    TABLES.contains(&key) || known_keys().iter().any(|known| known == key)
+}
+
+fn check_key(key: &str) -> Result<(), ScreenshotError> {
+    match is_known_key(key) {
+        true => Ok(()),
+        false => Err(config_error(format!("unknown setting `{}`", key))),
+    }
+}
+
+fn split_key(key: &str) -> (Vec<&str>, &str) {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let name = parts.pop().expect("split yields at least one part");
+    (parts, name)
+}
+
+/// The line `key` is set on in `text`, counting from 1.
+pub fn key_line(text: &str, key: &str) -> Option<usize> {
+    let document = ImDocument::parse(text).ok()?;
+    let (parents, name) = split_key(key);
+    let mut table: &dyn TableLike = document.as_table();
+    for parent in parents {
+        table = table.get(parent)?.as_table_like()?;
+    }
+    let (found, _) = table.get_key_value(name)?;
+    Some(line_of(text, found.span()?.start))
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+/// `message` about `key`, prefixed with the source that set it and, for a
+/// file, the line.
+pub(crate) fn locate(key: &str, message: &str, origins: &ConfigOrigins, files: &[(ConfigSource, String)]) -> String {
+    // List elements are reported as `tags[0]`
+    let key = key.split('[').next().unwrap_or(key);
+    let Some(source) = origins.get(key) else {
+        return format!("`{}`: {}", key, message);
+    };
+    let line = files
+        .iter()
+        .find(|(file, _)| file == source)
+        .and_then(|(_, text)| key_line(text, key));
+    match line {
+        Some(line) => format!("{}, line {}: `{}`: {}", source, line, key, message),
+        None => format!("{}: `{}`: {}", source, key, message),
+    }
+}
+
+/// The setting in `merged` that stops a config from deserializing, found by
+/// applying one setting at a time to the defaults; table fields are
+/// reported as `<table>.<field>`.
+pub(crate) fn failing_key(merged: &toml::Table) -> Option<String> {
+    let defaults = toml::Table::try_from(Config::default()).ok()?;
+    let fails = |key: &str, value: &toml::Value| {
+        let mut table = defaults.clone();
+        table.insert(key.to_string(), value.clone());
+        toml::Value::Table(table).try_into::<Config>().is_err()
+    };
+
+    let (key, value) = merged.iter().find(|(key, value)| fails(key, value))?;
+    let field = value.as_table().filter(|_| TABLES.contains(&key.as_str())).and_then(|fields| {
+        fields.iter().find_map(|(field, value)| {
+            let single = toml::Table::from_iter([(field.clone(), value.clone())]);
+            fails(key, &toml::Value::Table(single)).then(|| format!("{}.{}", key, field))
+        })
+    });
+    Some(field.unwrap_or_else(|| key.clone()))
+}
+
+/// Everything wrong with the config built from `files` and `env`: syntax
+/// errors, unknown settings and invalid values, each with where it was set.
+pub fn check(files: &[(ConfigSource, String)], env: config::Map<String, String>) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut syntax_errors = false;
+    for (source, text) in files {
+        match ImDocument::parse(text.as_str()) {
+            Ok(document) => unknown_keys(text, "", document.as_table(), source, &mut problems),
+            Err(e) => {
+                syntax_errors = true;
+                problems.push(format!("{}: {}", source, e.to_string().trim_end()));
+            },
+        }
+    }
+    if syntax_errors {
+        return problems;
+    }
+
+    match Config::load_texts(files, env) {
+        Ok((config, origins)) => {
+            for (key, e) in config.field_errors() {
+                problems.push(locate(key, &e.to_string(), &origins, files));
+            }
+        },
+        Err(ScreenshotError::ConfigError(config::ConfigError::Message(message))) => problems.push(message),
+        Err(e) => problems.push(e.to_string()),
+    }
+    problems
+}
+
+fn unknown_keys(text: &str, prefix: &str, table: &dyn TableLike, source: &ConfigSource, problems: &mut Vec<String>) {
+    for (name, item) in table.iter() {
+        let key = if prefix.is_empty() { name.to_string() } else { format!("{}.{}", prefix, name) };
+        if !is_known_key(&key) {
+            let line = table.get_key_value(name).and_then(|(found, _)| found.span()).map(|span| line_of(text, span.start));
+            match line {
+                Some(line) => problems.push(format!("{}, line {}: `{}`: unknown setting", source, line, key)),
+                None => problems.push(format!("{}: `{}`: unknown setting", source, key)),
+            }
+        } else if let Some(inner) = item.as_table_like().filter(|_| TABLES.contains(&key.as_str())) {
+            unknown_keys(text, &key, inner, source, problems);
+        }
+    }
+}
+
+/// The field names `T` deserializes from, read off its `Deserialize` impl.
+fn fields_of<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    let mut probe = FieldProbe(&[]);
+    let _ = T::deserialize(&mut probe);
+    probe.0
+}
+
+/// A deserializer that fails straight away, after noting the fields of the
+/// struct it was asked for.
+struct FieldProbe(&'static [&'static str]);
+
+impl<'de> Deserializer<'de> for &mut FieldProbe {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("only struct fields are probed"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0 = fields;
+        self.deserialize_any(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
@@ -0,0 +1,156 @@
+use crate::capture::metadata::{companion_files, is_companion_file, sidecar_path, CaptureMetadata};
+use crate::error::ScreenshotError;
+use crate::hash::{group_similar, HashKind, ImageHashes};
+use chrono::{DateTime, Local};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use log::{debug, warn};
+
+/// Extensions of the files `scan` looks at.
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+/// An image found in a directory, with its hashes.
+#[derive(Debug, Clone)]
+pub struct ScannedImage {
+    pub path: PathBuf,
+    pub captured_at: DateTime<Local>,
+    pub hashes: ImageHashes,
+}
+
+/// Near-identical images; `keep` is the oldest.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub keep: PathBuf,
+    /// The other images and their hash distance from `keep`.
+    pub duplicates: Vec<(PathBuf, u32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupeAction {
+    Report,
+    Delete,
+    Hardlink,
+}
+
+/// Hashes every image in `directory`, using the `.meta.json` sidecar when it
+/// is at least as new as the image and decoding the image otherwise.
+/// Thumbnails and files that fail to decode are left out.
+pub fn scan(directory: &Path) -> Result<Vec<ScannedImage>, ScreenshotError> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let is_image = path.extension()
+            .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+            .unwrap_or(false);
+        if path.is_file() && is_image && !is_companion_file(&path) {
+            paths.push(path);
+        }
+    }
+
+    let images: Vec<ScannedImage> = paths
+        .par_iter()
+        .filter_map(|path| match scan_image(path) {
+            Ok(image) => Some(image),
+            Err(e) => {
+                warn!("Skipping {}: {}", path.display(), e);
+                None
+            },
+        })
+        .collect();
+
+    Ok(images)
+}
+
+fn scan_image(path: &Path) -> Result<ScannedImage, ScreenshotError> {
+    let modified: DateTime<Local> = std::fs::metadata(path)?.modified()?.into();
+
+    let sidecar_modified = std::fs::metadata(sidecar_path(path))
+        .and_then(|m| m.modified())
+        .ok()
+        .map(DateTime::<Local>::from);
+    if sidecar_modified.is_some_and(|time| time >= modified) {
+        if let Some(metadata) = CaptureMetadata::read(path)? {
+            return Ok(ScannedImage { path: path.to_path_buf(), captured_at: metadata.captured_at, hashes: metadata.hashes });
+        }
+    }
+
+    debug!("Hashing {}", path.display());
+    let image = image::open(path)?.to_rgba8();
+    Ok(ScannedImage { path: path.to_path_buf(), captured_at: modified, hashes: ImageHashes::compute(&image) })
+}
+
+/// Groups `images` whose `kind` hashes are within `max_distance` bits of the
+/// oldest image of their group, which is the one to keep.
+pub fn find_duplicates(images: &[ScannedImage], kind: HashKind, max_distance: u32) -> Vec<DuplicateGroup> {
+    let mut sorted: Vec<&ScannedImage> = images.iter().collect();
+    sorted.sort_by(|a, b| a.captured_at.cmp(&b.captured_at).then_with(|| a.path.cmp(&b.path)));
+
+    let hashes: Vec<u64> = sorted.iter().map(|image| image.hashes.get(kind)).collect();
+    group_similar(&hashes, max_distance)
+        .into_iter()
+        .filter_map(|group| {
+            let keep = sorted[group[0]];
+            let duplicates: Vec<(PathBuf, u32)> = group[1..]
+                .iter()
+                .map(|&i| sorted[i])
+                .filter(|image| !same_file(&keep.path, &image.path))
+                .map(|image| (image.path.clone(), keep.hashes.distance(&image.hashes, kind)))
+                .collect();
+            (!duplicates.is_empty()).then(|| DuplicateGroup { keep: keep.path.clone(), duplicates })
+        })
+        .collect()
+}
+
+/// Deletes or hard-links the duplicates of a group. Deleting also removes
+/// their sidecars and thumbnails.
+pub fn apply(group: &DuplicateGroup, action: DedupeAction) -> Result<(), ScreenshotError> {
+    for (duplicate, _) in &group.duplicates {
+        match action {
+            DedupeAction::Report => {},
+            DedupeAction::Delete => {
+                for companion in companion_files(duplicate) {
+                    std::fs::remove_file(companion)?;
+                }
+                std::fs::remove_file(duplicate)?;
+            },
+            DedupeAction::Hardlink => {
+                // Link under a temporary name first so the duplicate is never missing
+                let file_name = duplicate.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                let link = duplicate.with_file_name(format!(".{}.{}.link", file_name, uuid::Uuid::new_v4()));
+                std::fs::hard_link(&group.keep, &link)?;
+                if let Err(e) = std::fs::rename(&link, duplicate) {
+                    let _ = std::fs::remove_file(&link);
+                    return Err(e.into());
+                }
+
+                // The duplicate's sidecar now describes the kept image
+                if let Some(mut metadata) = CaptureMetadata::read(duplicate)? {
+                    match CaptureMetadata::read(&group.keep)? {
+                        Some(kept) => {
+                            metadata.width = kept.width;
+                            metadata.height = kept.height;
+                            metadata.hashes = kept.hashes;
+                            metadata.write(duplicate)?;
+                        },
+                        None => std::fs::remove_file(sidecar_path(duplicate))?,
+                    }
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
@@ -0,0 +1,363 @@
+use crate::{capture::region::Region, error::ScreenshotError};
+use image::{Rgba, RgbaImage};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Per-channel difference at or below which two pixels count as equal.
+pub const DEFAULT_TOLERANCE: u8 = 16;
+
+/// Whether two RGBA8 pixels differ by more than `tolerance` in any channel.
+#[inline]
+pub fn pixel_changed(a: &[u8], b: &[u8], tolerance: u8) -> bool {
+    a.iter().zip(b).any(|(x, y)| x.abs_diff(*y) > tolerance)
+}
+
+/// Number of changed pixels between two same-sized RGBA8 buffers.
+pub fn changed_pixels(a: &[u8], b: &[u8], tolerance: u8) -> usize {
+    a.chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .filter(|(p, q)| pixel_changed(p, q, tolerance))
+        .count()
+}
+
+/// Fraction (0.0-1.0) of pixels that changed between two RGBA8 frames.
+/// Frames of different sizes are treated as completely different.
+pub fn changed_fraction(
+    a: &[u8],
+    a_size: (u32, u32),
+    b: &[u8],
+    b_size: (u32, u32),
+    tolerance: u8,
+) -> f64 {
+    let total = a_size.0 as usize * a_size.1 as usize;
+    if a_size != b_size || total == 0 || a.len() != total * 4 || b.len() != total * 4 {
+        return 1.0;
+    }
+
+    changed_pixels(a, b, tolerance) as f64 / total as f64
+}
+
+/// Smallest `(x, y, width, height)` box containing every changed pixel of two
+/// same-sized RGBA8 buffers, or `None` when nothing changed.
+pub fn changed_bounds(a: &[u8], b: &[u8], width: u32, tolerance: u8) -> Option<(u32, u32, u32, u32)> {
+    let row_bytes = width as usize * 4;
+    if row_bytes == 0 {
+        return None;
+    }
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+    for (y, (row_a, row_b)) in a.chunks_exact(row_bytes).zip(b.chunks_exact(row_bytes)).enumerate() {
+        for (x, (p, q)) in row_a.chunks_exact(4).zip(row_b.chunks_exact(4)).enumerate() {
+            if pixel_changed(p, q, tolerance) {
+                min_x = min_x.min(x as u32);
+                max_x = max_x.max(x as u32);
+                min_y = min_y.min(y as u32);
+                max_y = max_y.max(y as u32);
+            }
+        }
+    }
+
+    (min_x != u32::MAX).then(|| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+/// Parses a threshold written as a percentage (`2%`) or a fraction (`0.02`).
+pub fn parse_threshold(value: &str) -> Result<f64, ScreenshotError> {
+    let invalid = || ScreenshotError::InvalidThreshold(value.to_string());
+
+    let trimmed = value.trim();
+    let fraction = match trimmed.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map_err(|_| invalid())? / 100.0,
+        None => trimmed.parse::<f64>().map_err(|_| invalid())?,
+    };
+
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(invalid());
+    }
+
+    Ok(fraction)
+}
+
+/// Side length of the cells changed pixels are grouped into when finding
+/// changed regions; changes closer than this merge into one box.
+const REGION_CELL: u32 = 16;
+
+const CHANGED_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const BOX_COLOR: Rgba<u8> = Rgba([255, 140, 0, 255]);
+
+/// How `diff` lays out its output image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLayout {
+    /// The second image faded to grey with changed pixels in red.
+    Highlight,
+    /// First image, second image and highlight next to each other.
+    SideBySide,
+    /// Both images blended, with changed pixels tinted red.
+    Overlay,
+}
+
+impl FromStr for DiffLayout {
+    type Err = ScreenshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "highlight" => Ok(Self::Highlight),
+            "side-by-side" | "sidebyside" => Ok(Self::SideBySide),
+            "overlay" => Ok(Self::Overlay),
+            _ => Err(ScreenshotError::InvalidFormat(format!(
+                "{} (expected highlight, side-by-side or overlay)", s
+            ))),
+        }
+    }
+}
+
+/// What counts as a difference.
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Per-channel difference at or below which pixels count as equal.
+    pub tolerance: u8,
+    /// Areas left out of the comparison.
+    pub ignore: Vec<Region>,
+    /// Accept a changed pixel when each image has a matching pixel one step
+    /// away, which is what shifted anti-aliased edges look like.
+    pub antialiasing: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport {
+    pub width: u32,
+    pub height: u32,
+    pub changed_pixels: u64,
+    /// Pixels compared, i.e. the canvas minus ignored areas.
+    pub total_pixels: u64,
+    pub ignored_pixels: u64,
+    pub antialiased_pixels: u64,
+    pub changed_fraction: f64,
+    /// Boxes around each group of changed pixels, top to bottom.
+    pub regions: Vec<Region>,
+}
+
+/// Pixel-by-pixel comparison of two images. Images of different sizes are
+/// compared on a canvas as large as both; pixels only one of them covers
+/// count as changed.
+pub struct ImageDiff {
+    pub report: DiffReport,
+    changed: Vec<bool>,
+    ignored: Vec<bool>,
+}
+
+impl ImageDiff {
+    pub fn compute(a: &RgbaImage, b: &RgbaImage, tolerance: u8) -> Self {
+        Self::compute_with(a, b, &DiffOptions { tolerance, ..DiffOptions::default() })
+    }
+
+    pub fn compute_with(a: &RgbaImage, b: &RgbaImage, options: &DiffOptions) -> Self {
+        let width = a.width().max(b.width());
+        let height = a.height().max(b.height());
+        let len = width as usize * height as usize;
+
+        let mut ignored = vec![false; len];
+        for region in &options.ignore {
+            if let Some((x, y, w, h)) = region.clamp_to(width, height) {
+                for row in y..y + h {
+                    let start = (row * width + x) as usize;
+                    ignored[start..start + w as usize].fill(true);
+                }
+            }
+        }
+
+        let mut changed = vec![false; len];
+        let (mut count, mut antialiased) = (0u64, 0u64);
+        for y in 0..height {
+            for x in 0..width {
+                let index = (y * width + x) as usize;
+                if ignored[index] {
+                    continue;
+                }
+                let is_changed = match (pixel_at(a, x, y), pixel_at(b, x, y)) {
+                    (Some(p), Some(q)) if pixel_changed(&p.0, &q.0, options.tolerance) => {
+                        let shifted_edge = options.antialiasing
+                            && has_neighbour_match(p, b, x, y, options.tolerance)
+                            && has_neighbour_match(q, a, x, y, options.tolerance);
+                        if shifted_edge {
+                            antialiased += 1;
+                        }
+                        !shifted_edge
+                    },
+                    (Some(_), Some(_)) => false,
+                    _ => true,
+                };
+                if is_changed {
+                    changed[index] = true;
+                    count += 1;
+                }
+            }
+        }
+
+        let ignored_count = ignored.iter().filter(|&&i| i).count() as u64;
+        let total = len as u64 - ignored_count;
+        let report = DiffReport {
+            width,
+            height,
+            changed_pixels: count,
+            total_pixels: total,
+            ignored_pixels: ignored_count,
+            antialiased_pixels: antialiased,
+            changed_fraction: if total == 0 { 0.0 } else { count as f64 / total as f64 },
+            regions: changed_regions(&changed, width, height),
+        };
+
+        Self { report, changed, ignored }
+    }
+
+    pub fn is_changed(&self, x: u32, y: u32) -> bool {
+        x < self.report.width && y < self.report.height
+            && self.changed[(y * self.report.width + x) as usize]
+    }
+
+    pub fn render(&self, a: &RgbaImage, b: &RgbaImage, layout: DiffLayout) -> RgbaImage {
+        match layout {
+            DiffLayout::Highlight => self.highlight(b, a),
+            DiffLayout::Overlay => self.overlay(a, b),
+            DiffLayout::SideBySide => {
+                let (width, height) = (self.report.width, self.report.height);
+                let mut canvas = RgbaImage::new(width * 3, height);
+                image::imageops::replace(&mut canvas, a, 0, 0);
+                image::imageops::replace(&mut canvas, b, width as i64, 0);
+                image::imageops::replace(&mut canvas, &self.highlight(b, a), 2 * width as i64, 0);
+                canvas
+            },
+        }
+    }
+
+    /// `base` (falling back to `fallback` where it has no pixels) as a faded
+    /// greyscale, with changed pixels painted red, ignored areas tinted blue
+    /// and regions outlined.
+    fn highlight(&self, base: &RgbaImage, fallback: &RgbaImage) -> RgbaImage {
+        let mut out = RgbaImage::from_fn(self.report.width, self.report.height, |x, y| {
+            if self.is_changed(x, y) {
+                return CHANGED_COLOR;
+            }
+            let faded = match pixel_at(base, x, y).or_else(|| pixel_at(fallback, x, y)) {
+                Some(p) => {
+                    let luma = (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) as u8;
+                    255 - (255 - luma) / 3
+                },
+                None => 255,
+            };
+            if self.ignored[(y * self.report.width + x) as usize] {
+                Rgba([faded / 2, faded / 2 + 64, 255, 255])
+            } else {
+                Rgba([faded, faded, faded, 255])
+            }
+        });
+        self.outline_regions(&mut out);
+        out
+    }
+
+    fn overlay(&self, a: &RgbaImage, b: &RgbaImage) -> RgbaImage {
+        let mut out = RgbaImage::from_fn(self.report.width, self.report.height, |x, y| {
+            let blend = |p: Option<&Rgba<u8>>, q: Option<&Rgba<u8>>| match (p, q) {
+                (Some(p), Some(q)) => Rgba([0, 1, 2, 3].map(|i| ((p[i] as u16 + q[i] as u16) / 2) as u8)),
+                (Some(p), None) | (None, Some(p)) => *p,
+                (None, None) => Rgba([255, 255, 255, 255]),
+            };
+            let mut pixel = blend(pixel_at(a, x, y), pixel_at(b, x, y));
+            if self.is_changed(x, y) {
+                pixel = blend(Some(&pixel), Some(&CHANGED_COLOR));
+            }
+            pixel
+        });
+        self.outline_regions(&mut out);
+        out
+    }
+
+    fn outline_regions(&self, image: &mut RgbaImage) {
+        for region in &self.report.regions {
+            let (x0, y0) = (region.x as u32, region.y as u32);
+            let (x1, y1) = (x0 + region.width - 1, y0 + region.height - 1);
+            for x in x0..=x1 {
+                image.put_pixel(x, y0, BOX_COLOR);
+                image.put_pixel(x, y1, BOX_COLOR);
+            }
+            for y in y0..=y1 {
+                image.put_pixel(x0, y, BOX_COLOR);
+                image.put_pixel(x1, y, BOX_COLOR);
+            }
+        }
+    }
+}
+
+/// Whether `image` has a pixel equal to `pixel` next to `(x, y)`.
+fn has_neighbour_match(pixel: &Rgba<u8>, image: &RgbaImage, x: u32, y: u32, tolerance: u8) -> bool {
+    (-1i64..=1).any(|dy| {
+        (-1i64..=1).any(|dx| {
+            let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+            (dx, dy) != (0, 0)
+                && nx >= 0
+                && ny >= 0
+                && pixel_at(image, nx as u32, ny as u32).is_some_and(|n| !pixel_changed(&n.0, &pixel.0, tolerance))
+        })
+    })
+}
+
+fn pixel_at(image: &RgbaImage, x: u32, y: u32) -> Option<&Rgba<u8>> {
+    (x < image.width() && y < image.height()).then(|| image.get_pixel(x, y))
+}
+
+/// Groups changed pixels into regions: pixels are bucketed into cells,
+/// touching cells (diagonals included) form one region, and each region is
+/// the tight box around its changed pixels.
+fn changed_regions(changed: &[bool], width: u32, height: u32) -> Vec<Region> {
+    let cols = width.div_ceil(REGION_CELL) as usize;
+    let rows = height.div_ceil(REGION_CELL) as usize;
+
+    // Tight (min_x, min_y, max_x, max_y) of the changed pixels in each cell.
+    let mut cells: Vec<Option<(u32, u32, u32, u32)>> = vec![None; cols * rows];
+    for y in 0..height {
+        for x in 0..width {
+            if !changed[(y * width + x) as usize] {
+                continue;
+            }
+            let cell = &mut cells[(y / REGION_CELL) as usize * cols + (x / REGION_CELL) as usize];
+            *cell = Some(match *cell {
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                None => (x, y, x, y),
+            });
+        }
+    }
+
+    let mut seen = vec![false; cells.len()];
+    let mut regions = Vec::new();
+    for start in 0..cells.len() {
+        if seen[start] || cells[start].is_none() {
+            continue;
+        }
+
+        let mut bounds = cells[start].unwrap();
+        let mut stack = vec![start];
+        seen[start] = true;
+        while let Some(index) = stack.pop() {
+            let (x0, y0, x1, y1) = cells[index].unwrap();
+            bounds = (bounds.0.min(x0), bounds.1.min(y0), bounds.2.max(x1), bounds.3.max(y1));
+
+            let (col, row) = ((index % cols) as i64, (index / cols) as i64);
+            for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                let (c, r) = (col + dx, row + dy);
+                if c < 0 || r < 0 || c >= cols as i64 || r >= rows as i64 {
+                    continue;
+                }
+                let neighbour = r as usize * cols + c as usize;
+                if !seen[neighbour] && cells[neighbour].is_some() {
+                    seen[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+
+        let (x0, y0, x1, y1) = bounds;
+        regions.push(Region { x: x0 as i32, y: y0 as i32, width: x1 - x0 + 1, height: y1 - y0 + 1 });
+    }
+
+    regions.sort_by_key(|region| (region.y, region.x));
+    regions
+}
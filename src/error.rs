@@ -23,6 +23,30 @@ pub enum ScreenshotError {
     #[error("Invalid quality value: {0} (must be 1-100)")]
     InvalidQuality(u8),
 
+    #[error("Invalid scale: {0} (expected logical, physical, a factor or <n>dpi)")]
+    InvalidScale(String),
+
+    #[error("Invalid region: {0} (expected X,Y,WxH)")]
+    InvalidRegion(String),
+
+    #[error("Invalid duration or time: {0}")]
+    InvalidDuration(String),
+
+    #[error("Invalid template: {0}")]
+    InvalidTemplate(String),
+
+    #[error("Invalid size: {0} (expected bytes or a size such as 500MB or 2GiB)")]
+    InvalidSize(String),
+
+    #[error("Invalid threshold: {0} (expected a percentage such as 2% or a fraction such as 0.02)")]
+    InvalidThreshold(String),
+
+    #[error("Invalid collision strategy: {0} (expected counter, uuid, overwrite or error)")]
+    InvalidCollision(String),
+
+    #[error("File already exists: {}", .0.display())]
+    FileExists(std::path::PathBuf),
+
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
 
@@ -37,6 +61,24 @@ pub enum ScreenshotError {
 
     #[error("Clipboard error: {0}")]
     ClipboardError(String),
+
+    #[error("Baseline not found: {0}")]
+    BaselineNotFound(String),
+
+    #[error("Invalid baseline name: {0} (use letters, digits, '-', '_', '.' and '/')")]
+    InvalidBaselineName(String),
+
+    #[error("Baseline check failed: {0}")]
+    BaselineMismatch(String),
+
+    #[error("Images differ: {0}")]
+    DiffOverThreshold(String),
+
+    #[error("Capture matches the previous one, not saved: {}", .0.display())]
+    DuplicateCapture(std::path::PathBuf),
+
+    #[error("Not in the capture history: {0}")]
+    HistoryNotFound(String),
 }
 
 impl ScreenshotError {
@@ -44,9 +86,11 @@ impl ScreenshotError {
         match self {
             Self::NoScreensFound | Self::ScreenNotFound(_) => 2,
             Self::CaptureFailed(_) => 3,
-            Self::SaveError(_) => 4,
+            Self::SaveError(_) | Self::FileExists(_) => 4,
             Self::PermissionDenied(_) => 13,
             Self::PlatformNotSupported(_) => 5,
+            Self::BaselineMismatch(_) | Self::DiffOverThreshold(_) => 6,
+            Self::BaselineNotFound(_) => 7,
             _ => 1,
         }
     }
@@ -0,0 +1,184 @@
+use crate::error::ScreenshotError;
+use image::{imageops, ImageBuffer, Rgba};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Side of the grayscale image the perceptual hash is taken from.
+const PHASH_SIZE: usize = 32;
+
+/// Side of the low-frequency block of DCT coefficients kept by the perceptual hash.
+const PHASH_BLOCK: usize = 8;
+
+/// Which of the three hashes to compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashKind {
+    /// Each bit says whether an 8x8 cell is brighter than the mean.
+    Average,
+    /// Each bit says whether a cell is brighter than its right neighbour.
+    Difference,
+    /// Each bit says whether a low-frequency DCT coefficient is above the median.
+    Perceptual,
+}
+
+impl FromStr for HashKind {
+    type Err = ScreenshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ahash" | "average" => Ok(HashKind::Average),
+            "dhash" | "difference" => Ok(HashKind::Difference),
+            "phash" | "perceptual" => Ok(HashKind::Perceptual),
+            _ => Err(ScreenshotError::InvalidFormat(format!(
+                "{} (expected ahash, dhash or phash)", s
+            ))),
+        }
+    }
+}
+
+/// An RGBA8 image, owned or borrowed.
+type Rgba8<C> = ImageBuffer<Rgba<u8>, C>;
+
+/// 64-bit average, difference and perceptual hashes of an image, stored as hex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageHashes {
+    #[serde(with = "hex_u64")]
+    pub ahash: u64,
+    #[serde(with = "hex_u64")]
+    pub dhash: u64,
+    #[serde(with = "hex_u64")]
+    pub phash: u64,
+}
+
+impl ImageHashes {
+    pub fn compute<C: Deref<Target = [u8]>>(image: &Rgba8<C>) -> Self {
+        Self {
+            ahash: average_hash(image),
+            dhash: difference_hash(image),
+            phash: perceptual_hash(image),
+        }
+    }
+
+    pub fn get(&self, kind: HashKind) -> u64 {
+        match kind {
+            HashKind::Average => self.ahash,
+            HashKind::Difference => self.dhash,
+            HashKind::Perceptual => self.phash,
+        }
+    }
+
+    /// Number of differing bits between the `kind` hashes of two images.
+    pub fn distance(&self, other: &ImageHashes, kind: HashKind) -> u32 {
+        (self.get(kind) ^ other.get(kind)).count_ones()
+    }
+
+    /// The largest distance over all three hashes.
+    pub fn max_distance(&self, other: &ImageHashes) -> u32 {
+        [HashKind::Average, HashKind::Difference, HashKind::Perceptual]
+            .into_iter()
+            .map(|kind| self.distance(other, kind))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Downscales `image` to `width` x `height` and returns its BT.601 luma
+/// values, scaled by 1000 so that comparisons stay exact.
+fn luma<C: Deref<Target = [u8]>>(image: &Rgba8<C>, width: u32, height: u32) -> Vec<u32> {
+    imageops::thumbnail(image, width, height)
+        .pixels()
+        .map(|p| 299 * p[0] as u32 + 587 * p[1] as u32 + 114 * p[2] as u32)
+        .collect()
+}
+
+fn bits(values: impl Iterator<Item = bool>) -> u64 {
+    values.fold(0, |hash, bit| (hash << 1) | bit as u64)
+}
+
+pub fn average_hash<C: Deref<Target = [u8]>>(image: &Rgba8<C>) -> u64 {
+    let cells = luma(image, 8, 8);
+    let sum: u32 = cells.iter().sum();
+    bits(cells.iter().map(|&value| value * cells.len() as u32 > sum))
+}
+
+pub fn difference_hash<C: Deref<Target = [u8]>>(image: &Rgba8<C>) -> u64 {
+    let cells = luma(image, 9, 8);
+    bits(cells.chunks_exact(9).flat_map(|row| row.windows(2).map(|pair| pair[0] > pair[1])))
+}
+
+pub fn perceptual_hash<C: Deref<Target = [u8]>>(image: &Rgba8<C>) -> u64 {
+    let cells: Vec<f64> = luma(image, PHASH_SIZE as u32, PHASH_SIZE as u32).into_iter().map(f64::from).collect();
+
+    // cos((2x + 1) u pi / 2N) for the coefficients that are kept
+    let cosines: Vec<f64> = (0..PHASH_BLOCK)
+        .flat_map(|u| (0..PHASH_SIZE).map(move |x| ((2 * x + 1) as f64 * u as f64 * PI / (2 * PHASH_SIZE) as f64).cos()))
+        .collect();
+
+    // Separable DCT-II, rows then columns, limited to the low-frequency block
+    let mut rows = vec![0.0; PHASH_SIZE * PHASH_BLOCK];
+    for y in 0..PHASH_SIZE {
+        for u in 0..PHASH_BLOCK {
+            rows[y * PHASH_BLOCK + u] = (0..PHASH_SIZE)
+                .map(|x| cells[y * PHASH_SIZE + x] * cosines[u * PHASH_SIZE + x])
+                .sum();
+        }
+    }
+    let mut coefficients = Vec::with_capacity(PHASH_BLOCK * PHASH_BLOCK);
+    for v in 0..PHASH_BLOCK {
+        for u in 0..PHASH_BLOCK {
+            coefficients.push(
+                (0..PHASH_SIZE).map(|y| rows[y * PHASH_BLOCK + u] * cosines[v * PHASH_SIZE + y]).sum::<f64>()
+            );
+        }
+    }
+
+    // The DC term only carries overall brightness, so it stays out of the median
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    bits(coefficients.iter().map(|&value| value > median))
+}
+
+/// Groups items whose hashes are at most `max_distance` bits from the first
+/// item of their group. Each item not yet grouped starts a group with every
+/// later ungrouped item close to it, so similarity never chains: A~B and B~C
+/// do not put C with A. Returns only groups of two or more, as indices in
+/// ascending order, ordered by their first index.
+pub fn group_similar(hashes: &[u64], max_distance: u32) -> Vec<Vec<usize>> {
+    let mut grouped = vec![false; hashes.len()];
+    let mut groups = Vec::new();
+
+    for i in 0..hashes.len() {
+        if grouped[i] {
+            continue;
+        }
+        let group: Vec<usize> = std::iter::once(i)
+            .chain((i + 1..hashes.len()).filter(|&j| {
+                !grouped[j] && (hashes[i] ^ hashes[j]).count_ones() <= max_distance
+            }))
+            .collect();
+        if group.len() > 1 {
+            for &j in &group {
+                grouped[j] = true;
+            }
+            groups.push(group);
+        }
+    }
+
+    groups
+}
+
+mod hex_u64 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:016x}", value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        u64::from_str_radix(&text, 16).map_err(D::Error::custom)
+    }
+}
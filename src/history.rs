@@ -0,0 +1,144 @@
+use crate::{config::Config, error::ScreenshotError};
+use crate::utils::atomic::{write_atomic, CollisionStrategy};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use log::warn;
+
+/// One saved capture, stored as a line of `history.jsonl` in the config directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub path: PathBuf,
+    pub captured_at: DateTime<Local>,
+    pub mode: String,
+    pub screen: Option<usize>,
+    pub window: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Which entries `History::query` returns.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub since: Option<DateTime<Local>>,
+    pub mode: Option<String>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        self.since.is_none_or(|since| entry.captured_at >= since)
+            && self.mode.as_ref().is_none_or(|mode| entry.mode.eq_ignore_ascii_case(mode))
+    }
+}
+
+/// Append-only index of saved captures.
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// The index in the config directory.
+    pub fn open() -> Result<Self, ScreenshotError> {
+        Ok(Self::at(Config::config_dir()?.join("history.jsonl")))
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> Result<(), ScreenshotError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_vec(entry)
+            .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+        line.push(b'\n');
+
+        // A single write per entry, so concurrent captures do not interleave
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Points entries at new paths after their files were moved. Entries for
+    /// other files, and lines that cannot be read, are kept as they are.
+    pub fn relocate(&self, moved: &HashMap<PathBuf, PathBuf>) -> Result<(), ScreenshotError> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut rewritten = String::with_capacity(text.len());
+        for line in text.lines() {
+            let relocated = serde_json::from_str::<HistoryEntry>(line)
+                .ok()
+                .and_then(|entry| moved.get(&entry.path).map(|to| HistoryEntry { path: to.clone(), ..entry }));
+            match relocated {
+                Some(entry) => rewritten.push_str(&serde_json::to_string(&entry)
+                    .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?),
+                None => rewritten.push_str(line),
+            }
+            rewritten.push('\n');
+        }
+
+        // Replace the index in one step so a crash cannot truncate it
+        write_atomic(&self.path, CollisionStrategy::Overwrite, |file| Ok(file.write_all(rewritten.as_bytes())?))?;
+        Ok(())
+    }
+
+    /// Every entry, oldest first. Lines that cannot be read are skipped.
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>, ScreenshotError> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(number, line)| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!("Skipping line {} of {}: {}", number + 1, self.path.display(), e);
+                    None
+                },
+            })
+            .collect())
+    }
+
+    /// Entries matching `filter`, newest first, each with its position in the
+    /// unfiltered newest-first list (1 is the latest capture).
+    pub fn query(&self, filter: &HistoryFilter) -> Result<Vec<(usize, HistoryEntry)>, ScreenshotError> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .enumerate()
+            .map(|(i, entry)| (i + 1, entry))
+            .filter(|(_, entry)| filter.matches(entry))
+            .collect())
+    }
+
+    /// The `number`th most recent entry, counting from 1.
+    pub fn nth_latest(&self, number: usize) -> Result<HistoryEntry, ScreenshotError> {
+        let entries = self.entries()?;
+        number
+            .checked_sub(1)
+            .and_then(|back| entries.len().checked_sub(back + 1))
+            .map(|index| entries[index].clone())
+            .ok_or_else(|| ScreenshotError::HistoryNotFound(format!(
+                "no capture #{} ({} recorded)", number, entries.len()
+            )))
+    }
+}
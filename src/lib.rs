@@ -1,6 +1,14 @@
+pub mod baseline;
 pub mod config;
+pub mod config_file;
+pub mod dedupe;
 pub mod error;
 pub mod capture;
+pub mod diff;
+pub mod hash;
+pub mod history;
+pub mod organize;
+pub mod retention;
 pub mod utils;
 
 pub use error::ScreenshotError;
@@ -1,8 +1,30 @@
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use screenshots::Screen;
-use chrono::prelude::*;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Local;
+use screenshot_tool::{capture, capture::region::Region, diff, utils::clipboard, utils::time};
+use screenshot_tool::config::{system_config_path, Config, ConfigOrigins, ConfigSource, ENV_PREFIX, PROJECT_CONFIG};
+use screenshot_tool::config_file::{self, ConfigFile};
+use screenshot_tool::capture::record::{RecordFormat, RecordOptions};
+use screenshot_tool::capture::scroll::ScrollOptions;
+use screenshot_tool::capture::source::ScreenFrameSource;
+use screenshot_tool::capture::timelapse::TimelapseOptions;
+use screenshot_tool::capture::watch::WatchOptions;
+use screenshot_tool::baseline::{self, BaselineSettings, BaselineStore};
+use screenshot_tool::dedupe::{self, DedupeAction};
+use screenshot_tool::diff::{DiffLayout, DiffOptions, ImageDiff};
+use screenshot_tool::hash::HashKind;
+use screenshot_tool::history::{History, HistoryEntry, HistoryFilter};
+use screenshot_tool::organize;
+use screenshot_tool::retention::{self, RetentionPolicy};
+use screenshot_tool::utils::size::format_size;
+use screenshot_tool::utils::atomic::{write_atomic, CollisionStrategy};
+use screenshot_tool::utils::path::validate_output_path;
+use screenshot_tool::ScreenshotError;
 
 #[derive(Parser, Debug)]
 #[command(name = "screenshot")]
@@ -11,13 +33,41 @@ use anyhow::Result;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     output: Option<PathBuf>,
-    #[arg(short, long, default_value = "0")]
+    #[arg(short, long, default_value = "0", global = true)]
     delay: u64,
     
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     quiet: bool,
+
+    /// Output scale: logical, physical, a factor such as 0.5, or a DPI such as 144dpi
+    #[arg(long, global = true)]
+    scale: Option<String>,
+
+    /// Draw the mouse cursor into the capture
+    #[arg(long, global = true)]
+    cursor: bool,
+
+    /// Draw a highlight ring around the cursor (implies --cursor)
+    #[arg(long, global = true)]
+    highlight_cursor: bool,
+
+    /// Also copy the capture to the clipboard as image (default), or pick path, uri or all with --clipboard=path
+    #[arg(long, global = true, value_name = "TARGET", num_args = 0..=1, require_equals = true, default_missing_value = "image")]
+    clipboard: Option<String>,
+
+    /// Copy the capture to the clipboard without saving a file
+    #[arg(long, global = true)]
+    clipboard_only: bool,
+
+    /// Tag to store with the capture in the history (repeatable)
+    #[arg(long = "tag", global = true, value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// When the file name is taken: counter, uuid, overwrite or error
+    #[arg(long, global = true, value_name = "STRATEGY")]
+    on_collision: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -26,149 +76,1183 @@ enum Commands {
     Fullscreen {
         #[arg(short, long, default_value = "0")]
         screen: usize,
+        /// Capture this many frames in quick succession
+        #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+        burst: Option<u32>,
+        /// Time between burst frames, e.g. 100ms (default: as fast as possible)
+        #[arg(long, requires = "burst", value_parser = time::parse_duration_or_zero)]
+        interval: Option<Duration>,
     },
     Selection,
     Window,
+    /// Save the image currently on the clipboard
+    FromClipboard,
+    /// Run existing image files through the save pipeline
+    Process {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Output format (png, jpg or avif)
+        #[arg(long)]
+        format: Option<String>,
+        /// Crop away a uniform border
+        #[arg(long)]
+        trim: bool,
+        /// Black out a region, as X,Y,WxH (repeatable)
+        #[arg(long, value_name = "X,Y,WxH")]
+        redact: Vec<Region>,
+    },
+    /// Capture repeatedly at a fixed interval into a dated folder
+    Timelapse {
+        /// Interval between frames, e.g. 30s, 500ms, 5m
+        #[arg(long, value_parser = time::parse_duration)]
+        every: Duration,
+        /// Stop after this many frames have been saved
+        #[arg(long, conflicts_with = "until")]
+        count: Option<u32>,
+        /// Stop at this local time, HH:MM[:SS]
+        #[arg(long)]
+        until: Option<String>,
+        #[arg(short, long, default_value = "0")]
+        screen: usize,
+        /// Capture only this part of the screen, as X,Y,WxH
+        #[arg(long, value_name = "X,Y,WxH")]
+        region: Option<Region>,
+    },
+    /// Poll the screen and save only when it changes
+    Watch {
+        /// Fraction of pixels that must change, e.g. 2% or 0.02
+        #[arg(long, default_value = "2%", value_parser = diff::parse_threshold)]
+        threshold: f64,
+        /// How often to poll the screen
+        #[arg(long, default_value = "1s", value_parser = time::parse_duration)]
+        every: Duration,
+        /// Shortest time between two saves
+        #[arg(long, default_value = "0s", value_parser = time::parse_duration_or_zero)]
+        min_interval: Duration,
+        /// How long a change must stay still before it is saved
+        #[arg(long, default_value = "500ms", value_parser = time::parse_duration_or_zero)]
+        settle: Duration,
+        /// Stop after this many saved frames
+        #[arg(long)]
+        count: Option<u32>,
+        #[arg(short, long, default_value = "0")]
+        screen: usize,
+        /// Watch only this part of the screen, as X,Y,WxH
+        #[arg(long, value_name = "X,Y,WxH")]
+        region: Option<Region>,
+    },
+    /// Record the screen as an animated GIF or APNG
+    Record {
+        /// How long to record, e.g. 10s
+        #[arg(long, value_parser = time::parse_duration)]
+        duration: Duration,
+        #[arg(long, default_value = "10")]
+        fps: u32,
+        /// Output format (gif, apng, y4m, frames)
+        #[arg(long, default_value = "gif")]
+        format: RecordFormat,
+        /// Write to this path instead of the output directory; - streams y4m to stdout
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        #[arg(short, long, default_value = "0")]
+        screen: usize,
+        /// Record only this part of the screen, as X,Y,WxH
+        #[arg(long, value_name = "X,Y,WxH")]
+        region: Option<Region>,
+    },
+    /// Capture a scrolling region and stitch the frames into one tall image
+    Scroll {
+        /// Time between frames
+        #[arg(long, default_value = "300ms", value_parser = time::parse_duration)]
+        every: Duration,
+        /// Stop after this many frames
+        #[arg(long, default_value = "100", value_parser = clap::value_parser!(u32).range(1..))]
+        max_frames: u32,
+        /// Stop once the content has not moved for this long
+        #[arg(long, default_value = "2s", value_parser = time::parse_duration)]
+        idle: Duration,
+        /// Scroll automatically by this many wheel clicks per frame (needs xdotool)
+        #[arg(long, value_name = "CLICKS")]
+        auto_scroll: Option<u32>,
+        #[arg(short, long, default_value = "0")]
+        screen: usize,
+        /// Capture only this part of the screen, as X,Y,WxH
+        #[arg(long, value_name = "X,Y,WxH")]
+        region: Option<Region>,
+    },
+    /// Compare two images; -o writes the difference image.
+    /// Exits with 6 when more than --threshold of the pixels changed
+    Diff {
+        a: PathBuf,
+        b: PathBuf,
+        /// Output image layout (highlight, side-by-side, overlay)
+        #[arg(long, default_value = "highlight")]
+        layout: DiffLayout,
+        /// Per-channel difference to ignore (0-255)
+        #[arg(long, default_value = "0")]
+        tolerance: u8,
+        /// Changed pixels allowed before failing, e.g. 0.5% or 0.005
+        #[arg(long, default_value = "0", value_parser = diff::parse_threshold)]
+        threshold: f64,
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+        /// Same as -o: write the difference image here; the format follows the extension
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+    },
+    /// Find near-identical images in a directory and optionally remove them
+    Dedupe {
+        dir: PathBuf,
+        /// Hash to compare (ahash, dhash, phash)
+        #[arg(long, default_value = "phash")]
+        hash: HashKind,
+        /// Largest number of differing hash bits counted as a duplicate
+        #[arg(long, default_value = "4")]
+        distance: u32,
+        /// Delete duplicates, keeping the oldest image of each group
+        #[arg(long, conflicts_with = "hardlink")]
+        delete: bool,
+        /// Replace duplicates with hard links to the oldest image of each group
+        #[arg(long)]
+        hardlink: bool,
+    },
+    /// Delete or archive old captures according to the retention limits
+    Clean {
+        /// Only list what would be removed
+        #[arg(long)]
+        dry_run: bool,
+        /// Remove captures older than this many days [config: cleanup_after_days]
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u32>,
+        /// Keep at most this much, e.g. 2GB [config: cleanup_max_size]
+        #[arg(long, value_name = "SIZE")]
+        max_size: Option<String>,
+        /// Keep at most this many captures [config: cleanup_max_files]
+        #[arg(long, value_name = "COUNT")]
+        max_files: Option<usize>,
+        /// Move captures into this directory instead of deleting them [config: cleanup_archive]
+        #[arg(long, value_name = "DIR")]
+        archive: Option<PathBuf>,
+    },
+    /// Move captures in the output directory into its date and mode folders
+    Organize {
+        /// Only list what would be moved
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List recent captures, or open or copy one of them again
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+        /// Only captures since a duration ago (2h, 7d) or a date (2024-05-01)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only captures of this mode, e.g. fullscreen or window
+        #[arg(long)]
+        mode: Option<String>,
+        /// Show at most this many captures
+        #[arg(long, default_value = "20")]
+        limit: usize,
+        /// Print the entries as JSON
+        #[arg(long, global = true)]
+        json: bool,
+    },
+    /// Manage named baseline images for visual regression checks
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineAction,
+    },
+    /// Create, inspect, edit and check the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
     List,
+    /// Keeps serving clipboard contents after a capture exits
+    #[command(name = "clipboard-serve", hide = true)]
+    ClipboardServe {
+        #[arg(long)]
+        target: String,
+        #[arg(long)]
+        png: Option<PathBuf>,
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+}
+
+impl Commands {
+    /// Whether the command saves into the output directory, which is then
+    /// checked once up front instead of on every save.
+    fn writes_output(&self) -> bool {
+        !matches!(
+            self,
+            Commands::Diff { .. }
+                | Commands::Dedupe { .. }
+                | Commands::History { .. }
+                | Commands::Baseline { .. }
+                | Commands::Config { .. }
+                | Commands::List
+                | Commands::ClipboardServe { .. }
+        )
+    }
+
+    fn is_single_capture(&self) -> bool {
+        matches!(self, Commands::Fullscreen { .. } | Commands::Selection | Commands::Window | Commands::FromClipboard)
+    }
+}
+
+impl Cli {
+    /// Whether this run saves a file into the output directory.
+    fn saves_to_output(&self) -> bool {
+        self.command.writes_output() && !(self.clipboard_only && self.command.is_single_capture())
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum HistoryAction {
+    /// Open capture N from the list (1 is the latest) in the default viewer
+    Open { number: usize },
+    /// Print the path of the latest capture
+    Last,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print the effective configuration
+    Show {
+        /// Show which file, variable or flag set each value
+        #[arg(long)]
+        origin: bool,
+    },
+    /// Write a user config file with every default commented out
+    Init {
+        /// Replace an existing file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the path of the user config file
+    Path {
+        /// List every config file that is read, in order, and whether it exists
+        #[arg(long)]
+        all: bool,
+    },
+    /// Print the effective value of a setting, e.g. default_format or thumbnail.max_edge
+    Get { key: String },
+    /// Set a value in the user config, keeping its comments and layout
+    Set {
+        key: String,
+        value: String,
+        /// Edit ./.screenshot.toml instead of the user config
+        #[arg(long)]
+        project: bool,
+    },
+    /// Remove a setting from the user config, so the default applies again
+    Unset {
+        key: String,
+        /// Edit ./.screenshot.toml instead of the user config
+        #[arg(long)]
+        project: bool,
+    },
+    /// Open the user config in $VISUAL or $EDITOR, then check it
+    Edit {
+        /// Edit ./.screenshot.toml instead of the user config
+        #[arg(long)]
+        project: bool,
+    },
+    /// Check every config file and SCREENSHOT_* variable, exiting 1 on problems
+    Validate,
+}
+
+#[derive(Subcommand, Debug)]
+enum BaselineAction {
+    /// Store the current capture (or --image) as the baseline for NAME
+    Approve {
+        name: String,
+        /// Use this image file instead of capturing the screen
+        #[arg(long)]
+        image: Option<PathBuf>,
+        /// Leave an area out of future comparisons, as X,Y,WxH (repeatable);
+        /// areas stored by an earlier approve are kept
+        #[arg(long, value_name = "X,Y,WxH")]
+        ignore: Vec<Region>,
+        /// Drop the stored ignore areas instead of keeping them
+        #[arg(long)]
+        replace_ignore: bool,
+        /// Per-channel difference to ignore (0-255)
+        #[arg(long, default_value = "0")]
+        tolerance: u8,
+        /// Accept anti-aliased edges that moved by one pixel
+        #[arg(long)]
+        antialiasing: bool,
+        /// Changed pixels allowed, e.g. 0.1%
+        #[arg(long, default_value = "0", value_parser = diff::parse_threshold)]
+        threshold: f64,
+        #[arg(short, long, default_value = "0")]
+        screen: usize,
+        #[arg(long, value_name = "X,Y,WxH")]
+        region: Option<Region>,
+        /// Baseline directory
+        #[arg(long, default_value = "baselines")]
+        dir: PathBuf,
+    },
+    /// Compare the current capture (or --image) against one or more baselines
+    Check {
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// Image file to check, or a directory holding <NAME>.png for each name
+        #[arg(long)]
+        image: Option<PathBuf>,
+        /// Extra area to leave out, as X,Y,WxH (repeatable)
+        #[arg(long, value_name = "X,Y,WxH")]
+        ignore: Vec<Region>,
+        /// Per-channel difference to ignore, if above the stored one
+        #[arg(long, default_value = "0")]
+        tolerance: u8,
+        /// Accept anti-aliased edges that moved by one pixel
+        #[arg(long)]
+        antialiasing: bool,
+        /// Override the stored threshold, e.g. 0.1%
+        #[arg(long, value_parser = diff::parse_threshold)]
+        threshold: Option<f64>,
+        /// Where to write the HTML report of failures [default: ./baseline-report.html]
+        #[arg(long)]
+        report: Option<PathBuf>,
+        #[arg(short, long, default_value = "0")]
+        screen: usize,
+        #[arg(long, value_name = "X,Y,WxH")]
+        region: Option<Region>,
+        /// Baseline directory
+        #[arg(long, default_value = "baselines")]
+        dir: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    if cli.delay > 0 {
-        println!("Waiting {} seconds...", cli.delay);
-        std::thread::sleep(std::time::Duration::from_secs(cli.delay));
+    match cli.command {
+        Commands::List => list_screens(),
+        Commands::Process { ref inputs, ref format, trim, ref redact } => {
+            let mut config = load_config(&cli)?;
+            if let Some(ref format) = format {
+                config.default_format = format.clone();
+            }
+            config.trim |= trim;
+            config.redact.extend(redact.iter().copied());
+            config.skip_duplicates = false;
+            config.validate()?;
+            process_files(&cli, inputs, &config)?;
+        },
+        Commands::Timelapse { every, count, ref until, screen, region } => {
+            let config = load_config(&cli)?;
+            let options = TimelapseOptions {
+                interval: every,
+                count,
+                until: until.as_deref().map(|t| time::parse_until(t, &Local::now())).transpose()?,
+                screen,
+                region,
+            };
+            run_timelapse(&cli, &options, &config)?;
+            auto_clean(&cli, &config);
+        },
+        Commands::Watch { threshold, every, min_interval, settle, count, screen, region } => {
+            let config = load_config(&cli)?;
+            let options = WatchOptions { poll: every, threshold, min_interval, settle, count };
+            let mut source = ScreenFrameSource::new(screen, region, &config);
+            run_watch(&cli, &mut source, &options, &config)?;
+            auto_clean(&cli, &config);
+        },
+        Commands::Record { duration, fps, format, ref file, screen, region } => {
+            let config = load_config(&cli)?;
+            let options = RecordOptions { duration, fps, format, output: file.clone() };
+            let mut source = ScreenFrameSource::new(screen, region, &config);
+            run_record(&cli, &mut source, &options, &config)?;
+        },
+        Commands::Scroll { every, max_frames, idle, auto_scroll, screen, region } => {
+            let config = load_config(&cli)?;
+            let options = ScrollOptions { interval: every, max_frames, idle, auto_scroll };
+            let mut source = ScreenFrameSource::new(screen, region, &config);
+            run_scroll(&cli, &mut source, &options, &config)?;
+            auto_clean(&cli, &config);
+        },
+        Commands::Diff { ref a, ref b, layout, tolerance, threshold, json, ref file } => {
+            let file = match (cli.output.as_deref(), file.as_deref()) {
+                (Some(output), Some(file)) if output != file => {
+                    anyhow::bail!("-o and --file both name the difference image; give only one");
+                },
+                (output, file) => output.or(file),
+            };
+            let config = load_config(&cli)?;
+            let options = DiffRun { layout, tolerance, threshold, json, file };
+            if let Err(e) = run_diff(&cli, a, b, &options, &config) {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        },
+        Commands::Dedupe { ref dir, hash, distance, delete, hardlink } => {
+            let action = match (delete, hardlink) {
+                (true, _) => DedupeAction::Delete,
+                (_, true) => DedupeAction::Hardlink,
+                _ => DedupeAction::Report,
+            };
+            run_dedupe(&cli, dir, hash, distance, action)?;
+        },
+        Commands::Clean { dry_run, older_than, ref max_size, max_files, ref archive } => {
+            let mut config = load_config(&cli)?;
+            config.cleanup_after_days = older_than.or(config.cleanup_after_days);
+            config.cleanup_max_size = max_size.clone().or(config.cleanup_max_size);
+            config.cleanup_max_files = max_files.or(config.cleanup_max_files);
+            config.cleanup_archive = archive.clone().or(config.cleanup_archive);
+            config.validate()?;
+            if !run_clean(&cli, &config, dry_run)? {
+                anyhow::bail!(
+                    "No retention limits set; pass --older-than, --max-size or --max-files, \
+                     or set cleanup_after_days, cleanup_max_size or cleanup_max_files in the config"
+                );
+            }
+        },
+        Commands::Organize { dry_run } => {
+            let config = load_config(&cli)?;
+            run_organize(&cli, &config, dry_run)?;
+        },
+        Commands::History { ref action, ref since, ref mode, limit, json } => {
+            let config = load_config(&cli)?;
+            let filter = HistoryFilter {
+                since: since.as_deref().map(|s| time::parse_since(s, &Local::now())).transpose()?,
+                mode: mode.clone(),
+            };
+            run_history(&cli, action.as_ref(), &filter, limit, json, &config)?;
+        },
+        Commands::Baseline { ref action } => {
+            let config = load_config(&cli)?;
+            if let Err(e) = run_baseline(&cli, action, &config) {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        },
+        Commands::Config { ref action } => {
+            run_config(&cli, action)?;
+        },
+        Commands::ClipboardServe { ref target, ref png, ref file } => {
+            // The parent shows stderr as the reason the clipboard was not set
+            let served = target.parse().and_then(|target| clipboard::serve_clipboard(png.as_deref(), file.as_deref(), target));
+            if let Err(e) = served {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::Fullscreen { .. } | Commands::Selection | Commands::Window | Commands::FromClipboard => {
+            let config = load_config(&cli)?;
+            run_capture(&cli, &config)?;
+            if !cli.clipboard_only {
+                auto_clean(&cli, &config);
+            }
+        },
     }
 
-    let output_dir = cli.output.unwrap_or_else(get_default_screenshot_dir);
-    std::fs::create_dir_all(&output_dir)?;
+    Ok(())
+}
 
-    match cli.command {
-        Commands::Fullscreen { screen } => {
-            let path = capture_fullscreen(screen, output_dir)?;
-            if !cli.quiet {
-                println!("✅ Screenshot saved: {}", path.display());
+/// Loads the config layers and applies command-line overrides.
+fn load_config(cli: &Cli) -> Result<Config> {
+    let config = load_config_with_origins(cli)?.0;
+    if cli.saves_to_output() {
+        config.check_output_writable()?;
+    }
+    Ok(config)
+}
+
+fn load_config_with_origins(cli: &Cli) -> Result<(Config, ConfigOrigins)> {
+    let (mut config, mut origins) = Config::load_with_origins()?;
+    let mut set = |key: &str| origins.set(key, ConfigSource::CommandLine);
+
+    if let Some(ref output) = cli.output {
+        config.output_directory = output.clone();
+        set("output_directory");
+    }
+    if let Some(ref scale) = cli.scale {
+        config.scale = Some(scale.clone());
+        set("scale");
+    }
+    if cli.cursor {
+        config.cursor = true;
+        set("cursor");
+    }
+    if cli.highlight_cursor {
+        config.highlight_cursor = true;
+        set("highlight_cursor");
+    }
+    if let Some(ref target) = cli.clipboard {
+        config.copy_to_clipboard = true;
+        config.clipboard_target = Some(target.clone());
+        set("copy_to_clipboard");
+        set("clipboard_target");
+    }
+    config.clipboard_helper = std::env::current_exe().ok();
+    if !cli.tags.is_empty() {
+        config.tags.extend(cli.tags.iter().cloned());
+        set("tags");
+    }
+    if let Some(ref strategy) = cli.on_collision {
+        config.on_collision = Some(strategy.clone());
+        set("on_collision");
+    }
+    config.validate()?;
+
+    Ok((config, origins))
+}
+
+fn run_config(cli: &Cli, action: &ConfigAction) -> Result<()> {
+    match action {
+        ConfigAction::Show { origin: false } => {
+            print!("{}", toml::to_string_pretty(&load_config_with_origins(cli)?.0)?);
+        },
+        ConfigAction::Show { origin: true } => {
+            let (config, origins) = load_config_with_origins(cli)?;
+            let lines: Vec<(String, String)> = config
+                .values()?
+                .into_iter()
+                .map(|(key, value)| {
+                    let source = origins.get(&key).map(|source| source.to_string()).unwrap_or_else(|| "default".to_string());
+                    (format!("{} = {}", key, value), source)
+                })
+                .collect();
+            let width = lines.iter().map(|(line, _)| line.len()).max().unwrap_or(0);
+            for (line, source) in lines {
+                println!("{:<width$}  # {}", line, source, width = width);
             }
         },
-        Commands::Selection => {
-            let path = capture_selection(output_dir)?;
+        ConfigAction::Init { force } => {
+            let path = Config::user_config_path()?;
+            if path.exists() && !*force {
+                anyhow::bail!("{} already exists; pass --force to replace it", path.display());
+            }
+            let file = ConfigFile::parse(ConfigSource::User(path.clone()), &config_file::init_template())?;
+            file.save()?;
             if !cli.quiet {
-                println!("✅ Screenshot saved: {}", path.display());
+                println!("Wrote {}", path.display());
+            }
+        },
+        ConfigAction::Path { all: false } => {
+            println!("{}", Config::user_config_path()?.display());
+        },
+        ConfigAction::Path { all: true } => {
+            let read = Config::config_files(&std::env::current_dir()?)?;
+            for source in config_layers()? {
+                let status = if read.contains(&source) { "" } else { "  (not found)" };
+                println!("{}{}", source, status);
+            }
+        },
+        ConfigAction::Get { ref key } => {
+            let (config, _) = load_config_with_origins(cli)?;
+            if !config_file::known_keys().iter().any(|known| known == key || known.starts_with(&format!("{}.", key))) {
+                anyhow::bail!("unknown setting `{}`", key);
+            }
+            let nested = format!("{}.", key);
+            let values: Vec<_> = config
+                .values()?
+                .into_iter()
+                .filter(|(name, _)| name == key || name.starts_with(&nested))
+                .collect();
+            match values.as_slice() {
+                [] => std::process::exit(1),
+                [(name, value)] if name == key => match value {
+                    toml::Value::String(value) => println!("{}", value),
+                    value => println!("{}", value),
+                },
+                values => {
+                    for (name, value) in values {
+                        println!("{} = {}", name, value);
+                    }
+                },
+            }
+        },
+        ConfigAction::Set { ref key, ref value, project } => {
+            let mut file = ConfigFile::open(editable_config(*project)?)?;
+            file.set(key, value)?;
+            file.save()?;
+        },
+        ConfigAction::Unset { ref key, project } => {
+            let mut file = ConfigFile::open(editable_config(*project)?)?;
+            if file.unset(key)? {
+                file.save()?;
+            } else if !cli.quiet {
+                println!("`{}` is not set in {}", key, file.source());
+            }
+        },
+        ConfigAction::Edit { project } => {
+            let source = editable_config(*project)?;
+            let path = source.path().expect("editable configs are files").to_path_buf();
+            if !path.exists() {
+                ConfigFile::parse(source.clone(), &config_file::init_template())?.save()?;
             }
+            open_editor(&path)?;
+            let problems = config_file::check(&[(source, std::fs::read_to_string(&path)?)], config::Map::new());
+            report_config_problems(&problems);
         },
-        Commands::Window => {
-            let path = capture_window(output_dir)?;
+        ConfigAction::Validate => {
+            let files = Config::config_files(&std::env::current_dir()?)?
+                .into_iter()
+                .filter_map(|source| {
+                    let text = source.path().map(std::fs::read_to_string)?;
+                    Some(text.map(|text| (source, text)))
+                })
+                .collect::<std::io::Result<Vec<_>>>()?;
+            let env = std::env::vars().filter(|(name, _)| name.starts_with(ENV_PREFIX)).collect();
+            let problems = config_file::check(&files, env);
+            report_config_problems(&problems);
             if !cli.quiet {
-                println!("✅ Screenshot saved: {}", path.display());
+                println!("Configuration is valid ({} file{} read)", files.len(), if files.len() == 1 { "" } else { "s" });
             }
         },
-        Commands::List => {
-            list_screens();
+    }
+    Ok(())
+}
+
+/// Every config file that could be read here, lowest priority first.
+fn config_layers() -> Result<Vec<ConfigSource>> {
+    let mut layers: Vec<ConfigSource> = system_config_path().map(ConfigSource::System).into_iter().collect();
+    layers.push(ConfigSource::User(Config::user_config_path()?));
+    let current = std::env::current_dir()?;
+    let project = current
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| current.join(PROJECT_CONFIG));
+    layers.push(ConfigSource::Project(project));
+    Ok(layers)
+}
+
+/// The file `config set`, `unset` and `edit` change.
+fn editable_config(project: bool) -> Result<ConfigSource> {
+    Ok(match project {
+        true => ConfigSource::Project(std::env::current_dir()?.join(PROJECT_CONFIG)),
+        false => ConfigSource::User(Config::user_config_path()?),
+    })
+}
+
+fn open_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    // The variable may carry arguments, e.g. `code --wait`
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("$EDITOR is empty")?;
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor `{}`", editor))?;
+    if !status.success() {
+        anyhow::bail!("Editor `{}` exited with {}", editor, status);
+    }
+    Ok(())
+}
+
+/// Prints each problem and exits 1 when there are any.
+fn report_config_problems(problems: &[String]) {
+    if problems.is_empty() {
+        return;
+    }
+    for problem in problems {
+        eprintln!("{}", problem);
+    }
+    std::process::exit(1);
+}
+
+fn run_capture(cli: &Cli, config: &Config) -> Result<()> {
+    if cli.delay > 0 {
+        println!("Waiting {} seconds...", cli.delay);
+        std::thread::sleep(std::time::Duration::from_secs(cli.delay));
+    }
+
+    if cli.clipboard_only {
+        let image = match cli.command {
+            Commands::Fullscreen { burst: Some(_), .. } => {
+                anyhow::bail!("--burst cannot be combined with --clipboard-only");
+            },
+            Commands::Fullscreen { screen, .. } => capture::fullscreen::grab(screen, config)?,
+            Commands::Selection => capture::selection::grab()?,
+            Commands::Window => capture::window::grab()?,
+            Commands::FromClipboard => capture::clipboard::grab()?,
+            _ => unreachable!(),
+        };
+        capture::copy_image_with_config(&image.data, image.width, image.height, config, &image.info)?;
+        if !cli.quiet {
+            println!("📋 Screenshot copied to clipboard");
+        }
+        return Ok(());
+    }
+
+    if let Commands::Fullscreen { screen, burst: Some(count), interval } = cli.command {
+        let paths = capture::fullscreen::burst(screen, count, interval.unwrap_or_default(), config)?;
+        if !cli.quiet {
+            println!("✅ {} of {} burst frames saved:", paths.len(), count);
+            for path in &paths {
+                println!("   {}", path.display());
+            }
+        }
+        return Ok(());
+    }
+
+    let saved = match cli.command {
+        Commands::Fullscreen { screen, .. } => capture::fullscreen::capture(screen, config),
+        Commands::Selection => capture::selection::capture(config),
+        Commands::Window => capture::window::capture(config),
+        Commands::FromClipboard => capture::clipboard::capture(config),
+        _ => unreachable!(),
+    };
+    let path = match saved {
+        Err(ScreenshotError::DuplicateCapture(previous)) => {
+            if !cli.quiet {
+                println!("⏭️  Same as the previous capture, not saved: {}", previous.display());
+            }
+            return Ok(());
         },
+        result => result?,
+    };
+
+    if !cli.quiet {
+        println!("✅ Screenshot saved: {}", path.display());
     }
 
     Ok(())
 }
 
-fn get_default_screenshot_dir() -> PathBuf {
-    if cfg!(target_os = "windows") {
-        dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join("Pictures").join("Screenshots")
-    } else if cfg!(target_os = "macos") {
-        dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join("Desktop")
-    } else {
-        dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join("Pictures").join("Screenshots")
+fn process_files(cli: &Cli, inputs: &[PathBuf], config: &Config) -> Result<()> {
+    let mut failed = 0;
+    for (input, result) in capture::file::process_all(inputs, config) {
+        match result {
+            Ok(path) => {
+                if !cli.quiet {
+                    println!("✅ {} -> {}", input.display(), path.display());
+                }
+            },
+            Err(e) => {
+                eprintln!("❌ {}: {}", input.display(), e);
+                failed += 1;
+            },
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} files failed", failed, inputs.len());
     }
+
+    Ok(())
 }
 
-fn capture_fullscreen(screen_id: usize, save_dir: PathBuf) -> Result<PathBuf> {
-    let screens = Screen::all()?;
-    let screen = screens.get(screen_id).ok_or_else(|| anyhow::anyhow!("Screen {} not found", screen_id))?;
+/// Sets up a Ctrl+C handler that flips the returned flag instead of exiting.
+fn stop_flag() -> Result<Arc<AtomicBool>> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::SeqCst))?;
+    Ok(stop)
+}
+
+fn run_timelapse(cli: &Cli, options: &TimelapseOptions, config: &Config) -> Result<()> {
+    if cli.delay > 0 {
+        println!("Waiting {} seconds...", cli.delay);
+        std::thread::sleep(std::time::Duration::from_secs(cli.delay));
+    }
+
+    let stop = stop_flag()?;
+    if !cli.quiet {
+        println!("⏱️  Timelapse running, press Ctrl+C to stop");
+    }
 
-    let image = screen.capture()?;
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("screenshot_{}.png", timestamp);
-    let path = save_dir.join(filename);
+    let summary = capture::timelapse::run(options, config, &stop)?;
 
-    image::save_buffer_with_format(
-        &path,
-        image.rgba(),
-        image.width(),
-        image.height(),
-        image::ColorType::Rgba8,
-        image::ImageFormat::Png,
-    )?;
+    if !cli.quiet {
+        println!("✅ {} frames saved to {} ({} failed, {} skipped)",
+                 summary.captured, summary.directory.display(), summary.failed, summary.skipped);
+    }
 
-    Ok(path)
+    Ok(())
 }
 
-fn capture_selection(save_dir: PathBuf) -> Result<PathBuf> {
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("selection_{}.png", timestamp);
-    let path = save_dir.join(filename);
+fn run_watch(cli: &Cli, source: &mut ScreenFrameSource, options: &WatchOptions, config: &Config) -> Result<()> {
+    if cli.delay > 0 {
+        println!("Waiting {} seconds...", cli.delay);
+        std::thread::sleep(std::time::Duration::from_secs(cli.delay));
+    }
+
+    let stop = stop_flag()?;
+    if !cli.quiet {
+        println!("👀 Watching for changes, press Ctrl+C to stop");
+    }
+
+    let summary = capture::watch::run(source, options, config, &stop)?;
+
+    if !cli.quiet {
+        println!("✅ {} changes saved to {} ({} polls, {} failed)",
+                 summary.captured, config.output_root().display(), summary.polled, summary.failed);
+    }
+
+    Ok(())
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        let output = std::process::Command::new("gnome-screenshot")
-            .arg("-a").arg("-f").arg(&path)
-            .output()?;
+fn run_record(cli: &Cli, source: &mut ScreenFrameSource, options: &RecordOptions, config: &Config) -> Result<()> {
+    // Status output would corrupt a stream written to stdout.
+    let quiet = cli.quiet || options.output.as_deref() == Some(Path::new(capture::record::STDOUT));
 
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Selection capture failed"));
+    if cli.delay > 0 {
+        if !quiet {
+            println!("Waiting {} seconds...", cli.delay);
         }
+        std::thread::sleep(std::time::Duration::from_secs(cli.delay));
+    }
+
+    let stop = stop_flag()?;
+    if !quiet {
+        println!("🔴 Recording for {:?}, press Ctrl+C to stop early", options.duration);
+    }
+
+    let summary = capture::record::record(source, options, config, &stop)?;
+
+    if !quiet {
+        println!("✅ Recording saved: {} ({} frames, {} duplicates merged, {} failed)",
+                 summary.path.display(), summary.frames, summary.duplicates, summary.failed);
+    }
+
+    Ok(())
+}
+
+fn run_scroll(cli: &Cli, source: &mut ScreenFrameSource, options: &ScrollOptions, config: &Config) -> Result<()> {
+    if cli.delay > 0 {
+        println!("Waiting {} seconds...", cli.delay);
+        std::thread::sleep(std::time::Duration::from_secs(cli.delay));
+    }
+
+    let stop = stop_flag()?;
+    if !cli.quiet {
+        println!("📜 Scroll now; capture ends when the content stops moving or on Ctrl+C");
+    }
+
+    let path = capture::scroll::capture(source, options, config, &stop)?;
+
+    if !cli.quiet {
+        println!("✅ Scrolling capture saved: {}", path.display());
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        let output = std::process::Command::new("screencapture")
-            .arg("-s").arg("-x").arg(&path)
-            .output()?;
+    Ok(())
+}
 
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Selection capture failed"));
+/// `path` with its folder checked against `allowed_roots` and created.
+fn validated_file_path(path: &Path, config: &Config) -> screenshot_tool::Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| ScreenshotError::PermissionDenied(
+        format!("{} does not name a file", path.display())
+    ))?;
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    Ok(validate_output_path(parent, &config.allowed_roots)?.join(name))
+}
+
+struct DiffRun<'a> {
+    layout: DiffLayout,
+    tolerance: u8,
+    threshold: f64,
+    json: bool,
+    file: Option<&'a Path>,
+}
+
+fn run_diff(cli: &Cli, a: &Path, b: &Path, options: &DiffRun, config: &Config) -> screenshot_tool::Result<()> {
+    let image_a = image::open(a)?.to_rgba8();
+    let image_b = image::open(b)?.to_rgba8();
+
+    let diff = ImageDiff::compute(&image_a, &image_b, options.tolerance);
+    let report = &diff.report;
+
+    if let Some(output) = options.file {
+        let format = image::ImageFormat::from_path(output)?;
+        let rendered = diff.render(&image_a, &image_b, options.layout);
+        write_atomic(&validated_file_path(output, config)?, CollisionStrategy::Overwrite, |file| {
+            Ok(rendered.write_to(file, format)?)
+        })?;
+    }
+
+    if options.json {
+        let json = serde_json::to_string_pretty(report)
+            .map_err(|e| ScreenshotError::SaveError(std::io::Error::other(e)))?;
+        println!("{}", json);
+    } else if !cli.quiet {
+        println!("Changed: {} of {} pixels ({:.3}%)",
+                 report.changed_pixels, report.total_pixels, report.changed_fraction * 100.0);
+        println!("Regions: {}", report.regions.len());
+        for region in &report.regions {
+            println!("  {}", region);
         }
+        if let Some(output) = options.file {
+            println!("Difference image: {}", output.display());
+        }
+    }
+
+    if report.changed_fraction > options.threshold {
+        return Err(ScreenshotError::DiffOverThreshold(format!(
+            "{:.3}% of pixels changed, threshold is {:.3}%",
+            report.changed_fraction * 100.0, options.threshold * 100.0
+        )));
     }
 
-    #[cfg(target_os = "windows")]
-    {
-        return Err(anyhow::anyhow!("Selection capture not supported on Windows"));
+    Ok(())
+}
+
+fn run_dedupe(cli: &Cli, dir: &Path, kind: HashKind, distance: u32, action: DedupeAction) -> Result<()> {
+    let images = dedupe::scan(dir)?;
+    let groups = dedupe::find_duplicates(&images, kind, distance);
+
+    let verb = match action {
+        DedupeAction::Report => "duplicate",
+        DedupeAction::Delete => "deleted",
+        DedupeAction::Hardlink => "linked",
+    };
+    let mut count = 0;
+    for group in &groups {
+        dedupe::apply(group, action)?;
+        count += group.duplicates.len();
+        if !cli.quiet {
+            println!("{}", group.keep.display());
+            for (path, distance) in &group.duplicates {
+                println!("  {} {} (distance {})", verb, path.display(), distance);
+            }
+        }
     }
 
-    Ok(path)
+    if !cli.quiet {
+        println!("{} images scanned, {} duplicates in {} groups", images.len(), count, groups.len());
+    }
+    Ok(())
 }
 
-fn capture_window(save_dir: PathBuf) -> Result<PathBuf> {
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("window_{}.png", timestamp);
-    let path = save_dir.join(filename);
+/// Applies the retention limits to the output directory. Returns false when
+/// no limits are set.
+fn run_clean(cli: &Cli, config: &Config, dry_run: bool) -> Result<bool> {
+    let policy = RetentionPolicy::from_config(config)?;
+    if !policy.has_limits() {
+        return Ok(false);
+    }
+
+    let plan = retention::plan_cleanup(config, &policy, Local::now())?;
+    let verb = match (dry_run, &policy.archive) {
+        (true, _) => "Would remove",
+        (false, Some(_)) => "Archived",
+        (false, None) => "Removed",
+    };
 
-    #[cfg(target_os = "linux")]
-    {
-        let output = std::process::Command::new("gnome-screenshot")
-            .arg("-w").arg("-f").arg(&path)
-            .output()?;
+    if !dry_run {
+        retention::apply(&plan, &config.output_root(), policy.archive.as_deref(), &config.allowed_roots)?;
+    }
 
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Window capture failed"));
+    if !cli.quiet {
+        for (capture, reason) in &plan.expired {
+            println!("{} {} ({}, {})", verb, capture.path.display(), format_size(capture.size), reason);
         }
+        let freed: u64 = plan.expired.iter().map(|(capture, _)| capture.size).sum();
+        println!("🧹 {} {} captures ({}); kept {} ({})",
+                 verb, plan.expired.len(), format_size(freed), plan.kept, format_size(plan.kept_size));
+    }
+    Ok(true)
+}
+
+/// Runs the cleanup after a capture when `auto_clean` is set. Failures only warn.
+fn auto_clean(cli: &Cli, config: &Config) {
+    if !config.auto_clean {
+        return;
     }
+    let policy = match RetentionPolicy::from_config(config) {
+        Ok(policy) if policy.has_limits() => policy,
+        Ok(_) => return,
+        Err(e) => {
+            eprintln!("⚠️  Automatic cleanup skipped: {}", e);
+            return;
+        },
+    };
 
-    #[cfg(target_os = "macos")]
-    {
-        let output = std::process::Command::new("screencapture")
-            .arg("-W").arg("-x").arg(&path)
-            .output()?;
+    let cleaned = retention::plan_cleanup(config, &policy, Local::now()).and_then(|plan| {
+        retention::apply(&plan, &config.output_root(), policy.archive.as_deref(), &config.allowed_roots).map(|freed| (plan.expired.len(), freed))
+    });
+    match cleaned {
+        Ok((0, _)) => {},
+        Ok((count, freed)) => {
+            if !cli.quiet {
+                println!("🧹 Cleaned up {} old captures ({})", count, format_size(freed));
+            }
+        },
+        Err(e) => eprintln!("⚠️  Automatic cleanup failed: {}", e),
+    }
+}
 
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Window capture failed"));
+fn run_organize(cli: &Cli, config: &Config, dry_run: bool) -> Result<()> {
+    if config.output_root() == config.output_directory_for("capture", &Local::now())? {
+        anyhow::bail!(
+            "output_directory {} has no date or {{mode}} parts, so there is nothing to organize into",
+            config.output_directory.display()
+        );
+    }
+
+    let plan = organize::plan(config)?;
+    if !dry_run {
+        organize::apply(&plan, &config.allowed_roots)?;
+    }
+
+    if !cli.quiet {
+        let verb = if dry_run { "Would move" } else { "Moved" };
+        for step in &plan.moves {
+            println!("{} {} -> {} (from {})", verb, step.from.display(), step.to.display(), step.source);
         }
+        for (path, reason) in &plan.skipped {
+            println!("Skipped {}: {}", path.display(), reason);
+        }
+        println!("📁 {} {} files, skipped {}", verb, plan.moves.len(), plan.skipped.len());
     }
+    Ok(())
+}
+
+fn run_history(
+    cli: &Cli,
+    action: Option<&HistoryAction>,
+    filter: &HistoryFilter,
+    limit: usize,
+    json: bool,
+    config: &Config,
+) -> Result<()> {
+    let history = History::open()?;
 
-    #[cfg(target_os = "windows")]
-    {
-        return Err(anyhow::anyhow!("Window capture not supported on Windows"));
+    let entry = match action {
+        None => {
+            let entries: Vec<(usize, HistoryEntry)> = history.query(filter)?.into_iter().take(limit).collect();
+            if json {
+                let entries: Vec<&HistoryEntry> = entries.iter().map(|(_, entry)| entry).collect();
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else if entries.is_empty() {
+                if !cli.quiet {
+                    println!("No captures recorded in {}", history.path().display());
+                }
+            } else {
+                for (number, entry) in &entries {
+                    let mut line = format!(
+                        "{:>3}  {}  {:<10}  {:>9}  {}",
+                        number,
+                        entry.captured_at.format("%Y-%m-%d %H:%M:%S"),
+                        entry.mode,
+                        format!("{}x{}", entry.width, entry.height),
+                        entry.path.display(),
+                    );
+                    if !entry.tags.is_empty() {
+                        line.push_str(&format!("  [{}]", entry.tags.join(", ")));
+                    }
+                    if !entry.path.exists() {
+                        line.push_str("  (missing)");
+                    }
+                    println!("{}", line);
+                }
+            }
+            return Ok(());
+        },
+        Some(HistoryAction::Open { number }) => {
+            let entry = history.nth_latest(*number)?;
+            screenshot_tool::utils::path::open_with_default_app(&entry.path)?;
+            entry
+        },
+        Some(HistoryAction::Last) => {
+            let entry = history.nth_latest(1)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&entry)?);
+            } else {
+                println!("{}", entry.path.display());
+            }
+            entry
+        },
+    };
+
+    if config.copy_to_clipboard {
+        let image = image::open(&entry.path)?.to_rgba8();
+        clipboard::offer_capture(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            Some(&entry.path),
+            config.clipboard_target()?,
+            config.clipboard_helper.as_deref(),
+        )?;
+        if !cli.quiet && !json {
+            println!("📋 Copied to clipboard: {}", entry.path.display());
+        }
     }
+    Ok(())
+}
+
+fn run_baseline(cli: &Cli, action: &BaselineAction, config: &Config) -> screenshot_tool::Result<()> {
+    match action {
+        BaselineAction::Approve {
+            name, image, ignore, replace_ignore, tolerance, antialiasing, threshold, screen, region, dir,
+        } => {
+            let store = BaselineStore::new(dir);
+            if let Some(parent) = store.image_path(name)?.parent() {
+                validate_output_path(parent, &config.allowed_roots)?;
+            }
+            let actual = match image {
+                Some(path) => image::open(path)?.to_rgba8(),
+                None => baseline::capture_actual(*screen, region.as_ref(), config)?,
+            };
+            let mut settings = BaselineSettings {
+                ignore: ignore.clone(),
+                tolerance: *tolerance,
+                antialiasing: *antialiasing,
+                threshold: *threshold,
+            };
+            if !replace_ignore {
+                if let Some(stored) = store.settings(name)? {
+                    let kept = settings.keep_ignore(&stored);
+                    if kept > 0 && !cli.quiet {
+                        println!("Keeping {} stored ignore area(s); pass --replace-ignore to drop them", kept);
+                    }
+                }
+            }
+            let path = store.approve(name, &actual, &settings)?;
+            if !cli.quiet {
+                println!("✅ Baseline {} approved: {}", name, path.display());
+            }
+        },
+        BaselineAction::Check { names, image, ignore, tolerance, antialiasing, threshold, report, screen, region, dir } => {
+            let store = BaselineStore::new(dir);
+            let extra = DiffOptions { tolerance: *tolerance, ignore: ignore.clone(), antialiasing: *antialiasing };
+
+            let captured = match image {
+                None => Some(baseline::capture_actual(*screen, region.as_ref(), config)?),
+                Some(_) => None,
+            };
 
-    Ok(path)
+            let mut checks = Vec::new();
+            for name in names {
+                let actual = match (image, &captured) {
+                    (Some(path), _) if path.is_dir() => image::open(path.join(format!("{}.png", name)))?.to_rgba8(),
+                    (Some(path), _) => image::open(path)?.to_rgba8(),
+                    (None, Some(captured)) => captured.clone(),
+                    (None, None) => unreachable!(),
+                };
+                let check = store.check(name, actual, &extra, *threshold)?;
+                if !cli.quiet {
+                    let status = if check.passed { "✅ pass" } else { "❌ FAIL" };
+                    println!("{} {}: {} changed pixels ({:.3}%)",
+                             status, name, check.report.changed_pixels, check.report.changed_fraction * 100.0);
+                }
+                checks.push(check);
+            }
+
+            let failed = checks.iter().filter(|check| !check.passed).count();
+            if failed > 0 {
+                let report = report.clone().unwrap_or_else(|| PathBuf::from("baseline-report.html"));
+                let report = validated_file_path(&report, config)?;
+                baseline::write_report(&report, &checks)?;
+                return Err(ScreenshotError::BaselineMismatch(format!(
+                    "{} of {} baselines differ, see {}", failed, checks.len(), report.display()
+                )));
+            }
+        },
+    }
+
+    Ok(())
 }
 
 fn list_screens() {
@@ -177,7 +1261,8 @@ fn list_screens() {
             println!("Available screens:");
             for (i, screen) in screens.iter().enumerate() {
                 let info = &screen.display_info;
-                println!("  {} - {}x{} at ({}, {})", i, info.width, info.height, info.x, info.y);
+                println!("  {} - {}x{} at ({}, {}), scale {}x",
+                         i, info.width, info.height, info.x, info.y, info.scale_factor);
             }
         },
         Err(e) => {
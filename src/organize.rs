@@ -0,0 +1,179 @@
+use crate::capture::{metadata::{companion_files, is_companion_file, CaptureMetadata}, PNG_CREATION_TIME};
+use crate::history::History;
+use crate::utils::path::{move_file, validate_output_path};
+use crate::{config::Config, error::ScreenshotError};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use log::{debug, info};
+
+/// Timestamp layouts recognised in file names besides `filename_template`.
+const FILENAME_FORMATS: [&str; 5] = [
+    "%Y%m%d_%H%M%S",
+    "%Y-%m-%d_%H-%M-%S",
+    "%Y-%m-%d %H-%M-%S",
+    "%Y-%m-%d at %H.%M.%S",
+    "%Y%m%d%H%M%S",
+];
+
+/// Where the time of a capture was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSource {
+    Metadata,
+    History,
+    Embedded,
+    Filename,
+}
+
+impl fmt::Display for TimeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimeSource::Metadata => "metadata",
+            TimeSource::History => "history",
+            TimeSource::Embedded => "embedded time",
+            TimeSource::Filename => "file name",
+        })
+    }
+}
+
+/// A file and the place the output directory template puts it.
+#[derive(Debug, Clone)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub source: TimeSource,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OrganizePlan {
+    pub moves: Vec<Move>,
+    /// Files with no usable timestamp, or whose target is already taken.
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Plans moving the images directly inside the output root into the layout
+/// of `config.output_directory`. Times come from, in order, the `.meta.json`
+/// sidecar, the history index, the PNG creation time and the file name.
+pub fn plan(config: &Config) -> Result<OrganizePlan, ScreenshotError> {
+    let root = config.output_root();
+    let mut plan = OrganizePlan::default();
+    if !root.exists() {
+        return Ok(plan);
+    }
+    let root = root.canonicalize()?;
+
+    let recorded: HashMap<PathBuf, (DateTime<Local>, String)> = History::open()?
+        .entries()?
+        .into_iter()
+        .map(|entry| (entry.path, (entry.captured_at, entry.mode)))
+        .collect();
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&root)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .map(|entry| entry.path())
+        .filter(|path| is_image(path) && !is_companion_file(path))
+        .collect();
+    files.sort();
+
+    for path in files {
+        let found = match CaptureMetadata::read(&path) {
+            Ok(Some(metadata)) => Some((metadata.captured_at, metadata.mode, TimeSource::Metadata)),
+            _ => recorded
+                .get(&path)
+                .map(|(time, mode)| (*time, mode.clone(), TimeSource::History))
+                .or_else(|| embedded_time(&path).map(|time| (time, default_mode(&path), TimeSource::Embedded)))
+                .or_else(|| filename_time(&path, &config.filename_template).map(|time| (time, default_mode(&path), TimeSource::Filename))),
+        };
+        let Some((time, mode, source)) = found else {
+            plan.skipped.push((path, "no timestamp found".to_string()));
+            continue;
+        };
+
+        let directory = config.output_directory_for(&mode, &time)?;
+        let Some(name) = path.file_name() else { continue };
+        let to = directory.join(name);
+
+        if directory.canonicalize().is_ok_and(|directory| directory == root) {
+            debug!("{} is already in place", path.display());
+        } else if to.exists() {
+            plan.skipped.push((path, format!("{} already exists", to.display())));
+        } else {
+            plan.moves.push(Move { from: path, to, source });
+        }
+    }
+    Ok(plan)
+}
+
+/// Moves the planned files with their sidecars and thumbnails, and updates
+/// the history index to point at the new paths. Target folders must lie
+/// inside `allowed_roots` when it is not empty.
+pub fn apply(plan: &OrganizePlan, allowed_roots: &[PathBuf]) -> Result<(), ScreenshotError> {
+    let mut moved = HashMap::new();
+
+    for step in &plan.moves {
+        let directory = validate_output_path(step.to.parent().unwrap_or(Path::new(".")), allowed_roots)?;
+
+        for companion in companion_files(&step.from) {
+            if let Some(name) = companion.file_name() {
+                move_file(&companion, &directory.join(name))?;
+            }
+        }
+        let to = directory.join(step.to.file_name().unwrap_or_default());
+        move_file(&step.from, &to)?;
+        info!("Moved {} to {}", step.from.display(), to.display());
+
+        moved.insert(step.from.clone(), to);
+    }
+
+    if !moved.is_empty() {
+        History::open()?.relocate(&moved)?;
+    }
+    Ok(())
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| matches!(ext.to_string_lossy().to_lowercase().as_str(), "png" | "jpg" | "jpeg" | "webp"))
+        .unwrap_or(false)
+}
+
+/// Captures without metadata are filed as their file name prefix suggests.
+fn default_mode(path: &Path) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+    ["fullscreen", "selection", "window", "clipboard", "scroll", "watch", "recording"]
+        .into_iter()
+        .find(|mode| stem.starts_with(mode))
+        .or_else(|| stem.starts_with("screen_").then_some("fullscreen"))
+        .unwrap_or("capture")
+        .to_string()
+}
+
+/// The creation time written into a PNG by the save pipeline.
+pub fn embedded_time(path: &Path) -> Option<DateTime<Local>> {
+    let file = std::fs::File::open(path).ok()?;
+    let reader = png::Decoder::new(std::io::BufReader::new(file)).read_info().ok()?;
+    reader
+        .info()
+        .uncompressed_latin1_text
+        .iter()
+        .find(|chunk| chunk.keyword == PNG_CREATION_TIME)
+        .and_then(|chunk| DateTime::parse_from_rfc3339(chunk.text.trim()).ok())
+        .map(|time| time.with_timezone(&Local))
+}
+
+/// A timestamp found anywhere in the file name, read with `template` or one
+/// of the common screenshot naming layouts.
+pub fn filename_time(path: &Path, template: &str) -> Option<DateTime<Local>> {
+    let stem = path.file_stem()?.to_string_lossy().into_owned();
+
+    std::iter::once(template)
+        .chain(FILENAME_FORMATS)
+        .find_map(|format| {
+            stem.char_indices().find_map(|(start, _)| {
+                NaiveDateTime::parse_and_remainder(&stem[start..], format).ok().map(|(time, _)| time)
+            })
+        })
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+}
@@ -0,0 +1,207 @@
+use crate::capture::metadata::{companion_files, is_companion_file, CaptureMetadata};
+use crate::history::History;
+use crate::utils::path::{move_file, validate_output_path};
+use crate::{config::Config, error::ScreenshotError};
+use chrono::{DateTime, Duration, Local};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use log::{debug, info, warn};
+
+/// Limits on what is kept in the output directory. Unset limits do not apply.
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    pub max_age: Option<Duration>,
+    pub max_size: Option<u64>,
+    pub max_files: Option<usize>,
+    /// Move expired captures here instead of deleting them.
+    pub archive: Option<PathBuf>,
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &Config) -> Result<Self, ScreenshotError> {
+        Ok(Self {
+            max_age: config.cleanup_after_days.map(|days| Duration::days(days as i64)),
+            max_size: config.cleanup_max_size()?,
+            max_files: config.cleanup_max_files,
+            archive: config.cleanup_archive.clone(),
+        })
+    }
+
+    pub fn has_limits(&self) -> bool {
+        self.max_age.is_some() || self.max_size.is_some() || self.max_files.is_some()
+    }
+}
+
+/// A capture in the output directory that the tool is known to have written.
+#[derive(Debug, Clone)]
+pub struct TrackedCapture {
+    pub path: PathBuf,
+    pub captured_at: DateTime<Local>,
+    /// Bytes taken by the image, its sidecar and thumbnails.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanReason {
+    Age,
+    Count,
+    Size,
+}
+
+impl fmt::Display for CleanReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CleanReason::Age => "too old",
+            CleanReason::Count => "over the file limit",
+            CleanReason::Size => "over the size limit",
+        })
+    }
+}
+
+/// What a cleanup would remove and what it would keep.
+#[derive(Debug, Clone, Default)]
+pub struct CleanPlan {
+    pub expired: Vec<(TrackedCapture, CleanReason)>,
+    pub kept: usize,
+    pub kept_size: u64,
+}
+
+/// Captures below `directory` that appear in the history or have a
+/// `.meta.json` sidecar. Anything else was not written by this tool and is
+/// never a candidate for cleanup.
+pub fn tracked_captures(
+    directory: &Path,
+    history: Option<&History>,
+    exclude: Option<&Path>,
+) -> Result<Vec<TrackedCapture>, ScreenshotError> {
+    let directory = directory.canonicalize()?;
+    let exclude = exclude.and_then(|path| path.canonicalize().ok());
+
+    let recorded: HashMap<PathBuf, DateTime<Local>> = match history {
+        Some(history) => history.entries()?.into_iter().map(|entry| (entry.path, entry.captured_at)).collect(),
+        None => HashMap::new(),
+    };
+
+    let mut files = Vec::new();
+    collect_files(&directory, exclude.as_deref(), &mut files)?;
+
+    let mut captures = Vec::new();
+    for path in files {
+        let captured_at = match recorded.get(&path) {
+            Some(&time) => Some(time),
+            None => match CaptureMetadata::read(&path) {
+                Ok(metadata) => metadata.map(|metadata| metadata.captured_at),
+                Err(e) => {
+                    warn!("Ignoring {}: {}", path.display(), e);
+                    None
+                },
+            },
+        };
+        let Some(captured_at) = captured_at else {
+            debug!("Not created by screenshot, leaving alone: {}", path.display());
+            continue;
+        };
+
+        let size = std::iter::once(path.clone())
+            .chain(companion_files(&path))
+            .filter_map(|file| std::fs::metadata(file).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        captures.push(TrackedCapture { path, captured_at, size });
+    }
+    Ok(captures)
+}
+
+fn collect_files(directory: &Path, exclude: Option<&Path>, files: &mut Vec<PathBuf>) -> Result<(), ScreenshotError> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if exclude != Some(path.as_path()) {
+                collect_files(&path, exclude, files)?;
+            }
+        } else if file_type.is_file() && !is_companion_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Decides which captures to remove: those older than the age limit, then,
+/// going from newest to oldest, everything past the file limit or past the
+/// point where the size limit is reached.
+pub fn plan(mut captures: Vec<TrackedCapture>, policy: &RetentionPolicy, now: DateTime<Local>) -> CleanPlan {
+    captures.sort_by(|a, b| b.captured_at.cmp(&a.captured_at).then_with(|| a.path.cmp(&b.path)));
+
+    let mut plan = CleanPlan::default();
+    let mut size_reached = false;
+    for capture in captures {
+        let reason = if policy.max_age.is_some_and(|age| capture.captured_at < now - age) {
+            Some(CleanReason::Age)
+        } else if policy.max_files.is_some_and(|max| plan.kept >= max) {
+            Some(CleanReason::Count)
+        } else if size_reached || policy.max_size.is_some_and(|max| plan.kept_size + capture.size > max) {
+            size_reached = true;
+            Some(CleanReason::Size)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => plan.expired.push((capture, reason)),
+            None => {
+                plan.kept += 1;
+                plan.kept_size += capture.size;
+            },
+        }
+    }
+    plan
+}
+
+/// Plans a cleanup of the output directory under `policy`, using the history
+/// index and sidecars to find the captures to consider.
+pub fn plan_cleanup(config: &Config, policy: &RetentionPolicy, now: DateTime<Local>) -> Result<CleanPlan, ScreenshotError> {
+    let root = config.output_root();
+    if !root.exists() {
+        return Ok(CleanPlan::default());
+    }
+    let history = History::open()?;
+    let captures = tracked_captures(&root, Some(&history), policy.archive.as_deref())?;
+    Ok(plan(captures, policy, now))
+}
+
+/// Deletes the expired captures with their sidecars and thumbnails, or moves
+/// them below `archive` keeping their path relative to `directory`. Archive
+/// folders must lie inside `allowed_roots` when it is not empty. Returns the
+/// number of bytes freed in `directory`.
+pub fn apply(
+    plan: &CleanPlan,
+    directory: &Path,
+    archive: Option<&Path>,
+    allowed_roots: &[PathBuf],
+) -> Result<u64, ScreenshotError> {
+    let directory = directory.canonicalize()?;
+    let mut freed = 0;
+
+    for (capture, reason) in &plan.expired {
+        let files: Vec<PathBuf> = std::iter::once(capture.path.clone()).chain(companion_files(&capture.path)).collect();
+        for file in files {
+            match archive {
+                Some(archive) => {
+                    let target = match file.strip_prefix(&directory) {
+                        Ok(relative) => archive.join(relative),
+                        Err(_) => archive.join(file.file_name().unwrap_or_default()),
+                    };
+                    let parent = validate_output_path(target.parent().unwrap_or(archive), allowed_roots)?;
+                    move_file(&file, &parent.join(target.file_name().unwrap_or_default()))?;
+                },
+                None => std::fs::remove_file(&file)?,
+            }
+        }
+        info!("Cleaned {} ({})", capture.path.display(), reason);
+        freed += capture.size;
+    }
+    Ok(freed)
+}
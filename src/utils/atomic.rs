@@ -0,0 +1,247 @@
+use crate::{error::ScreenshotError, utils::path::ensure_unique_filename};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use uuid::Uuid;
+use log::warn;
+
+/// What to do when the file being saved already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionStrategy {
+    /// Save as `<name>_1`, `<name>_2`, ...
+    #[default]
+    Counter,
+    /// Save as `<name>_<uuid>`.
+    Uuid,
+    Overwrite,
+    Error,
+}
+
+impl FromStr for CollisionStrategy {
+    type Err = ScreenshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "counter" => Ok(Self::Counter),
+            "uuid" => Ok(Self::Uuid),
+            "overwrite" => Ok(Self::Overwrite),
+            "error" => Ok(Self::Error),
+            _ => Err(ScreenshotError::InvalidCollision(s.to_string())),
+        }
+    }
+}
+
+/// A file written under a temporary name in its target directory and only
+/// renamed into place by `commit`, once its contents are on disk. Dropping it
+/// uncommitted removes the temporary file, so a failed or interrupted write
+/// never leaves a partial file under the real name.
+pub struct AtomicFile {
+    path: PathBuf,
+    temp: PathBuf,
+    writer: Option<BufWriter<File>>,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> Result<Self, ScreenshotError> {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let temp = path.with_file_name(format!(".{}.{}.tmp", name, Uuid::new_v4().simple()));
+        let file = File::create(&temp)?;
+
+        Ok(Self { path: path.to_path_buf(), temp, writer: Some(BufWriter::new(file)) })
+    }
+
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        self.writer.as_mut().expect("writer is only taken by commit")
+    }
+
+    /// Flushes and syncs the file, then moves it to its final name as
+    /// `collision` decides. Returns the path it was saved under.
+    pub fn commit(mut self, collision: CollisionStrategy) -> Result<PathBuf, ScreenshotError> {
+        let file = self.writer.take().expect("commit is only called once")
+            .into_inner()
+            .map_err(|e| ScreenshotError::SaveError(e.into_error()))?;
+        file.sync_all()?;
+        drop(file);
+
+        let target = place(&self.temp, &self.path, collision)?;
+        sync_directory(&target);
+        Ok(target)
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+impl Seek for AtomicFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.writer().seek(pos)
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        // Close the file first, Windows cannot remove it while it is open
+        drop(self.writer.take());
+        match std::fs::remove_file(&self.temp) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                warn!("Failed to remove temporary file {}: {}", self.temp.display(), e);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// A directory filled under a temporary name next to its target and only
+/// renamed into place by `commit`. Dropping it uncommitted removes it with
+/// everything written so far. An existing directory is never replaced or
+/// merged into, so `Overwrite` is treated like `Error`.
+pub struct AtomicDir {
+    path: PathBuf,
+    temp: PathBuf,
+    committed: bool,
+}
+
+impl AtomicDir {
+    pub fn create(path: &Path) -> Result<Self, ScreenshotError> {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let temp = path.with_file_name(format!(".{}.{}.tmp", name, Uuid::new_v4().simple()));
+        if let Some(parent) = temp.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::create_dir(&temp)?;
+
+        Ok(Self { path: path.to_path_buf(), temp, committed: false })
+    }
+
+    /// Where the contents go until `commit`.
+    pub fn temp_path(&self) -> &Path {
+        &self.temp
+    }
+
+    /// Moves the directory to its final name as `collision` decides. Returns
+    /// the path it was saved under.
+    pub fn commit(mut self, collision: CollisionStrategy) -> Result<PathBuf, ScreenshotError> {
+        let collision = match collision {
+            CollisionStrategy::Overwrite => CollisionStrategy::Error,
+            other => other,
+        };
+        let target = place_with(&self.temp, &self.path, collision, rename_dir_no_clobber)?;
+        self.committed = true;
+        sync_directory(&target);
+        Ok(target)
+    }
+}
+
+impl Drop for AtomicDir {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        if let Err(e) = std::fs::remove_dir_all(&self.temp) {
+            warn!("Failed to remove temporary directory {}: {}", self.temp.display(), e);
+        }
+    }
+}
+
+/// Writes `path` through an `AtomicFile` filled in by `write`.
+pub fn write_atomic<F>(path: &Path, collision: CollisionStrategy, write: F) -> Result<PathBuf, ScreenshotError>
+where
+    F: FnOnce(&mut AtomicFile) -> Result<(), ScreenshotError>,
+{
+    let mut file = AtomicFile::create(path)?;
+    write(&mut file)?;
+    file.commit(collision)
+}
+
+/// Moves `temp` to `path`, or to a free name next to it.
+fn place(temp: &Path, path: &Path, collision: CollisionStrategy) -> Result<PathBuf, ScreenshotError> {
+    place_with(temp, path, collision, rename_no_clobber)
+}
+
+fn place_with(
+    temp: &Path,
+    path: &Path,
+    collision: CollisionStrategy,
+    rename_no_clobber: fn(&Path, &Path) -> std::io::Result<()>,
+) -> Result<PathBuf, ScreenshotError> {
+    if collision == CollisionStrategy::Overwrite {
+        std::fs::rename(temp, path)?;
+        return Ok(path.to_path_buf());
+    }
+
+    // Another process may take a name between choosing it and linking to it,
+    // so keep choosing until the no-clobber rename succeeds.
+    for _ in 0..100 {
+        let target = match collision {
+            CollisionStrategy::Counter => ensure_unique_filename(path.to_path_buf()),
+            CollisionStrategy::Uuid if path.exists() => with_suffix(path, &Uuid::new_v4().simple().to_string()),
+            _ => path.to_path_buf(),
+        };
+        match rename_no_clobber(temp, &target) {
+            Ok(()) => return Ok(target),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                if collision == CollisionStrategy::Error {
+                    return Err(ScreenshotError::FileExists(target));
+                }
+            },
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(ScreenshotError::FileExists(path.to_path_buf()))
+}
+
+/// `<stem>_<suffix>.<extension>` next to `path`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    match path.extension() {
+        Some(extension) => path.with_file_name(format!("{}_{}.{}", stem, suffix, extension.to_string_lossy())),
+        None => path.with_file_name(format!("{}_{}", stem, suffix)),
+    }
+}
+
+/// Renames `from` to `to`, failing with `AlreadyExists` instead of replacing
+/// it. A hard link gives that atomically; filesystems without hard links fall
+/// back to checking first.
+fn rename_no_clobber(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::hard_link(from, to) {
+        Ok(()) => {
+            if let Err(e) = std::fs::remove_file(from) {
+                warn!("Failed to remove temporary file {}: {}", from.display(), e);
+            }
+            Ok(())
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Err(e),
+        Err(_) if to.exists() => Err(std::io::ErrorKind::AlreadyExists.into()),
+        Err(_) => std::fs::rename(from, to),
+    }
+}
+
+/// Directories cannot be hard-linked, so this checks first; another process
+/// creating `to` in between makes the rename fail or, for an empty
+/// directory, replace it.
+fn rename_dir_no_clobber(from: &Path, to: &Path) -> std::io::Result<()> {
+    if to.symlink_metadata().is_ok() {
+        return Err(std::io::ErrorKind::AlreadyExists.into());
+    }
+    std::fs::rename(from, to)
+}
+
+/// Makes the rename itself durable. Not possible, or needed, everywhere.
+fn sync_directory(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        if let Err(e) = File::open(parent).and_then(|directory| directory.sync_all()) {
+            log::debug!("Failed to sync {}: {}", parent.display(), e);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
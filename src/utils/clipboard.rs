@@ -1,6 +1,11 @@
 use crate::error::ScreenshotError;
-use std::path::PathBuf;
-use log::{debug, warn};
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use log::debug;
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+use log::warn;
 
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
 use arboard::Clipboard;
@@ -8,20 +13,35 @@ use arboard::Clipboard;
 pub fn copy_file_to_clipboard(path: &PathBuf) -> Result<(), ScreenshotError> {
     debug!("Copying image to clipboard: {}", path.display());
 
+    let image_data = std::fs::read(path)?;
+    let img = image::load_from_memory(&image_data)?;
+    let rgba_img = img.to_rgba8();
+    let (width, height) = rgba_img.dimensions();
+
+    copy_image_to_clipboard(rgba_img.as_raw(), width, height)
+}
+
+/// Places an RGBA8 buffer on the clipboard as-is, alpha included.
+pub fn copy_image_to_clipboard(rgba: &[u8], width: u32, height: u32) -> Result<(), ScreenshotError> {
+    debug!("Copying {}x{} RGBA image to clipboard", width, height);
+
+    let expected = width as usize * height as usize * 4;
+    if rgba.len() != expected {
+        return Err(ScreenshotError::ClipboardError(format!(
+            "Image buffer is {} bytes, expected {} for {}x{} RGBA",
+            rgba.len(), expected, width, height
+        )));
+    }
+
     #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
     {
-        let image_data = std::fs::read(path)?;
-        let img = image::load_from_memory(&image_data)?;
-        let rgb_img = img.to_rgb8();
-        let (width, height) = rgb_img.dimensions();
-
         let mut clipboard = Clipboard::new()
             .map_err(|e| ScreenshotError::ClipboardError(e.to_string()))?;
 
         let img_data = arboard::ImageData {
             width: width as usize,
             height: height as usize,
-            bytes: rgb_img.into_raw().into(),
+            bytes: rgba.into(),
         };
 
         clipboard.set_image(img_data)
@@ -38,3 +58,346 @@ pub fn copy_file_to_clipboard(path: &PathBuf) -> Result<(), ScreenshotError> {
         ))
     }
 }
+
+/// Reads the current clipboard image as RGBA8, returning `(pixels, width, height)`.
+pub fn read_image_from_clipboard() -> Result<(Vec<u8>, u32, u32), ScreenshotError> {
+    debug!("Reading image from clipboard");
+
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    {
+        let mut clipboard = Clipboard::new()
+            .map_err(|e| ScreenshotError::ClipboardError(e.to_string()))?;
+
+        let img_data = clipboard.get_image()
+            .map_err(|e| ScreenshotError::ClipboardError(format!("No image on clipboard: {}", e)))?;
+
+        Ok((img_data.bytes.into_owned(), img_data.width as u32, img_data.height as u32))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        warn!("Clipboard not supported on this platform");
+        Err(ScreenshotError::PlatformNotSupported(
+            "Clipboard not supported on this platform".to_string()
+        ))
+    }
+}
+
+/// Which representations of a capture to put on the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardTarget {
+    Image,
+    Path,
+    Uri,
+    All,
+}
+
+impl FromStr for ClipboardTarget {
+    type Err = ScreenshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "image" => Ok(Self::Image),
+            "path" => Ok(Self::Path),
+            "uri" => Ok(Self::Uri),
+            "all" => Ok(Self::All),
+            _ => Err(ScreenshotError::ClipboardError(format!(
+                "Unknown clipboard target: {} (expected image, path, uri or all)", s
+            ))),
+        }
+    }
+}
+
+impl ClipboardTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Path => "path",
+            Self::Uri => "uri",
+            Self::All => "all",
+        }
+    }
+
+    fn wants_image(&self) -> bool {
+        matches!(self, Self::Image | Self::All)
+    }
+
+    fn wants_path(&self) -> bool {
+        matches!(self, Self::Path | Self::All)
+    }
+
+    fn wants_uri(&self) -> bool {
+        matches!(self, Self::Uri | Self::All)
+    }
+}
+
+/// One MIME type offered on the clipboard together with its bytes.
+#[derive(Debug, Clone)]
+pub struct ClipboardOffer {
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// Hidden subcommand the CLI runs to keep serving the clipboard after it exits.
+pub const HELPER_SUBCOMMAND: &str = "clipboard-serve";
+
+/// Line the helper prints on stdout once it owns the clipboard.
+pub const HELPER_READY: &str = "ready";
+
+/// How long to wait for the helper to own the clipboard or fail.
+#[cfg(target_os = "linux")]
+const HELPER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Builds the offers for `target` from an encoded PNG and/or a saved file.
+pub fn build_offers(png: Option<Vec<u8>>, file: Option<&Path>, target: ClipboardTarget) -> Vec<ClipboardOffer> {
+    let mut offers = Vec::new();
+
+    if target.wants_image() {
+        if let Some(png) = png {
+            offers.push(ClipboardOffer { mime: "image/png".to_string(), data: png });
+        }
+    }
+
+    if let Some(file) = file {
+        let absolute = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+
+        if target.wants_uri() {
+            let uri = file_uri(&absolute);
+            offers.push(ClipboardOffer {
+                mime: "text/uri-list".to_string(),
+                data: format!("{}\r\n", uri).into_bytes(),
+            });
+            offers.push(ClipboardOffer {
+                mime: "x-special/gnome-copied-files".to_string(),
+                data: format!("copy\n{}", uri).into_bytes(),
+            });
+        }
+
+        if target.wants_path() {
+            offers.push(ClipboardOffer {
+                mime: "text/plain;charset=utf-8".to_string(),
+                data: absolute.to_string_lossy().into_owned().into_bytes(),
+            });
+        }
+    }
+
+    offers
+}
+
+/// `file://` URI for an absolute path, percent-encoding everything but unreserved bytes and `/`.
+pub fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+pub fn encode_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>, ScreenshotError> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).write_image(rgba, width, height, ColorType::Rgba8)?;
+    Ok(png)
+}
+
+/// Offers a capture on the clipboard as `target`. When `helper` is set, a
+/// detached copy of that executable keeps serving every target after this
+/// process exits; otherwise a single representation is copied in-process.
+pub fn offer_capture(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    file: Option<&Path>,
+    target: ClipboardTarget,
+    helper: Option<&Path>,
+) -> Result<(), ScreenshotError> {
+    #[cfg(target_os = "linux")]
+    if let Some(helper) = helper {
+        return spawn_helper(helper, rgba, width, height, file, target);
+    }
+    let _ = helper;
+
+    let file_text = |to_text: fn(&Path) -> String| {
+        file.map(to_text).ok_or_else(|| ScreenshotError::ClipboardError(
+            "No saved file to put on the clipboard".to_string()
+        ))
+    };
+
+    match target {
+        ClipboardTarget::Image | ClipboardTarget::All => copy_image_to_clipboard(rgba, width, height),
+        ClipboardTarget::Path => copy_text_to_clipboard(&file_text(|p| p.to_string_lossy().into_owned())?),
+        ClipboardTarget::Uri => copy_text_to_clipboard(&file_text(file_uri)?),
+    }
+}
+
+pub fn copy_text_to_clipboard(text: &str) -> Result<(), ScreenshotError> {
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    {
+        let mut clipboard = Clipboard::new()
+            .map_err(|e| ScreenshotError::ClipboardError(e.to_string()))?;
+        clipboard.set_text(text)
+            .map_err(|e| ScreenshotError::ClipboardError(e.to_string()))?;
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = text;
+        Err(ScreenshotError::PlatformNotSupported(
+            "Clipboard not supported on this platform".to_string()
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn spawn_helper(
+    helper: &Path,
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    file: Option<&Path>,
+    target: ClipboardTarget,
+) -> Result<(), ScreenshotError> {
+    use crate::utils::atomic::{write_atomic, CollisionStrategy};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc;
+
+    let mut command = Command::new(helper);
+    command.arg(HELPER_SUBCOMMAND).arg("--target").arg(target.as_str());
+
+    let png_path = if target.wants_image() {
+        let path = std::env::temp_dir().join(format!("screenshot_clipboard_{}.png", uuid::Uuid::new_v4()));
+        let png = encode_png(rgba, width, height)?;
+        write_atomic(&path, CollisionStrategy::Overwrite, |file| Ok(file.write_all(&png)?))?;
+        command.arg("--png").arg(&path);
+        Some(path)
+    } else {
+        None
+    };
+
+    if let Some(file) = file {
+        command.arg("--file").arg(file);
+    }
+
+    debug!("Spawning clipboard helper: {:?}", command);
+
+    let failed = |message: String| {
+        if let Some(ref path) = png_path {
+            let _ = std::fs::remove_file(path);
+        }
+        ScreenshotError::ClipboardError(message)
+    };
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| failed(format!("Failed to start clipboard helper: {}", e)))?;
+
+    // The helper says when it owns the clipboard; if it exits first, its
+    // stderr says why.
+    let (Some(stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(failed("Clipboard helper has no output pipes".to_string()));
+    };
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = BufReader::new(stdout).read_line(&mut line);
+        let _ = sender.send(line);
+    });
+
+    match receiver.recv_timeout(HELPER_TIMEOUT) {
+        Ok(line) if line.trim_end() == HELPER_READY => {
+            debug!("Clipboard helper {} owns the clipboard", child.id());
+            Ok(())
+        },
+        Ok(_) => {
+            let mut message = String::new();
+            let _ = stderr.read_to_string(&mut message);
+            let status = child.wait().map(|status| status.to_string()).unwrap_or_default();
+            let message = message.trim();
+            Err(failed(match message.is_empty() {
+                true => format!("Clipboard helper exited ({})", status),
+                false => format!("Clipboard helper failed: {}", message),
+            }))
+        },
+        Err(_) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(failed(format!("Clipboard helper did not take the clipboard within {:?}", HELPER_TIMEOUT)))
+        },
+    }
+}
+
+/// Entry point of the helper process. Serves every offer until another
+/// application takes over the clipboard, printing [`HELPER_READY`] on stdout
+/// once it owns it. `png` is a temporary file and is removed.
+pub fn serve_clipboard(
+    png: Option<&Path>,
+    file: Option<&Path>,
+    target: ClipboardTarget,
+) -> Result<(), ScreenshotError> {
+    let png_data = match png {
+        Some(path) => {
+            let data = std::fs::read(path);
+            let _ = std::fs::remove_file(path);
+            Some(data?)
+        },
+        None => None,
+    };
+
+    let offers = build_offers(png_data, file, target);
+    if offers.is_empty() {
+        return Err(ScreenshotError::ClipboardError("Nothing to put on the clipboard".to_string()));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let ready = || {
+            use std::io::Write;
+            let mut stdout = std::io::stdout();
+            let _ = writeln!(stdout, "{}", HELPER_READY).and_then(|_| stdout.flush());
+        };
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            match serve_wayland(&offers, &ready) {
+                Ok(()) => return Ok(()),
+                Err(e) => debug!("Wayland clipboard unavailable ({}), trying X11", e),
+            }
+        }
+        crate::utils::x11_clipboard::serve(&offers, &ready)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Err(ScreenshotError::PlatformNotSupported(
+            "Clipboard helper is only used on Linux".to_string()
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn serve_wayland(offers: &[ClipboardOffer], ready: &dyn Fn()) -> Result<(), ScreenshotError> {
+    use wl_clipboard_rs::copy::{MimeSource, MimeType, Options, Source};
+
+    let sources = offers
+        .iter()
+        .map(|offer| MimeSource {
+            source: Source::Bytes(offer.data.clone().into_boxed_slice()),
+            mime_type: MimeType::Specific(offer.mime.clone()),
+        })
+        .collect();
+
+    let mut options = Options::new();
+    options.foreground(true);
+    let copy = options.prepare_copy_multi(sources)
+        .map_err(|e| ScreenshotError::ClipboardError(e.to_string()))?;
+    ready();
+    copy.serve()
+        .map_err(|e| ScreenshotError::ClipboardError(e.to_string()))
+}
@@ -1,2 +1,8 @@
+pub mod atomic;
 pub mod clipboard;
 pub mod path;
+pub mod size;
+pub mod template;
+pub mod time;
+#[cfg(target_os = "linux")]
+pub mod x11_clipboard;
@@ -1,21 +1,196 @@
 use crate::{config::Config, error::ScreenshotError};
-use std::path::PathBuf;
-use chrono::Local;
+use crate::utils::template::{escape_strftime, expand_filename, expand_text, filename_tokens, FilenameValues};
+use std::path::{Component, Path, PathBuf};
+use chrono::{DateTime, Local};
 use uuid::Uuid;
 
-pub fn validate_output_path(path: &PathBuf) -> Result<(), ScreenshotError> {
-    if path.to_string_lossy().contains("..") {
-        return Err(ScreenshotError::PermissionDenied(
-            "Path traversal not allowed".to_string()
-        ));
+/// Checks that output may be written into `directory` and returns it
+/// canonicalized. `~` and environment variables are expanded, symlinks are
+/// resolved, and when `allowed_roots` is not empty the result must lie inside
+/// one of them, so a symlink cannot lead out of them. The directory is created
+/// if needed; [`check_writable`] tells whether files can be created in it.
+pub fn validate_output_path(directory: &Path, allowed_roots: &[PathBuf]) -> Result<PathBuf, ScreenshotError> {
+    let requested = expand_path(directory)?;
+    let resolved = resolve(&requested)?;
+
+    if !allowed_roots.is_empty() {
+        let roots = allowed_roots
+            .iter()
+            .map(|root| expand_path(root).and_then(|root| resolve(&root)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !roots.iter().any(|root| resolved.starts_with(root)) {
+            let list = roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>().join(", ");
+            let lexical = absolute(&requested)?;
+            return Err(ScreenshotError::PermissionDenied(
+                if resolved != lexical && roots.iter().any(|root| lexical.starts_with(root)) {
+                    format!("{} follows a symlink to {}, outside the allowed roots ({})",
+                            directory.display(), resolved.display(), list)
+                } else {
+                    format!("{} is outside the allowed roots ({})", resolved.display(), list)
+                }
+            ));
+        }
+    }
+
+    if resolved.exists() && !resolved.is_dir() {
+        return Err(ScreenshotError::PermissionDenied(format!("{} is not a directory", resolved.display())));
     }
-    
-    if let Some(parent) = path.parent() {
-        if !parent.exists() {
-            std::fs::create_dir_all(parent)?;
+    std::fs::create_dir_all(&resolved).map_err(|e| ScreenshotError::PermissionDenied(
+        format!("cannot create {}: {}", resolved.display(), e)
+    ))?;
+
+    Ok(resolved)
+}
+
+/// Creates and removes a probe file in `directory`, which is more reliable
+/// than reading permission bits (ACLs, read-only mounts).
+pub fn check_writable(directory: &Path) -> Result<(), ScreenshotError> {
+    let probe = directory.join(format!(".screenshot-write-test-{}", Uuid::new_v4().simple()));
+    std::fs::File::create(&probe).map_err(|e| ScreenshotError::PermissionDenied(
+        format!("{} is not writable: {}", directory.display(), e)
+    ))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// `path` made absolute against the current directory, with `.` and `..`
+/// applied lexically.
+fn absolute(path: &Path) -> Result<PathBuf, ScreenshotError> {
+    let mut absolute = std::env::current_dir()?;
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                absolute.pop();
+            },
+            component => absolute.push(component),
         }
     }
+    Ok(absolute)
+}
+
+/// Canonicalizes the part of `path` that exists, following symlinks the way
+/// the filesystem would, and appends the rest.
+fn resolve(path: &Path) -> Result<PathBuf, ScreenshotError> {
+    let mut resolved = std::env::current_dir()?;
+    let mut existing = true;
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir if !existing => {
+                resolved.pop();
+            },
+            component => {
+                resolved.push(component);
+                if !existing {
+                    continue;
+                }
+                match std::fs::canonicalize(&resolved) {
+                    Ok(canonical) => resolved = canonical,
+                    Err(_) if std::fs::symlink_metadata(&resolved).is_ok() => {
+                        return Err(ScreenshotError::PermissionDenied(format!(
+                            "{} is a symlink to a missing target", resolved.display()
+                        )));
+                    },
+                    Err(_) => existing = false,
+                }
+            },
+        }
+    }
+    Ok(resolved)
+}
+
+/// Replaces a leading `~` with the home directory.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Expands a leading `~` and `$VAR` or `${VAR}` references. A `$` not
+/// followed by a variable name is kept as it is.
+pub fn expand_path(path: &Path) -> Result<PathBuf, ScreenshotError> {
+    let path = expand_home(path);
+    let text = path.to_string_lossy();
+    if !text.contains('$') {
+        return Ok(path);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_ref();
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => return Err(ScreenshotError::PermissionDenied(format!(
+                    "{}: '${{' is never closed", path.display()
+                ))),
+            },
+            None => {
+                let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            },
+        };
+        if name.is_empty() {
+            out.push('$');
+            continue;
+        }
+
+        let value = std::env::var(name).map_err(|_| ScreenshotError::PermissionDenied(format!(
+            "{}: environment variable {} is not set", path.display(), name
+        )))?;
+        out.push_str(&value);
+        rest = after;
+    }
+    out.push_str(rest);
+    Ok(PathBuf::from(out))
+}
 
+fn is_templated(component: &Component) -> bool {
+    let text = component.as_os_str().to_string_lossy();
+    text.contains('%') || text.contains('{')
+}
+
+/// The directory a capture of `mode` taken at `now` is saved in: `template`
+/// with `~`, environment variables, strftime specifiers and `{mode}`,
+/// `{user}`, `{host}` and `{date}` expanded.
+pub fn resolve_output_directory(
+    template: &Path,
+    mode: &str,
+    now: &DateTime<Local>,
+) -> Result<PathBuf, ScreenshotError> {
+    let template = expand_path(template)?;
+    if !template.components().any(|component| is_templated(&component)) {
+        return Ok(template);
+    }
+
+    let text = template.to_string_lossy().replace("{mode}", &escape_strftime(&sanitize_filename(mode)));
+    Ok(PathBuf::from(expand_text(&text, now)?))
+}
+
+/// The part of an output directory template before its first templated
+/// component, under which all captures end up.
+pub fn output_root(template: &Path) -> PathBuf {
+    expand_path(template)
+        .unwrap_or_else(|_| expand_home(template))
+        .components()
+        .take_while(|component| !is_templated(component))
+        .collect()
+}
+
+/// Renames `from` to `to`, copying and deleting when they are on different filesystems.
+pub fn move_file(from: &Path, to: &Path) -> Result<(), ScreenshotError> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)?;
     Ok(())
 }
 
@@ -29,45 +204,86 @@ pub fn sanitize_filename(name: &str) -> String {
         .collect()
 }
 
-pub fn generate_filename(config: &Config, prefix: &str) -> Result<String, ScreenshotError> {
-    let filename = if let Some(ref custom) = config.custom_filename {
-        format!("{}.{}", sanitize_filename(custom), config.default_format)
-    } else {
-        let timestamp = Local::now();
-        let formatted = timestamp.format(&config.filename_template).to_string();
-        let name = if prefix.is_empty() {
+/// The file name for a capture saved in `directory`: `custom_filename`, or
+/// `filename_template` expanded with `values`. Templates without tokens are
+/// prefixed with `prefix`; `{counter}` takes the first number whose name is
+/// still free in `directory`.
+pub fn generate_filename(
+    config: &Config,
+    prefix: &str,
+    values: &FilenameValues,
+    directory: &Path,
+) -> Result<String, ScreenshotError> {
+    if let Some(ref custom) = config.custom_filename {
+        return Ok(format!("{}.{}", sanitize_filename(custom), config.default_format));
+    }
+
+    let timestamp = Local::now();
+    let tokens = filename_tokens(&config.filename_template);
+    let mut counter = 1;
+    loop {
+        let formatted = expand_filename(&config.filename_template, values, counter, &timestamp)?;
+        let name = if formatted.is_empty() {
+            // e.g. "{window_title}" for a capture without a window
+            if prefix.is_empty() { "screenshot".to_string() } else { prefix.to_string() }
+        } else if prefix.is_empty() || !tokens.is_empty() {
             formatted
         } else {
             format!("{}_{}", prefix, formatted)
         };
+        let filename = format!("{}.{}", name, config.default_format);
 
-        format!("{}.{}", name, config.default_format)
-    };
-
-    Ok(filename)
+        if !tokens.contains(&"counter") || !directory.join(&filename).exists() {
+            return Ok(filename);
+        }
+        counter += 1;
+    }
 }
 
+/// `path`, or the first of `<stem>_1.<ext>`, `<stem>_2.<ext>`, ... that does
+/// not exist yet.
 pub fn ensure_unique_filename(path: PathBuf) -> PathBuf {
     if !path.exists() {
         return path;
     }
 
-    let stem = path.file_stem().unwrap().to_string_lossy();
-    let extension = path.extension().unwrap().to_string_lossy();
-    let parent = path.parent().unwrap();
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
 
     let mut counter = 1;
     loop {
-        let new_name = format!("{}_{}.{}", stem, counter, extension);
-        let new_path = parent.join(new_name);
+        let new_path = path.with_file_name(format!("{}_{}{}", stem, counter, extension));
         if !new_path.exists() {
             return new_path;
         }
         counter += 1;
         if counter > 9999 {
             let uuid = Uuid::new_v4();
-            let unique_name = format!("{}_{}_{}.{}", stem, counter, uuid, extension);
-            return parent.join(unique_name);
+            return path.with_file_name(format!("{}_{}_{}{}", stem, counter, uuid, extension));
         }
     }
 }
+
+/// Opens `path` in the desktop's default application for its type.
+pub fn open_with_default_app(path: &Path) -> Result<(), ScreenshotError> {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("open", &[])
+    } else if cfg!(target_os = "windows") {
+        ("cmd", &["/C", "start", ""])
+    } else {
+        ("xdg-open", &[])
+    };
+
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .map_err(|e| ScreenshotError::ExternalCommandFailed(format!("Failed to run {}: {}", program, e)))?;
+
+    if !status.success() {
+        return Err(ScreenshotError::ExternalCommandFailed(format!(
+            "{} could not open {}", program, path.display()
+        )));
+    }
+    Ok(())
+}
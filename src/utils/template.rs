@@ -0,0 +1,186 @@
+use crate::{error::ScreenshotError, utils::path::sanitize_filename};
+use chrono::{DateTime, Local};
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// Runs chrono strftime over `template`, reporting bad specifiers instead of panicking.
+pub fn format_timestamp(template: &str, now: &DateTime<Local>) -> Result<String, ScreenshotError> {
+    let mut out = String::new();
+    write!(out, "{}", now.format(template))
+        .map_err(|_| ScreenshotError::InvalidTemplate(template.to_string()))?;
+    Ok(out)
+}
+
+/// Expands `{user}`, `{host}` and `{date}`, then applies strftime. A `%` in
+/// the user or host name is kept as it is.
+pub fn expand_text(template: &str, now: &DateTime<Local>) -> Result<String, ScreenshotError> {
+    let text = template
+        .replace("{user}", &escape_strftime(&username()))
+        .replace("{host}", &escape_strftime(&hostname()))
+        .replace("{date}", "%Y-%m-%d");
+
+    format_timestamp(&text, now)
+}
+
+/// `value` with each `%` doubled, so strftime leaves it alone.
+pub(crate) fn escape_strftime(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// Values for the `{...}` tokens of `filename_template`. Values that are not
+/// known, such as the window title of a fullscreen capture, expand to nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FilenameValues<'a> {
+    pub mode: &'a str,
+    pub screen: Option<usize>,
+    pub width: u32,
+    pub height: u32,
+    pub window_title: Option<&'a str>,
+    pub window_class: Option<&'a str>,
+    /// The perceptual hash of the saved image, for `{hash8}`.
+    pub hash: Option<u64>,
+}
+
+/// Tokens `filename_template` may contain, besides strftime specifiers.
+pub const FILENAME_TOKENS: [&str; 12] = [
+    "mode", "screen", "width", "height", "window_title", "window_class",
+    "host", "user", "date", "counter", "uuid", "hash8",
+];
+
+/// Tokens that take a zero-padded width, as in `{counter:04}`.
+const NUMERIC_TOKENS: [&str; 4] = ["screen", "width", "height", "counter"];
+
+/// Window titles can be arbitrarily long; file names cannot.
+const MAX_TOKEN_CHARS: usize = 64;
+
+enum Segment<'t> {
+    Text(&'t str),
+    Token { name: &'t str, width: Option<usize> },
+}
+
+fn parse_filename_template(template: &str) -> Result<Vec<Segment<'_>>, ScreenshotError> {
+    let invalid = |reason: String| ScreenshotError::InvalidTemplate(format!("filename_template \"{}\": {}", template, reason));
+
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        if open > 0 {
+            segments.push(Segment::Text(&rest[..open]));
+        }
+        let close = rest[open..]
+            .find('}')
+            .map(|close| open + close)
+            .ok_or_else(|| invalid(format!("'{{' at \"{}\" is never closed", &rest[open..])))?;
+
+        let token = &rest[open + 1..close];
+        let (name, spec) = match token.split_once(':') {
+            Some((name, spec)) => (name, Some(spec)),
+            None => (token, None),
+        };
+        if !FILENAME_TOKENS.contains(&name) {
+            return Err(invalid(format!("unknown token {{{}}}, expected one of {}", token, token_list())));
+        }
+        let width = match spec {
+            None => None,
+            Some(_) if !NUMERIC_TOKENS.contains(&name) => {
+                return Err(invalid(format!("{{{}}} does not take a width", name)));
+            },
+            Some(spec) => Some(spec.parse::<usize>().ok().filter(|width| *width <= 20).ok_or_else(|| {
+                invalid(format!("\"{}\" in {{{}}} is not a width such as {{{}:04}}", spec, token, name))
+            })?),
+        };
+
+        segments.push(Segment::Token { name, width });
+        rest = &rest[close + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    Ok(segments)
+}
+
+fn token_list() -> String {
+    FILENAME_TOKENS.iter().map(|token| format!("{{{}}}", token)).collect::<Vec<_>>().join(", ")
+}
+
+/// Checks that `template` only uses known tokens and valid strftime specifiers.
+pub fn validate_filename_template(template: &str) -> Result<(), ScreenshotError> {
+    expand_filename(template, &FilenameValues::default(), 1, &Local::now()).map(|_| ())
+}
+
+/// The tokens used in `template`, or none when it does not parse.
+pub fn filename_tokens(template: &str) -> Vec<&str> {
+    parse_filename_template(template)
+        .map(|segments| segments
+            .into_iter()
+            .filter_map(|segment| match segment {
+                Segment::Token { name, .. } => Some(name),
+                Segment::Text(_) => None,
+            })
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Expands the tokens of `template` and applies strftime to the text around
+/// them. Every value goes through `sanitize_filename`, and is never itself
+/// read as a strftime specifier.
+pub fn expand_filename(
+    template: &str,
+    values: &FilenameValues,
+    counter: u32,
+    now: &DateTime<Local>,
+) -> Result<String, ScreenshotError> {
+    let mut out = String::new();
+    for segment in parse_filename_template(template)? {
+        let (name, width) = match segment {
+            Segment::Text(text) => {
+                out.push_str(&format_timestamp(text, now).map_err(|_| ScreenshotError::InvalidTemplate(
+                    format!("filename_template \"{}\": invalid strftime specifier in \"{}\"", template, text)
+                ))?);
+                continue;
+            },
+            Segment::Token { name, width } => (name, width),
+        };
+
+        let number = |value: u64| match width {
+            Some(width) => format!("{:0width$}", value, width = width),
+            None => value.to_string(),
+        };
+        let value = match name {
+            "mode" => values.mode.to_string(),
+            "screen" => values.screen.map(|screen| number(screen as u64)).unwrap_or_default(),
+            "width" => number(values.width as u64),
+            "height" => number(values.height as u64),
+            "window_title" => values.window_title.unwrap_or_default().to_string(),
+            "window_class" => values.window_class.unwrap_or_default().to_string(),
+            "host" => hostname(),
+            "user" => username(),
+            "date" => now.format("%Y-%m-%d").to_string(),
+            "counter" => number(counter as u64),
+            "uuid" => Uuid::new_v4().to_string(),
+            "hash8" => values.hash.map(|hash| format!("{:016x}", hash)[..8].to_string()).unwrap_or_default(),
+            _ => unreachable!("tokens are checked while parsing"),
+        };
+        out.push_str(&sanitize_filename(value.trim()).chars().take(MAX_TOKEN_CHARS).collect::<String>());
+    }
+    Ok(out)
+}
+
+pub fn username() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+pub fn hostname() -> String {
+    if let Ok(name) = std::fs::read_to_string("/proc/sys/kernel/hostname") {
+        let name = name.trim();
+        if !name.is_empty() {
+            return name.to_string();
+        }
+    }
+
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "localhost".to_string())
+}
@@ -0,0 +1,78 @@
+use crate::error::ScreenshotError;
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use std::time::Duration;
+
+/// Parses durations such as `500ms`, `30s`, `1.5m`, `2h` or `7d`. A bare number is seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, ScreenshotError> {
+    match parse_seconds(value)? {
+        seconds if seconds > 0.0 => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(ScreenshotError::InvalidDuration(value.to_string())),
+    }
+}
+
+/// Like [`parse_duration`], but also accepts zero, for waits that can be skipped.
+pub fn parse_duration_or_zero(value: &str) -> Result<Duration, ScreenshotError> {
+    parse_seconds(value).map(Duration::from_secs_f64)
+}
+
+fn parse_seconds(value: &str) -> Result<f64, ScreenshotError> {
+    let invalid = || ScreenshotError::InvalidDuration(value.to_string());
+
+    let trimmed = value.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" | "min" => number * 60.0,
+        "h" => number * 3600.0,
+        "d" => number * 86400.0,
+        _ => return Err(invalid()),
+    };
+
+    if !seconds.is_finite() || seconds >= u64::MAX as f64 {
+        return Err(invalid());
+    }
+
+    Ok(seconds)
+}
+
+/// A point in the past given as a duration ago (`2h`, `7d`), a date
+/// (`2024-05-01`) or a local date and time (`2024-05-01 14:30`).
+pub fn parse_since(value: &str, now: &DateTime<Local>) -> Result<DateTime<Local>, ScreenshotError> {
+    let invalid = || ScreenshotError::InvalidDuration(value.to_string());
+    let trimmed = value.trim();
+
+    if let Ok(ago) = parse_duration(trimmed) {
+        let ago = ChronoDuration::from_std(ago).map_err(|_| invalid())?;
+        return now.checked_sub_signed(ago).ok_or_else(invalid);
+    }
+
+    let naive = NaiveDateTime::parse_from_str(trimmed, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDate::parse_from_str(trimmed, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN)))
+        .map_err(|_| invalid())?;
+    Local.from_local_datetime(&naive).earliest().ok_or_else(invalid)
+}
+
+/// Next local time matching `HH:MM` or `HH:MM:SS`, today if still ahead, otherwise tomorrow.
+pub fn parse_until(value: &str, now: &DateTime<Local>) -> Result<DateTime<Local>, ScreenshotError> {
+    let invalid = || ScreenshotError::InvalidDuration(value.to_string());
+
+    let time = NaiveTime::parse_from_str(value.trim(), "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value.trim(), "%H:%M"))
+        .map_err(|_| invalid())?;
+
+    let mut date = now.date_naive();
+    loop {
+        if let Some(candidate) = Local.from_local_datetime(&date.and_time(time)).earliest() {
+            if candidate > *now {
+                return Ok(candidate);
+            }
+        }
+        date = date.checked_add_signed(ChronoDuration::days(1)).ok_or_else(invalid)?;
+    }
+}
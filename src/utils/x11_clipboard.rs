@@ -0,0 +1,166 @@
+use crate::{error::ScreenshotError, utils::clipboard::ClipboardOffer};
+use log::debug;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, EventMask,
+    PropMode, Property, SelectionNotifyEvent, Window, WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+/// An INCR transfer in progress for data too large for one property write.
+struct Transfer {
+    requestor: Window,
+    property: Atom,
+    target: Atom,
+    offer: usize,
+    offset: usize,
+}
+
+fn x11_error<E: std::fmt::Display>(e: E) -> ScreenshotError {
+    ScreenshotError::ClipboardError(format!("X11 clipboard: {}", e))
+}
+
+/// Owns the CLIPBOARD selection and answers requests for every offer until
+/// another client takes ownership. `ready` is called once the selection is ours.
+pub fn serve(offers: &[ClipboardOffer], ready: &dyn Fn()) -> Result<(), ScreenshotError> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(x11_error)?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let window = conn.generate_id().map_err(x11_error)?;
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0, 0, 1, 1, 0,
+        WindowClass::INPUT_ONLY,
+        COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    ).map_err(x11_error)?;
+
+    let atom = |name: &str| -> Result<Atom, ScreenshotError> {
+        Ok(conn.intern_atom(false, name.as_bytes())
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?
+            .atom)
+    };
+
+    let clipboard = atom("CLIPBOARD")?;
+    let targets = atom("TARGETS")?;
+    let timestamp = atom("TIMESTAMP")?;
+    let incr = atom("INCR")?;
+
+    // Every target atom we answer for, mapped to the offer that backs it.
+    let mut served: Vec<(Atom, usize)> = Vec::new();
+    for (i, offer) in offers.iter().enumerate() {
+        served.push((atom(&offer.mime)?, i));
+        if offer.mime.starts_with("text/plain") {
+            for alias in ["UTF8_STRING", "STRING", "TEXT", "text/plain"] {
+                served.push((atom(alias)?, i));
+            }
+        }
+    }
+
+    conn.set_selection_owner(window, clipboard, CURRENT_TIME).map_err(x11_error)?;
+    let owner = conn.get_selection_owner(clipboard)
+        .map_err(x11_error)?
+        .reply()
+        .map_err(x11_error)?
+        .owner;
+    if owner != window {
+        return Err(ScreenshotError::ClipboardError("Could not take ownership of the clipboard".to_string()));
+    }
+    conn.flush().map_err(x11_error)?;
+    ready();
+
+    debug!("Serving {} clipboard targets on window {}", served.len(), window);
+
+    let chunk = (conn.maximum_request_bytes() / 4).max(4096);
+    let mut transfers: Vec<Transfer> = Vec::new();
+
+    loop {
+        match conn.wait_for_event().map_err(x11_error)? {
+            Event::SelectionRequest(req) => {
+                let property = if req.property == NONE { req.target } else { req.property };
+                let mut reply_property = property;
+
+                if req.target == targets {
+                    let mut list = vec![targets, timestamp];
+                    list.extend(served.iter().map(|&(target, _)| target));
+                    conn.change_property32(PropMode::REPLACE, req.requestor, property, AtomEnum::ATOM, &list)
+                        .map_err(x11_error)?;
+                } else if req.target == timestamp {
+                    conn.change_property32(PropMode::REPLACE, req.requestor, property, AtomEnum::INTEGER, &[CURRENT_TIME])
+                        .map_err(x11_error)?;
+                } else if let Some(&(_, offer)) = served.iter().find(|&&(target, _)| target == req.target) {
+                    let data = &offers[offer].data;
+                    if data.len() > chunk {
+                        conn.change_window_attributes(
+                            req.requestor,
+                            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+                        ).map_err(x11_error)?;
+                        conn.change_property32(PropMode::REPLACE, req.requestor, property, incr, &[data.len() as u32])
+                            .map_err(x11_error)?;
+                        transfers.retain(|t| !(t.requestor == req.requestor && t.property == property));
+                        transfers.push(Transfer {
+                            requestor: req.requestor,
+                            property,
+                            target: req.target,
+                            offer,
+                            offset: 0,
+                        });
+                    } else {
+                        conn.change_property8(PropMode::REPLACE, req.requestor, property, req.target, data)
+                            .map_err(x11_error)?;
+                    }
+                } else {
+                    reply_property = NONE;
+                }
+
+                let notify = SelectionNotifyEvent {
+                    response_type: SELECTION_NOTIFY_EVENT,
+                    sequence: 0,
+                    time: req.time,
+                    requestor: req.requestor,
+                    selection: req.selection,
+                    target: req.target,
+                    property: reply_property,
+                };
+                conn.send_event(false, req.requestor, EventMask::NO_EVENT, notify).map_err(x11_error)?;
+                conn.flush().map_err(x11_error)?;
+            },
+            Event::PropertyNotify(ev) if ev.state == Property::DELETE => {
+                let Some(pos) = transfers.iter().position(|t| t.requestor == ev.window && t.property == ev.atom) else {
+                    continue;
+                };
+
+                let transfer = &mut transfers[pos];
+                let data = &offers[transfer.offer].data;
+                let end = (transfer.offset + chunk).min(data.len());
+
+                // A zero-length write after the last chunk ends the transfer.
+                conn.change_property8(
+                    PropMode::REPLACE,
+                    transfer.requestor,
+                    transfer.property,
+                    transfer.target,
+                    &data[transfer.offset..end],
+                ).map_err(x11_error)?;
+
+                if transfer.offset == data.len() {
+                    transfers.remove(pos);
+                } else {
+                    transfer.offset = end;
+                }
+                conn.flush().map_err(x11_error)?;
+            },
+            Event::SelectionClear(ev) if ev.selection == clipboard => {
+                debug!("Clipboard taken over by another client, exiting");
+                return Ok(());
+            },
+            _ => {},
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use screenshot_tool::utils::atomic::{write_atomic, CollisionStrategy};
+use screenshot_tool::ScreenshotError;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+fn write(path: &Path, collision: CollisionStrategy, contents: &str) -> Result<PathBuf, ScreenshotError> {
+    write_atomic(path, collision, |file| Ok(file.write_all(contents.as_bytes())?))
+}
+
+fn names(directory: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn counter_keeps_both_files() {
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    let path = directory.join("shot.png");
+
+    assert_eq!(write(&path, CollisionStrategy::Counter, "one").unwrap(), path);
+    assert_eq!(write(&path, CollisionStrategy::Counter, "two").unwrap(), directory.join("shot_1.png"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "one");
+    assert_eq!(names(directory), vec!["shot.png", "shot_1.png"]);
+}
+
+#[test]
+fn overwrite_error_and_uuid_strategies() {
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    let path = directory.join("shot.png");
+    write(&path, CollisionStrategy::Error, "one").unwrap();
+
+    write(&path, CollisionStrategy::Overwrite, "two").unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "two");
+
+    assert!(matches!(write(&path, CollisionStrategy::Error, "three"), Err(ScreenshotError::FileExists(_))));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "two");
+
+    let saved = write(&path, CollisionStrategy::Uuid, "four").unwrap();
+    assert_ne!(saved, path);
+    assert_eq!(saved.extension().unwrap(), "png");
+    assert_eq!(std::fs::read_to_string(&saved).unwrap(), "four");
+    assert_eq!(names(directory).len(), 2);
+}
+
+#[test]
+fn failed_writes_leave_nothing_behind() {
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    let path = directory.join("shot.png");
+
+    let result = write_atomic(&path, CollisionStrategy::Counter, |file| {
+        file.write_all(b"half an image")?;
+        Err(ScreenshotError::CaptureFailed("encoder gave up".to_string()))
+    });
+    assert!(result.is_err());
+    assert!(names(directory).is_empty());
+}
+
+#[test]
+fn strategies_parse() {
+    assert_eq!("UUID".parse::<CollisionStrategy>().unwrap(), CollisionStrategy::Uuid);
+    assert!("rename".parse::<CollisionStrategy>().is_err());
+}
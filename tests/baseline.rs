@@ -0,0 +1,110 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use image::{Rgba, RgbaImage};
+use screenshot_tool::baseline::{write_report, BaselineSettings, BaselineStore};
+use screenshot_tool::capture::region::Region;
+use screenshot_tool::diff::DiffOptions;
+use screenshot_tool::ScreenshotError;
+
+fn white() -> RgbaImage {
+    RgbaImage::from_pixel(20, 10, Rgba([255, 255, 255, 255]))
+}
+
+fn with_black(pixels: &[(u32, u32)]) -> RgbaImage {
+    let mut image = white();
+    for &(x, y) in pixels {
+        image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+    }
+    image
+}
+
+fn region(x: i32, y: i32, width: u32, height: u32) -> Region {
+    Region { x, y, width, height }
+}
+
+#[test]
+fn approved_baselines_are_checked_against_captures() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = BaselineStore::new(directory.path());
+
+    let path = store.approve("pages/login", &white(), &BaselineSettings::default()).unwrap();
+    assert_eq!(path, directory.path().join("pages/login.png"));
+    assert!(directory.path().join("pages/login.json").exists());
+
+    let check = store.check("pages/login", white(), &DiffOptions::default(), None).unwrap();
+    assert!(check.passed);
+
+    let check = store.check("pages/login", with_black(&[(1, 1)]), &DiffOptions::default(), None).unwrap();
+    assert!(!check.passed);
+    assert_eq!(check.report.changed_pixels, 1);
+    // A threshold on the command line overrides the stored one
+    assert!(store.check("pages/login", with_black(&[(1, 1)]), &DiffOptions::default(), Some(0.01)).unwrap().passed);
+
+    // A different size never passes, whatever the threshold
+    let larger = RgbaImage::from_pixel(21, 10, Rgba([255, 255, 255, 255]));
+    assert!(!store.check("pages/login", larger, &DiffOptions::default(), Some(1.0)).unwrap().passed);
+
+    assert!(matches!(store.check("missing", white(), &DiffOptions::default(), None), Err(ScreenshotError::BaselineNotFound(_))));
+    for name in ["", "../escape", "a//b", "bad name"] {
+        assert!(matches!(store.image_path(name), Err(ScreenshotError::InvalidBaselineName(_))), "{}", name);
+    }
+}
+
+#[test]
+fn stored_settings_apply_to_checks() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = BaselineStore::new(directory.path());
+    let settings = BaselineSettings { ignore: vec![region(0, 0, 5, 5)], antialiasing: true, ..BaselineSettings::default() };
+    store.approve("home", &with_black(&[(10, 5)]), &settings).unwrap();
+
+    // Inside the stored ignore area, and an edge shifted by one pixel
+    let check = store.check("home", with_black(&[(2, 2), (11, 5)]), &DiffOptions::default(), None).unwrap();
+    assert!(check.passed, "{:?}", check.report);
+    assert_eq!((check.report.ignored_pixels, check.report.antialiased_pixels), (25, 2));
+
+    // Extra ignore areas add to the stored ones
+    let actual = with_black(&[(10, 5), (18, 8)]);
+    assert!(!store.check("home", actual.clone(), &DiffOptions::default(), None).unwrap().passed);
+    let extra = DiffOptions { ignore: vec![region(15, 5, 5, 5)], ..DiffOptions::default() };
+    assert!(store.check("home", actual, &extra, None).unwrap().passed);
+}
+
+#[test]
+fn approving_again_can_keep_the_stored_ignore_areas() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = BaselineStore::new(directory.path());
+    let first = BaselineSettings { ignore: vec![region(0, 0, 5, 5), region(10, 0, 2, 2)], ..BaselineSettings::default() };
+    store.approve("home", &white(), &first).unwrap();
+
+    let mut again = BaselineSettings { ignore: vec![region(10, 0, 2, 2)], ..BaselineSettings::default() };
+    let stored = store.settings("home").unwrap().unwrap();
+    assert_eq!(again.keep_ignore(&stored), 1);
+    assert_eq!(again.ignore, vec![region(10, 0, 2, 2), region(0, 0, 5, 5)]);
+
+    assert!(store.settings("never-approved").unwrap().is_none());
+}
+
+#[test]
+fn report_embeds_the_failures_and_lists_the_passes() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = BaselineStore::new(directory.path().join("baselines"));
+    store.approve("fails", &white(), &BaselineSettings::default()).unwrap();
+    store.approve("passes", &white(), &BaselineSettings::default()).unwrap();
+
+    let checks = vec![
+        store.check("fails", with_black(&[(3, 4)]), &DiffOptions::default(), None).unwrap(),
+        store.check("passes", white(), &DiffOptions::default(), None).unwrap(),
+    ];
+    let path = directory.path().join("out/report.html");
+    write_report(&path, &checks).unwrap();
+
+    let html = std::fs::read_to_string(&path).unwrap();
+    assert!(html.contains("1 of 2 checks failed"));
+    assert!(html.contains("Changed regions: 3,4,1x1"));
+    assert!(html.contains("<h2 class=\"pass\">Passed</h2>\n<p>passes</p>"));
+
+    // Baseline, actual and difference, each a valid PNG
+    let images: Vec<&str> = html.split("data:image/png;base64,").skip(1).map(|rest| &rest[..rest.find('"').unwrap()]).collect();
+    assert_eq!(images.len(), 3);
+    let difference = image::load_from_memory(&BASE64.decode(images[2]).unwrap()).unwrap().to_rgba8();
+    assert_eq!(*difference.get_pixel(3, 4), Rgba([255, 140, 0, 255]));
+}
@@ -0,0 +1,61 @@
+use screenshot_tool::capture::clipboard::clipboard_image;
+use screenshot_tool::capture::fullscreen::save_burst;
+use screenshot_tool::capture::{save_image_with_config, CaptureInfo, CapturedImage};
+use screenshot_tool::config::Config;
+use std::path::Path;
+
+fn config(directory: &Path) -> Config {
+    Config { output_directory: directory.to_path_buf(), write_metadata: false, history: false, ..Config::default() }
+}
+
+fn frame(shade: u8) -> CapturedImage {
+    CapturedImage {
+        data: vec![shade; 4 * 3 * 4],
+        width: 4,
+        height: 3,
+        info: CaptureInfo { mode: "fullscreen".to_string(), screen: Some(0), ..CaptureInfo::default() },
+    }
+}
+
+fn names(paths: &[std::path::PathBuf]) -> Vec<String> {
+    paths.iter().map(|path| path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+}
+
+#[test]
+fn burst_frames_share_a_name_and_keep_their_numbers() {
+    let directory = tempfile::tempdir().unwrap();
+    let config = Config { filename_template: "burst".to_string(), ..config(directory.path()) };
+
+    // Frame 2 of 3 failed to capture
+    let mut paths = save_burst(&[(1, frame(10)), (3, frame(30))], 3, &config).unwrap();
+    paths.sort();
+    assert_eq!(names(&paths), vec!["screenshot_burst_01.png", "screenshot_burst_03.png"]);
+    assert_eq!(image::open(&paths[1]).unwrap().to_rgba8().get_pixel(0, 0)[0], 30);
+
+    // Wide enough for every number in the burst
+    let frames: Vec<_> = (1..=100).filter(|n| n % 50 == 0).map(|n| (n, frame(0))).collect();
+    let mut paths = save_burst(&frames, 100, &config).unwrap();
+    paths.sort();
+    assert_eq!(names(&paths), vec!["screenshot_burst_050.png", "screenshot_burst_100.png"]);
+
+    assert!(save_burst(&[], 3, &config).is_err());
+}
+
+#[test]
+fn clipboard_images_are_saved_like_captures() {
+    let directory = tempfile::tempdir().unwrap();
+
+    assert!(clipboard_image(vec![0; 11], 2, 2).is_err());
+    assert!(clipboard_image(Vec::new(), 0, 0).is_err());
+
+    let mut pixels = vec![255u8; 2 * 2 * 4];
+    pixels[3] = 0;
+    let image = clipboard_image(pixels, 2, 2).unwrap();
+    assert_eq!(image.info.mode, "clipboard");
+
+    let path = save_image_with_config(&image.data, image.width, image.height, &config(directory.path()), "clipboard", &image.info).unwrap();
+    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+    assert!(name.starts_with("clipboard_") && name.ends_with(".png"), "{}", name);
+    // Alpha survives the trip
+    assert_eq!(image::open(&path).unwrap().to_rgba8().get_pixel(0, 0)[3], 0);
+}
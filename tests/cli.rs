@@ -0,0 +1,52 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Runs the binary in `directory`, with its config read from there too.
+fn screenshot(directory: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_screenshot"))
+        .args(args)
+        .current_dir(directory)
+        .env("XDG_CONFIG_HOME", directory.join("config"))
+        .env("HOME", directory)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn diff_writes_its_image_with_o_and_exits_6_over_the_threshold() {
+    let directory = tempfile::tempdir().unwrap();
+    let a = RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255]));
+    let mut b = a.clone();
+    b.put_pixel(4, 4, Rgba([0, 0, 0, 255]));
+    a.save(directory.path().join("a.png")).unwrap();
+    b.save(directory.path().join("b.png")).unwrap();
+
+    let output = screenshot(directory.path(), &["diff", "a.png", "b.png", "-o", "diff.png"]);
+    assert_eq!(output.status.code(), Some(6), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(image::image_dimensions(directory.path().join("diff.png")).unwrap(), (10, 10));
+
+    let output = screenshot(directory.path(), &["diff", "a.png", "b.png", "--threshold", "1%", "--file", "within.png"]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(directory.path().join("within.png").is_file());
+
+    let output = screenshot(directory.path(), &["diff", "a.png", "a.png", "-o", "x.png", "--file", "y.png"]);
+    assert!(!output.status.success());
+    assert!(!directory.path().join("x.png").exists() && !directory.path().join("y.png").exists());
+}
+
+#[test]
+fn commands_that_save_nothing_skip_the_output_directory_check() {
+    let directory = tempfile::tempdir().unwrap();
+    std::fs::write(directory.path().join("taken"), b"").unwrap();
+
+    let output = screenshot(directory.path(), &["-o", "taken", "config", "show"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // Without a display the capture itself fails, but not on the output directory
+    let output = screenshot(directory.path(), &["-o", "taken", "fullscreen", "--clipboard-only"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("not a directory"));
+
+    let output = screenshot(directory.path(), &["-o", "taken", "fullscreen"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not a directory"));
+}
@@ -0,0 +1,53 @@
+use screenshot_tool::utils::clipboard::{build_offers, copy_image_to_clipboard, file_uri, offer_capture, ClipboardTarget};
+use std::path::Path;
+
+#[test]
+fn rgba_buffers_of_the_wrong_length_are_refused() {
+    // RGB bytes for a 2x2 image: checked before any clipboard is opened
+    let error = copy_image_to_clipboard(&[0; 12], 2, 2).unwrap_err().to_string();
+    assert!(error.contains("12 bytes, expected 16 for 2x2"), "{}", error);
+    assert!(copy_image_to_clipboard(&[0; 20], 2, 2).is_err());
+}
+
+#[test]
+fn offers_follow_the_target() {
+    let file = Path::new("/tmp/shots/a b.png");
+    let mimes = |target| -> Vec<String> {
+        build_offers(Some(vec![1, 2, 3]), Some(file), target).into_iter().map(|offer| offer.mime).collect()
+    };
+
+    assert_eq!(mimes(ClipboardTarget::Image), vec!["image/png"]);
+    assert_eq!(mimes(ClipboardTarget::Path), vec!["text/plain;charset=utf-8"]);
+    assert_eq!(mimes(ClipboardTarget::Uri), vec!["text/uri-list", "x-special/gnome-copied-files"]);
+    assert_eq!(mimes(ClipboardTarget::All).len(), 4);
+
+    let offers = build_offers(None, Some(file), ClipboardTarget::All);
+    let text = |mime: &str| offers.iter().find(|offer| offer.mime == mime).map(|offer| String::from_utf8(offer.data.clone()).unwrap());
+    assert_eq!(text("text/uri-list").as_deref(), Some("file:///tmp/shots/a%20b.png\r\n"));
+    assert_eq!(text("x-special/gnome-copied-files").as_deref(), Some("copy\nfile:///tmp/shots/a%20b.png"));
+    assert_eq!(text("text/plain;charset=utf-8").as_deref(), Some("/tmp/shots/a b.png"));
+
+    // Without a saved file only the image can be offered
+    assert_eq!(build_offers(Some(vec![1]), None, ClipboardTarget::All).len(), 1);
+    assert!(build_offers(None, None, ClipboardTarget::Path).is_empty());
+}
+
+#[test]
+fn file_uris_percent_encode_reserved_bytes() {
+    assert_eq!(file_uri(Path::new("/home/me/shot_1.png")), "file:///home/me/shot_1.png");
+    assert_eq!(file_uri(Path::new("/tmp/100% ü#?.png")), "file:///tmp/100%25%20%C3%BC%23%3F.png");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn a_helper_that_cannot_take_the_clipboard_reports_why() {
+    // No display to serve on: the helper fails before it owns anything
+    std::env::remove_var("DISPLAY");
+    std::env::remove_var("WAYLAND_DISPLAY");
+    let helper = Path::new(env!("CARGO_BIN_EXE_screenshot"));
+
+    let error = offer_capture(&[0; 16], 2, 2, Some(Path::new("/tmp/a.png")), ClipboardTarget::All, Some(helper))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Clipboard helper failed") && error.contains("X11"), "{}", error);
+}
@@ -0,0 +1,75 @@
+use screenshot_tool::config::{Config, ConfigSource, PROJECT_CONFIG};
+
+#[test]
+fn later_layers_override_earlier_ones() {
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    let user = directory.join("user.toml");
+    let project = directory.join(PROJECT_CONFIG);
+    std::fs::write(&user, "default_quality = 70\ndefault_format = \"jpg\"\n[thumbnail]\nmax_edge = 200\nformat = \"png\"\nquality = 80\n").unwrap();
+    std::fs::write(&project, "default_format = \"png\"\n").unwrap();
+
+    let files = [ConfigSource::User(user.clone()), ConfigSource::Project(project.clone())];
+    let env = [
+        ("SCREENSHOT_THUMBNAIL__MAX_EDGE", "128"),
+        ("SCREENSHOT_TAGS", "a,b"),
+        ("SCREENSHOT_TRIM", ""),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+
+    let (config, origins) = Config::load_layers(&files, env).unwrap();
+    assert_eq!(config.default_quality, 70);
+    assert_eq!(config.default_format, "png");
+    assert_eq!(config.thumbnail.as_ref().map(|t| (t.max_edge, t.quality)), Some((128, 80)));
+    assert_eq!(config.tags, vec!["a".to_string(), "b".to_string()]);
+    assert!(!config.trim);
+
+    assert_eq!(origins.get("default_quality"), Some(&ConfigSource::User(user)));
+    assert_eq!(origins.get("default_format"), Some(&ConfigSource::Project(project)));
+    assert_eq!(origins.get("thumbnail.max_edge"), Some(&ConfigSource::Environment("SCREENSHOT_THUMBNAIL__MAX_EDGE".to_string())));
+    assert_eq!(origins.get("trim"), Some(&ConfigSource::Default));
+    assert_eq!(origins.get("output_directory"), Some(&ConfigSource::Default));
+}
+
+#[test]
+fn embedded_defaults_match_the_documented_ones() {
+    let config = Config::default();
+    assert_eq!(config.default_format, "png");
+    assert_eq!(config.default_quality, 90);
+    assert!(!config.write_metadata && config.history);
+    assert!(config.validate().is_ok());
+
+    let values = config.values().unwrap();
+    assert!(values.iter().any(|(key, value)| key == "on_collision" && value.as_str() == Some("counter")));
+}
+
+#[test]
+fn nearest_project_config_wins_and_errors_name_the_file() {
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    let nested = directory.join("a/b");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(directory.join(PROJECT_CONFIG), "default_quality = 10\n").unwrap();
+    std::fs::write(directory.join("a").join(PROJECT_CONFIG), "default_quality = [\n").unwrap();
+
+    let files = Config::config_files(&nested).unwrap();
+    let project = directory.join("a").join(PROJECT_CONFIG);
+    assert_eq!(files.last(), Some(&ConfigSource::Project(project.clone())));
+
+    let error = Config::load_layers(&files[files.len() - 1..], Default::default()).unwrap_err().to_string();
+    assert!(error.contains(&project.display().to_string()) && error.contains("line 1"), "{}", error);
+}
+
+#[test]
+fn redact_regions_can_be_set_from_the_environment() {
+    let env = |value: &str| [("SCREENSHOT_REDACT".to_string(), value.to_string())].into_iter().collect();
+
+    let (config, origins) = Config::load_layers(&[], env("0,0,10x10; 20,30,40x50")).unwrap();
+    assert_eq!(config.redact, vec!["0,0,10x10".parse().unwrap(), "20,30,40x50".parse().unwrap()]);
+    assert_eq!(origins.get("redact"), Some(&ConfigSource::Environment("SCREENSHOT_REDACT".to_string())));
+
+    let error = Config::load_layers(&[], env("0,0,10")).unwrap_err().to_string();
+    assert!(error.contains("SCREENSHOT_REDACT") && error.contains("0,0,10"), "{}", error);
+}
@@ -0,0 +1,83 @@
+use screenshot_tool::config::ConfigSource;
+use screenshot_tool::config_file::{check, init_template, key_line, ConfigFile};
+use std::path::PathBuf;
+
+fn user(name: &str) -> ConfigSource {
+    ConfigSource::User(PathBuf::from(format!("/home/u/{}.toml", name)))
+}
+
+#[test]
+fn set_and_unset_keep_comments_and_layout() {
+    let text = "# My settings\ndefault_quality = 70  # keep it small\n\n[thumbnail]\n# edge in pixels\nmax_edge = 200\n";
+    let mut file = ConfigFile::parse(user("edit"), text).unwrap();
+
+    file.set("default_quality", "95").unwrap();
+    file.set("thumbnail.format", "jpg").unwrap();
+    file.set("tags", "[\"a\", \"b\"]").unwrap();
+    assert_eq!(
+        file.to_string(),
+        "# My settings\ndefault_quality = 95  # keep it small\ntags = [\"a\", \"b\"]\n\n[thumbnail]\n# edge in pixels\nmax_edge = 200\nformat = \"jpg\"\n"
+    );
+
+    assert!(file.unset("tags").unwrap());
+    assert!(!file.unset("watermark.text").unwrap());
+    assert!(file.set("default_qualty", "95").is_err());
+    assert!(file.set("thumbnail", "1").is_err());
+
+    // Settings added to a fresh file go below its commented-out defaults
+    let mut file = ConfigFile::parse(user("init"), &init_template()).unwrap();
+    file.set("default_format", "jpg").unwrap();
+    let text = file.to_string();
+    assert!(text.starts_with("# screenshot configuration"));
+    assert!(text.ends_with("default_format = \"jpg\"\n"));
+    assert!(check(&[(user("init"), init_template())], Default::default()).is_empty());
+}
+
+#[test]
+fn check_reports_the_line_and_key_of_each_problem() {
+    let text = "default_format = \"png\"\ndefault_quality = \"high\"\nfoo = 1\n\n[thumbnail]\nmax_edge = 10\nedge = 3\n";
+    assert_eq!(key_line(text, "thumbnail.max_edge"), Some(6));
+    assert_eq!(
+        check(&[(user("a"), text.to_string())], Default::default()),
+        vec![
+            "user config /home/u/a.toml, line 3: `foo`: unknown setting".to_string(),
+            "user config /home/u/a.toml, line 7: `thumbnail.edge`: unknown setting".to_string(),
+            "user config /home/u/a.toml, line 2: `default_quality`: invalid type: string \"high\", expected an integer".to_string(),
+        ]
+    );
+
+    let files = [
+        (user("b"), "default_format = \"bmp\"\n".to_string()),
+        (ConfigSource::Project(PathBuf::from("/p/.screenshot.toml")), "# quality\n\ndefault_quality = 0\n".to_string()),
+    ];
+    assert_eq!(
+        check(&files, Default::default()),
+        vec![
+            "user config /home/u/b.toml, line 1: `default_format`: Invalid format: bmp".to_string(),
+            "project config /p/.screenshot.toml, line 3: `default_quality`: Invalid quality value: 0 (must be 1-100)".to_string(),
+        ]
+    );
+
+    let env = [("SCREENSHOT_ON_COLLISION".to_string(), "maybe".to_string())].into_iter().collect();
+    let problems = check(&[], env);
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("environment SCREENSHOT_ON_COLLISION: `on_collision`:"), "{}", problems[0]);
+
+    let problems = check(&[(user("c"), "default_quality = \n".to_string())], Default::default());
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("line 1"), "{}", problems[0]);
+}
+
+#[test]
+fn values_that_fail_to_deserialize_are_located() {
+    let text = "default_quality = 80\n\nredact = [\"0,0,10x10\"]\n";
+    assert_eq!(
+        check(&[(user("d"), text.to_string())], Default::default()),
+        vec!["user config /home/u/d.toml, line 3: `redact`: invalid type: string \"0,0,10x10\", expected struct Region".to_string()]
+    );
+
+    let text = "[watermark]\ntext = \"draft\"\ntile = [true]\n";
+    let problems = check(&[(user("e"), text.to_string())], Default::default());
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("user config /home/u/e.toml, line 3: `watermark.tile`:"), "{}", problems[0]);
+}
@@ -0,0 +1,76 @@
+use image::{Rgba, RgbaImage};
+use screenshot_tool::capture::cursor::{
+    fallback_arrow, overlay_cursor, CursorImage, CursorState, MockCursorBackend,
+};
+
+fn blank(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_pixel(width, height, Rgba([10, 20, 30, 255]))
+}
+
+#[test]
+fn fallback_arrow_is_drawn_at_hotspot() {
+    let mut image = blank(100, 100);
+    let backend = MockCursorBackend::new(40, 50);
+
+    overlay_cursor(&mut image, (0, 0), 1.0, &backend, false).unwrap();
+
+    // Arrow tip is black, the pixel inside the arrow is white.
+    assert_eq!(*image.get_pixel(40, 50), Rgba([0, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(41, 52), Rgba([255, 255, 255, 255]));
+    assert_eq!(*image.get_pixel(39, 50), Rgba([10, 20, 30, 255]));
+}
+
+#[test]
+fn cursor_position_is_relative_to_screen_origin_and_scaled() {
+    let mut image = blank(200, 200);
+    let backend = MockCursorBackend::new(1950, 30);
+
+    // Second monitor at x=1920, captured at 2x.
+    overlay_cursor(&mut image, (1920, 0), 2.0, &backend, false).unwrap();
+
+    assert_eq!(*image.get_pixel(60, 60), Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn reported_sprite_is_offset_by_its_hotspot() {
+    let mut image = blank(50, 50);
+    let sprite = CursorImage {
+        width: 3,
+        height: 3,
+        xhot: 1,
+        yhot: 1,
+        pixels: [255, 0, 0, 255].repeat(9),
+    };
+    let backend = MockCursorBackend {
+        state: CursorState { x: 10, y: 10, image: Some(sprite) },
+    };
+
+    overlay_cursor(&mut image, (0, 0), 1.0, &backend, false).unwrap();
+
+    assert_eq!(*image.get_pixel(9, 9), Rgba([255, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(11, 11), Rgba([255, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(12, 12), Rgba([10, 20, 30, 255]));
+}
+
+#[test]
+fn highlight_draws_ring_around_cursor() {
+    let mut image = blank(100, 100);
+    let backend = MockCursorBackend::new(50, 50);
+
+    overlay_cursor(&mut image, (0, 0), 1.0, &backend, true).unwrap();
+
+    // A point on the ring to the left of the cursor is tinted, the far corner is not.
+    assert_ne!(*image.get_pixel(32, 50), Rgba([10, 20, 30, 255]));
+    assert_eq!(*image.get_pixel(0, 0), Rgba([10, 20, 30, 255]));
+}
+
+#[test]
+fn cursor_outside_capture_is_clipped() {
+    let mut image = blank(20, 20);
+    let backend = MockCursorBackend::new(-500, -500);
+
+    overlay_cursor(&mut image, (0, 0), 1.0, &backend, true).unwrap();
+
+    assert!(image.pixels().all(|p| *p == Rgba([10, 20, 30, 255])));
+    assert_eq!(fallback_arrow().height, 19);
+}
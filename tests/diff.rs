@@ -0,0 +1,110 @@
+use image::{Rgba, RgbaImage};
+use screenshot_tool::capture::region::Region;
+use screenshot_tool::diff::{DiffLayout, DiffOptions, ImageDiff};
+
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+fn white(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_pixel(width, height, WHITE)
+}
+
+fn with_black(mut image: RgbaImage, pixels: &[(u32, u32)]) -> RgbaImage {
+    for &(x, y) in pixels {
+        image.put_pixel(x, y, BLACK);
+    }
+    image
+}
+
+fn region(x: i32, y: i32, width: u32, height: u32) -> Region {
+    Region { x, y, width, height }
+}
+
+#[test]
+fn identical_images_have_no_changes() {
+    let report = ImageDiff::compute(&white(20, 20), &white(20, 20), 0).report;
+    assert_eq!((report.changed_pixels, report.total_pixels), (0, 400));
+    assert_eq!(report.changed_fraction, 0.0);
+    assert!(report.regions.is_empty());
+}
+
+#[test]
+fn changes_are_counted_and_grouped_into_regions() {
+    let b = with_black(white(64, 64), &[(2, 2), (3, 3), (15, 50), (16, 51), (35, 5)]);
+    let diff = ImageDiff::compute(&white(64, 64), &b, 0);
+    let report = &diff.report;
+
+    assert_eq!(report.changed_pixels, 5);
+    assert_eq!(report.changed_fraction, 5.0 / 4096.0);
+    assert!(diff.is_changed(3, 3) && !diff.is_changed(3, 2) && !diff.is_changed(99, 99));
+    // Neighbouring cells merge; cells with a gap between them do not
+    assert_eq!(report.regions, vec![region(2, 2, 2, 2), region(35, 5, 1, 1), region(15, 50, 2, 2)]);
+}
+
+#[test]
+fn tolerance_and_ignored_areas_are_not_changes() {
+    let mut b = white(10, 10);
+    b.put_pixel(0, 0, Rgba([245, 255, 255, 255]));
+    assert_eq!(ImageDiff::compute(&white(10, 10), &b, 16).report.changed_pixels, 0);
+    assert_eq!(ImageDiff::compute(&white(10, 10), &b, 0).report.changed_pixels, 1);
+
+    let b = with_black(white(10, 10), &[(1, 1), (8, 8)]);
+    let options = DiffOptions { ignore: vec![region(0, 0, 4, 4), region(-2, 9, 5, 5)], ..DiffOptions::default() };
+    let report = ImageDiff::compute_with(&white(10, 10), &b, &options).report;
+    assert_eq!((report.changed_pixels, report.ignored_pixels, report.total_pixels), (1, 16 + 3, 100 - 19));
+    assert_eq!(report.regions, vec![region(8, 8, 1, 1)]);
+}
+
+#[test]
+fn shifted_antialiased_edges_can_be_accepted() {
+    let a = with_black(white(10, 10), &[(5, 5)]);
+    let b = with_black(white(10, 10), &[(6, 5)]);
+
+    let strict = ImageDiff::compute_with(&a, &b, &DiffOptions::default()).report;
+    assert_eq!((strict.changed_pixels, strict.antialiased_pixels), (2, 0));
+
+    let relaxed = ImageDiff::compute_with(&a, &b, &DiffOptions { antialiasing: true, ..DiffOptions::default() }).report;
+    assert_eq!((relaxed.changed_pixels, relaxed.antialiased_pixels), (0, 2));
+
+    // A pixel that appears out of nowhere is still a change
+    let c = with_black(white(10, 10), &[(5, 5), (1, 1)]);
+    let report = ImageDiff::compute_with(&a, &c, &DiffOptions { antialiasing: true, ..DiffOptions::default() }).report;
+    assert_eq!(report.changed_pixels, 1);
+}
+
+#[test]
+fn pixels_outside_the_smaller_image_count_as_changed() {
+    let report = ImageDiff::compute(&white(10, 10), &white(12, 10), 0).report;
+    assert_eq!((report.width, report.height), (12, 10));
+    assert_eq!(report.changed_pixels, 20);
+    assert_eq!(report.regions, vec![region(10, 0, 2, 10)]);
+}
+
+#[test]
+fn layouts_mark_the_changes() {
+    let a = white(20, 20);
+    let block: Vec<(u32, u32)> = (4..7).flat_map(|y| (4..7).map(move |x| (x, y))).collect();
+    let b = with_black(white(20, 20), &block);
+    let diff = ImageDiff::compute(&a, &b, 0);
+
+    let highlight = diff.render(&a, &b, DiffLayout::Highlight);
+    assert_eq!(highlight.dimensions(), (20, 20));
+    assert_eq!(*highlight.get_pixel(5, 5), Rgba([255, 0, 0, 255]));
+    assert_eq!(*highlight.get_pixel(4, 4), Rgba([255, 140, 0, 255]));
+    assert_eq!(*highlight.get_pixel(15, 15), WHITE);
+
+    let side_by_side = diff.render(&a, &b, DiffLayout::SideBySide);
+    assert_eq!(side_by_side.dimensions(), (60, 20));
+    assert_eq!(*side_by_side.get_pixel(5, 5), WHITE);
+    assert_eq!(*side_by_side.get_pixel(25, 5), BLACK);
+    assert_eq!(*side_by_side.get_pixel(45, 5), Rgba([255, 0, 0, 255]));
+
+    let overlay = diff.render(&a, &b, DiffLayout::Overlay);
+    assert_eq!(overlay.dimensions(), (20, 20));
+    assert_eq!(*overlay.get_pixel(15, 15), WHITE);
+    // Grey from the blend, tinted towards red
+    assert_eq!(*overlay.get_pixel(5, 5), Rgba([191, 63, 63, 255]));
+
+    assert_eq!("side-by-side".parse::<DiffLayout>().unwrap(), DiffLayout::SideBySide);
+    assert!("sideways".parse::<DiffLayout>().is_err());
+}
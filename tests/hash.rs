@@ -0,0 +1,99 @@
+use image::{Rgba, RgbaImage};
+use screenshot_tool::dedupe::{apply, find_duplicates, DedupeAction, ScannedImage};
+use screenshot_tool::hash::{group_similar, HashKind, ImageHashes};
+use chrono::{Duration, Local};
+use std::path::PathBuf;
+
+/// A window-like image: a light background with a dark bar at `bar_y`.
+fn window(bar_y: u32, shade: u8) -> RgbaImage {
+    RgbaImage::from_fn(320, 200, |x, y| {
+        if (bar_y..bar_y + 30).contains(&y) || (x < 60 && y > 100) {
+            Rgba([40, 40, 60, 255])
+        } else {
+            Rgba([shade, shade, shade, 255])
+        }
+    })
+}
+
+#[test]
+fn identical_images_hash_the_same() {
+    let a = ImageHashes::compute(&window(20, 230));
+    let b = ImageHashes::compute(&window(20, 230));
+
+    assert_eq!(a, b);
+    assert_eq!(a.max_distance(&b), 0);
+}
+
+#[test]
+fn small_changes_stay_close_and_layout_changes_do_not() {
+    let original = ImageHashes::compute(&window(20, 230));
+    let brighter = ImageHashes::compute(&window(20, 240));
+    let moved = ImageHashes::compute(&window(140, 230));
+
+    assert!(original.distance(&brighter, HashKind::Perceptual) <= 4);
+    assert!(original.distance(&moved, HashKind::Perceptual) > 10);
+}
+
+#[test]
+fn hashes_round_trip_as_hex() {
+    let hashes = ImageHashes::compute(&window(20, 230));
+    let json = serde_json::to_string(&hashes).unwrap();
+
+    assert!(json.contains(&format!("\"{:016x}\"", hashes.phash)));
+    assert_eq!(serde_json::from_str::<ImageHashes>(&json).unwrap(), hashes);
+}
+
+#[test]
+fn groups_do_not_chain_and_are_ordered() {
+    // 0b0111 is one bit from 0b0011 but two from 0b0001, which leads the group
+    let groups = group_similar(&[0b0001, 0xff00, 0b0011, 0b0111, 0xff01, 0xf0f0], 1);
+
+    assert_eq!(groups, vec![vec![0, 2], vec![1, 4]]);
+}
+
+#[test]
+fn the_oldest_duplicate_is_kept() {
+    let now = Local::now();
+    let hashes = ImageHashes::compute(&window(20, 230));
+    let other = ImageHashes::compute(&window(140, 230));
+    let scanned = |name: &str, age: i64, hashes: ImageHashes| ScannedImage {
+        path: PathBuf::from(name),
+        captured_at: now - Duration::minutes(age),
+        hashes,
+    };
+
+    let images = [scanned("new.png", 1, hashes), scanned("old.png", 5, hashes), scanned("other.png", 3, other)];
+    let groups = find_duplicates(&images, HashKind::Perceptual, 0);
+
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].keep, PathBuf::from("old.png"));
+    assert_eq!(groups[0].duplicates, vec![(PathBuf::from("new.png"), 0)]);
+}
+
+#[test]
+fn a_chain_of_near_duplicates_keeps_its_far_end() {
+    let directory = tempfile::tempdir().unwrap();
+    let now = Local::now();
+    // A~B and B~C are one bit apart, but C is two bits from A
+    let images: Vec<ScannedImage> = [("a.png", 0b00), ("b.png", 0b01), ("c.png", 0b11)]
+        .iter()
+        .enumerate()
+        .map(|(age, &(name, phash))| {
+            let path = directory.path().join(name);
+            std::fs::write(&path, name).unwrap();
+            ScannedImage {
+                path,
+                captured_at: now - Duration::minutes(10 - age as i64),
+                hashes: ImageHashes { ahash: 0, dhash: 0, phash },
+            }
+        })
+        .collect();
+
+    let groups = find_duplicates(&images, HashKind::Perceptual, 1);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].keep, images[0].path);
+    assert_eq!(groups[0].duplicates, vec![(images[1].path.clone(), 1)]);
+
+    apply(&groups[0], DedupeAction::Delete).unwrap();
+    assert!(images[0].path.exists() && !images[1].path.exists() && images[2].path.exists());
+}
@@ -0,0 +1,75 @@
+use chrono::{Duration, Local, TimeZone};
+use screenshot_tool::history::{History, HistoryEntry, HistoryFilter};
+use screenshot_tool::utils::time::parse_since;
+use std::path::PathBuf;
+
+fn temp_history(name: &str) -> History {
+    let path = std::env::temp_dir().join(format!("screenshot_history_{}_{}.jsonl", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    History::at(path)
+}
+
+fn entry(name: &str, mode: &str, minutes_ago: i64) -> HistoryEntry {
+    HistoryEntry {
+        path: PathBuf::from(format!("/captures/{}.png", name)),
+        captured_at: Local::now() - Duration::minutes(minutes_ago),
+        mode: mode.to_string(),
+        screen: Some(0),
+        window: None,
+        width: 800,
+        height: 600,
+        tags: vec!["demo".to_string()],
+    }
+}
+
+#[test]
+fn entries_survive_a_broken_line() {
+    let history = temp_history("broken");
+    history.append(&entry("one", "fullscreen", 10)).unwrap();
+    std::fs::write(history.path(), std::fs::read_to_string(history.path()).unwrap() + "{not json\n").unwrap();
+    history.append(&entry("two", "window", 5)).unwrap();
+
+    let entries = history.entries().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].path, PathBuf::from("/captures/two.png"));
+    assert_eq!(entries[0].tags, vec!["demo".to_string()]);
+
+    std::fs::remove_file(history.path()).unwrap();
+}
+
+#[test]
+fn query_keeps_list_numbers_when_filtering() {
+    let history = temp_history("query");
+    history.append(&entry("old", "fullscreen", 120)).unwrap();
+    history.append(&entry("mid", "window", 30)).unwrap();
+    history.append(&entry("new", "fullscreen", 1)).unwrap();
+
+    let filter = HistoryFilter { mode: Some("fullscreen".to_string()), ..HistoryFilter::default() };
+    let numbers: Vec<usize> = history.query(&filter).unwrap().iter().map(|(n, _)| *n).collect();
+    assert_eq!(numbers, vec![1, 3]);
+
+    let recent = HistoryFilter { since: Some(Local::now() - Duration::hours(1)), mode: None };
+    assert_eq!(history.query(&recent).unwrap().len(), 2);
+
+    assert_eq!(history.nth_latest(2).unwrap().path, PathBuf::from("/captures/mid.png"));
+    assert!(history.nth_latest(0).is_err());
+    assert!(history.nth_latest(4).is_err());
+
+    std::fs::remove_file(history.path()).unwrap();
+}
+
+#[test]
+fn missing_history_is_empty() {
+    assert!(temp_history("missing").entries().unwrap().is_empty());
+}
+
+#[test]
+fn since_accepts_durations_and_dates() {
+    let now = Local.with_ymd_and_hms(2024, 5, 10, 12, 0, 0).unwrap();
+
+    assert_eq!(parse_since("2h", &now).unwrap(), Local.with_ymd_and_hms(2024, 5, 10, 10, 0, 0).unwrap());
+    assert_eq!(parse_since("7d", &now).unwrap(), Local.with_ymd_and_hms(2024, 5, 3, 12, 0, 0).unwrap());
+    assert_eq!(parse_since("2024-05-01", &now).unwrap(), Local.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap());
+    assert_eq!(parse_since("2024-05-01 14:30", &now).unwrap(), Local.with_ymd_and_hms(2024, 5, 1, 14, 30, 0).unwrap());
+    assert!(parse_since("yesterday", &now).is_err());
+}
@@ -0,0 +1,19 @@
+use screenshot_tool::config::Config;
+use screenshot_tool::utils::path::generate_filename;
+use screenshot_tool::utils::template::FilenameValues;
+
+#[test]
+fn default_names_start_with_the_capture_label() {
+    let directory = tempfile::tempdir().unwrap();
+    let config = Config::default();
+
+    for label in ["screenshot", "selection", "window"] {
+        let name = generate_filename(&config, label, &FilenameValues::default(), directory.path()).unwrap();
+        assert!(name.starts_with(&format!("{}_2", label)) && name.ends_with(".png"), "{}", name);
+    }
+
+    // A template that spells out its own label keeps it as written
+    let custom = Config { filename_template: "screenshot_%Y".to_string(), ..Config::default() };
+    let name = generate_filename(&custom, "selection", &FilenameValues::default(), directory.path()).unwrap();
+    assert!(name.starts_with("selection_screenshot_2"), "{}", name);
+}
@@ -0,0 +1,35 @@
+use chrono::{Local, TimeZone};
+use screenshot_tool::organize::filename_time;
+use screenshot_tool::utils::path::{output_root, resolve_output_directory};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn output_template_expands_dates_and_mode() {
+    let now = Local.with_ymd_and_hms(2024, 5, 1, 14, 30, 0).unwrap();
+
+    let directory = resolve_output_directory(Path::new("/shots/%Y/%m/{mode}"), "window", &now).unwrap();
+    assert_eq!(directory, PathBuf::from("/shots/2024/05/window"));
+
+    let plain = resolve_output_directory(Path::new("/shots/plain"), "window", &now).unwrap();
+    assert_eq!(plain, PathBuf::from("/shots/plain"));
+    assert!(resolve_output_directory(Path::new("/shots/100%"), "window", &now).is_err());
+
+    assert_eq!(output_root(Path::new("/shots/%Y/%m/{mode}")), PathBuf::from("/shots"));
+    assert_eq!(output_root(Path::new("/shots/plain")), PathBuf::from("/shots/plain"));
+}
+
+#[test]
+fn timestamps_are_read_from_common_file_names() {
+    let expected = Local.with_ymd_and_hms(2024, 5, 1, 14, 30, 0).unwrap();
+    let template = "screenshot_%Y%m%d_%H%M%S";
+
+    for name in [
+        "screenshot_20240501_143000.png",
+        "window_screenshot_20240501_143000_2.png",
+        "Screenshot 2024-05-01 at 14.30.00.png",
+        "Screenshot_2024-05-01_14-30-00.png",
+    ] {
+        assert_eq!(filename_time(Path::new(name), template), Some(expected), "{}", name);
+    }
+    assert_eq!(filename_time(Path::new("holiday.png"), template), None);
+}
@@ -0,0 +1,96 @@
+use screenshot_tool::config::Config;
+use screenshot_tool::utils::path::{check_writable, expand_path, validate_output_path};
+use screenshot_tool::ScreenshotError;
+use std::path::{Path, PathBuf};
+
+fn denied(result: Result<PathBuf, ScreenshotError>) -> String {
+    match result {
+        Err(ScreenshotError::PermissionDenied(message)) => message,
+        other => panic!("expected PermissionDenied, got {:?}", other),
+    }
+}
+
+#[test]
+fn dots_in_names_are_allowed_and_parent_dirs_resolved() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+
+    let directory = validate_output_path(&root.join("a..b"), &[]).unwrap();
+    assert_eq!(directory, root.join("a..b"));
+    assert!(directory.is_dir());
+
+    let directory = validate_output_path(&root.join("new/../shots"), std::slice::from_ref(&root)).unwrap();
+    assert_eq!(directory, root.join("shots"));
+}
+
+#[test]
+fn output_stays_inside_allowed_roots() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    let allowed = root.join("allowed");
+    std::fs::create_dir_all(&allowed).unwrap();
+
+    assert!(validate_output_path(&allowed.join("2024"), std::slice::from_ref(&allowed)).is_ok());
+
+    let message = denied(validate_output_path(&allowed.join("../elsewhere"), std::slice::from_ref(&allowed)));
+    assert!(message.contains("outside the allowed roots"), "{}", message);
+    assert!(!root.join("elsewhere").exists());
+
+    let file = allowed.join("taken");
+    std::fs::write(&file, b"").unwrap();
+    assert!(denied(validate_output_path(&file, &[])).contains("not a directory"));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_cannot_lead_out_of_allowed_roots() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    let allowed = root.join("allowed");
+    let outside = root.join("outside");
+    std::fs::create_dir_all(&allowed).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    std::os::unix::fs::symlink(&outside, allowed.join("escape")).unwrap();
+    std::os::unix::fs::symlink(root.join("missing"), allowed.join("dangling")).unwrap();
+
+    let message = denied(validate_output_path(&allowed.join("escape/shots"), std::slice::from_ref(&allowed)));
+    assert!(message.contains("follows a symlink"), "{}", message);
+
+    // Without a restriction the link is simply followed
+    assert_eq!(validate_output_path(&allowed.join("escape"), &[]).unwrap(), outside);
+
+    assert!(denied(validate_output_path(&allowed.join("dangling"), &[])).contains("missing target"));
+}
+
+#[test]
+fn environment_variables_are_expanded() {
+    std::env::set_var("SCREENSHOT_TEST_DIR", "/data/shots");
+
+    assert_eq!(expand_path(Path::new("$SCREENSHOT_TEST_DIR/a")).unwrap(), PathBuf::from("/data/shots/a"));
+    assert_eq!(expand_path(Path::new("${SCREENSHOT_TEST_DIR}_b")).unwrap(), PathBuf::from("/data/shots_b"));
+    assert_eq!(expand_path(Path::new("/price/$")).unwrap(), PathBuf::from("/price/$"));
+    assert!(denied(expand_path(Path::new("$SCREENSHOT_TEST_UNSET/x"))).contains("SCREENSHOT_TEST_UNSET is not set"));
+}
+
+#[test]
+fn the_output_root_is_checked_once_up_front() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    let config = |output: PathBuf, allowed: Vec<PathBuf>| Config {
+        output_directory: output,
+        allowed_roots: allowed,
+        ..Config::default()
+    };
+
+    // Only the fixed part of a templated directory is created
+    config(root.join("shots/%Y/{mode}"), vec![]).check_output_writable().unwrap();
+    assert!(root.join("shots").is_dir());
+    assert_eq!(std::fs::read_dir(root.join("shots")).unwrap().count(), 0);
+
+    let outside = config(root.join("elsewhere"), vec![root.join("shots")]).check_output_writable();
+    assert!(denied(outside.map(|_| root.clone())).contains("outside the allowed roots"));
+    assert!(!root.join("elsewhere").exists());
+
+    check_writable(&root).unwrap();
+    assert!(check_writable(&root.join("missing")).is_err());
+}
@@ -0,0 +1,219 @@
+use image::{Rgba, RgbaImage};
+use screenshot_tool::capture::record::{
+    capture_frames, encode_apng, encode_gif, FrameSequenceWriter, RecordFormat, RecordOptions, RecordSummary,
+    RecordedFrame,
+};
+use screenshot_tool::capture::y4m::{rgba_to_i420, Y4mWriter};
+use screenshot_tool::capture::source::MockFrameSource;
+use screenshot_tool::capture::{CaptureInfo, CapturedImage};
+use screenshot_tool::utils::atomic::CollisionStrategy;
+use std::io::Cursor;
+use std::ops::ControlFlow;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+fn frame(shade: u8) -> CapturedImage {
+    CapturedImage { data: vec![shade; 8 * 6 * 4], width: 8, height: 6, info: CaptureInfo::default() }
+}
+
+fn options(duration: Duration, fps: u32) -> RecordOptions {
+    RecordOptions { duration, fps, format: RecordFormat::Gif, output: None }
+}
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+fn collect(source: &mut MockFrameSource, options: &RecordOptions) -> (Vec<RecordedFrame>, RecordSummary) {
+    let mut frames = Vec::new();
+    let mut summary = RecordSummary::default();
+    capture_frames(source, options, &AtomicBool::new(false), &mut summary, &mut |frame| {
+        frames.push(frame);
+        Ok(ControlFlow::Continue(()))
+    }).unwrap();
+    (frames, summary)
+}
+
+#[test]
+fn repeated_frames_extend_the_previous_one() {
+    let mut source = MockFrameSource::new(vec![frame(0), frame(0), frame(200)]);
+    let (frames, summary) = collect(&mut source, &options(ms(300), 20));
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(summary.frames, 2);
+    assert_eq!(summary.duplicates, summary.captured - 2);
+    assert_eq!(frames[1].image.data, frame(200).data);
+
+    // The first frame stays up until the change was seen, two slots in
+    assert!(frames[0].duration >= ms(90) && frames[0].duration < ms(300), "{:?}", frames[0].duration);
+    assert_eq!(frames[1].at, frames[0].at + frames[0].duration);
+    // The last one runs to the end of the recording
+    let total = frames[1].at + frames[1].duration;
+    assert!(total > ms(250) && total <= ms(300), "{:?}", total);
+}
+
+#[test]
+fn a_breaking_sink_ends_the_recording() {
+    let mut source = MockFrameSource::new(vec![frame(0), frame(100), frame(200)]);
+    let mut summary = RecordSummary::default();
+    let mut received = 0;
+    let started = Instant::now();
+
+    capture_frames(&mut source, &options(Duration::from_secs(10), 20), &AtomicBool::new(false), &mut summary, &mut |_| {
+        received += 1;
+        Ok(if received == 2 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) })
+    }).unwrap();
+
+    assert_eq!(summary.frames, 1);
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let mut summary = RecordSummary::default();
+    assert!(capture_frames(&mut source, &options(ms(100), 0), &AtomicBool::new(false), &mut summary, &mut |_| {
+        Ok(ControlFlow::Continue(()))
+    }).is_err());
+}
+
+fn encoded_frames() -> Vec<(RgbaImage, Duration)> {
+    let first = RgbaImage::from_pixel(8, 6, Rgba([255, 0, 0, 255]));
+    let mut second = first.clone();
+    second.put_pixel(5, 4, Rgba([0, 0, 255, 255]));
+    vec![(first, ms(100)), (second, ms(250))]
+}
+
+#[test]
+fn gif_frames_store_only_the_changed_box() {
+    let mut gif = Vec::new();
+    encode_gif(&mut gif, &encoded_frames()).unwrap();
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(Cursor::new(gif)).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (8, 6));
+
+    let first = decoder.read_next_frame().unwrap().unwrap().clone();
+    assert_eq!((first.width, first.height, first.delay), (8, 6, 10));
+    assert_eq!(&first.buffer[..4], &[255, 0, 0, 255]);
+
+    let second = decoder.read_next_frame().unwrap().unwrap();
+    assert_eq!((second.left, second.top, second.width, second.height, second.delay), (5, 4, 1, 1, 25));
+    assert_eq!(&second.buffer[..4], &[0, 0, 255, 255]);
+
+    assert!(decoder.read_next_frame().unwrap().is_none());
+    assert!(encode_gif(Vec::new(), &[]).is_err());
+}
+
+#[test]
+fn apng_frames_keep_their_delays() {
+    let mut apng = Vec::new();
+    encode_apng(&mut apng, &encoded_frames()).unwrap();
+
+    let mut reader = png::Decoder::new(Cursor::new(apng)).read_info().unwrap();
+    assert_eq!(reader.info().animation_control.unwrap().num_frames, 2);
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let mut delays = Vec::new();
+    for _ in 0..2 {
+        reader.next_frame(&mut buffer).unwrap();
+        let control = reader.info().frame_control.unwrap();
+        delays.push((control.delay_num, control.delay_den));
+    }
+    assert_eq!(delays, vec![(100, 1000), (250, 1000)]);
+    assert_eq!(&buffer[(4 * 8 + 5) * 4..][..4], &[0, 0, 255, 255]);
+}
+
+#[test]
+fn i420_planes_average_chroma_over_blocks() {
+    // 3x2: the odd column gets a chroma sample of its own
+    let mut image = RgbaImage::from_pixel(3, 2, Rgba([255, 255, 255, 255]));
+    image.put_pixel(2, 0, Rgba([0, 0, 0, 255]));
+    image.put_pixel(2, 1, Rgba([0, 0, 0, 255]));
+    let mut planes = Vec::new();
+    rgba_to_i420(&image, &mut planes);
+
+    assert_eq!(planes.len(), 6 + 2 + 2);
+    assert_eq!(&planes[..6], &[235, 235, 16, 235, 235, 16]);
+    // Grey has no colour
+    assert_eq!(&planes[6..], &[128, 128, 128, 128]);
+
+    let red = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+    rgba_to_i420(&red, &mut planes);
+    assert_eq!(planes, vec![82, 82, 82, 82, 90, 240]);
+}
+
+#[test]
+fn y4m_frames_land_on_the_grid_at_their_real_times() {
+    let black = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+    let white = RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255]));
+    let mut stream = Vec::new();
+
+    let mut writer = Y4mWriter::new(&mut stream, 10);
+    // Shown for 250ms: rounds to 3 frames at 10 fps
+    writer.write_frame(&black, ms(250)).unwrap();
+    // Replaced again before the next grid point: dropped
+    writer.write_frame(&white, ms(260)).unwrap();
+    writer.write_frame(&white, ms(400)).unwrap();
+    assert_eq!(writer.frames_written(), 4);
+    assert!(writer.write_frame(&RgbaImage::new(4, 4), ms(500)).is_err());
+    writer.finish().unwrap();
+
+    let header = b"YUV4MPEG2 W2 H2 F10:1 Ip A1:1 C420jpeg\n";
+    assert!(stream.starts_with(header));
+    let frames: Vec<&[u8]> = stream[header.len()..].chunks(6 + 6).collect();
+    assert_eq!(frames.len(), 4);
+    assert!(frames.iter().all(|frame| frame.starts_with(b"FRAME\n")));
+    assert_eq!(frames[2][6], 16);
+    assert_eq!(frames[3][6], 235);
+}
+
+#[test]
+fn frame_manifest_records_real_timing() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("recording");
+    let mut writer = FrameSequenceWriter::create(&path, 10).unwrap();
+
+    for (shade, at, duration) in [(0, 5, 180), (200, 185, 100)] {
+        let frame = RecordedFrame { image: frame(shade), at: ms(at), duration: ms(duration) };
+        let image = RgbaImage::from_raw(8, 6, frame.image.data.clone()).unwrap();
+        writer.write_frame(&image, 96, &frame).unwrap();
+    }
+    // Nothing under the real name until the manifest is written
+    assert!(!path.exists());
+    assert_eq!(writer.finish(CollisionStrategy::Counter).unwrap(), path);
+
+    let manifest: serde_json::Value = serde_json::from_slice(&std::fs::read(path.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["fps"], 10);
+    assert_eq!((manifest["width"].as_u64(), manifest["height"].as_u64()), (Some(8), Some(6)));
+    let frames = manifest["frames"].as_array().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1]["file"], "frame_00002.png");
+    assert_eq!((frames[0]["at_ms"].as_u64(), frames[0]["duration_ms"].as_u64()), (Some(5), Some(180)));
+    assert_eq!((frames[1]["at_ms"].as_u64(), frames[1]["duration_ms"].as_u64()), (Some(185), Some(100)));
+    assert_eq!(image::open(path.join("frame_00002.png")).unwrap().to_rgba8().get_pixel(0, 0)[0], 200);
+}
+
+#[test]
+fn frame_directories_follow_the_collision_strategy() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("recording");
+    let one_frame = |collision| {
+        let mut writer = FrameSequenceWriter::create(&path, 10).unwrap();
+        let recorded = RecordedFrame { image: frame(0), at: ms(0), duration: ms(100) };
+        writer.write_frame(&RgbaImage::new(8, 6), 96, &recorded).unwrap();
+        writer.finish(collision)
+    };
+
+    assert_eq!(one_frame(CollisionStrategy::Counter).unwrap(), path);
+    assert_eq!(one_frame(CollisionStrategy::Counter).unwrap(), directory.path().join("recording_1"));
+    // An existing directory is never replaced or merged into
+    assert!(one_frame(CollisionStrategy::Overwrite).is_err());
+    assert!(one_frame(CollisionStrategy::Error).is_err());
+
+    // Abandoned recordings leave nothing behind
+    drop(FrameSequenceWriter::create(&directory.path().join("abandoned"), 10).unwrap());
+    let mut names: Vec<String> = std::fs::read_dir(directory.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["recording", "recording_1"]);
+}
@@ -0,0 +1,76 @@
+use chrono::{Duration, Local};
+use screenshot_tool::retention::{apply, plan, CleanReason, RetentionPolicy, TrackedCapture};
+use screenshot_tool::utils::size::parse_size;
+use std::path::PathBuf;
+
+fn capture(name: &str, days_ago: i64, size: u64) -> TrackedCapture {
+    TrackedCapture {
+        path: PathBuf::from(format!("{}.png", name)),
+        captured_at: Local::now() - Duration::days(days_ago),
+        size,
+    }
+}
+
+fn reasons(plan: &screenshot_tool::retention::CleanPlan) -> Vec<(String, CleanReason)> {
+    plan.expired.iter().map(|(c, reason)| (c.path.display().to_string(), *reason)).collect()
+}
+
+#[test]
+fn old_captures_expire() {
+    let policy = RetentionPolicy { max_age: Some(Duration::days(30)), ..RetentionPolicy::default() };
+    let result = plan(vec![capture("new", 1, 10), capture("old", 40, 10)], &policy, Local::now());
+
+    assert_eq!(reasons(&result), vec![("old.png".to_string(), CleanReason::Age)]);
+    assert_eq!((result.kept, result.kept_size), (1, 10));
+}
+
+#[test]
+fn count_and_size_limits_keep_the_newest() {
+    let captures = vec![capture("c", 3, 100), capture("a", 1, 100), capture("d", 4, 10), capture("b", 2, 100)];
+
+    let by_count = RetentionPolicy { max_files: Some(2), ..RetentionPolicy::default() };
+    let result = plan(captures.clone(), &by_count, Local::now());
+    assert_eq!(reasons(&result), vec![("c.png".to_string(), CleanReason::Count), ("d.png".to_string(), CleanReason::Count)]);
+
+    // The small oldest capture would fit but goes once the limit is reached
+    let by_size = RetentionPolicy { max_size: Some(250), ..RetentionPolicy::default() };
+    let result = plan(captures, &by_size, Local::now());
+    assert_eq!(reasons(&result), vec![("c.png".to_string(), CleanReason::Size), ("d.png".to_string(), CleanReason::Size)]);
+    assert_eq!(result.kept_size, 200);
+}
+
+#[test]
+fn no_limits_keep_everything() {
+    let result = plan(vec![capture("a", 400, 1 << 30)], &RetentionPolicy::default(), Local::now());
+    assert!(result.expired.is_empty());
+    assert!(!RetentionPolicy::default().has_limits());
+}
+
+#[test]
+fn sizes_parse_decimal_and_binary_units() {
+    assert_eq!(parse_size("4096").unwrap(), 4096);
+    assert_eq!(parse_size("500MB").unwrap(), 500_000_000);
+    assert_eq!(parse_size("1.5G").unwrap(), 1_500_000_000);
+    assert_eq!(parse_size("2GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+    assert!(parse_size("lots").is_err());
+}
+
+#[test]
+fn archives_stay_inside_allowed_roots() {
+    let directory = tempfile::tempdir().unwrap();
+    let shots = directory.path().join("shots");
+    std::fs::create_dir_all(shots.join("2024")).unwrap();
+    let old = shots.join("2024/old.png");
+    std::fs::write(&old, b"png").unwrap();
+    let policy = RetentionPolicy { max_files: Some(0), ..RetentionPolicy::default() };
+    let expired = plan(vec![TrackedCapture { path: old.clone(), ..capture("old", 40, 3) }], &policy, Local::now());
+
+    let outside = directory.path().join("archive");
+    let error = apply(&expired, &shots, Some(&outside), std::slice::from_ref(&shots)).unwrap_err();
+    assert!(error.to_string().contains("outside the allowed roots"), "{}", error);
+    assert!(old.exists() && !outside.exists());
+
+    let inside = shots.join("archive");
+    assert_eq!(apply(&expired, &shots, Some(&inside), std::slice::from_ref(&shots)).unwrap(), 3);
+    assert!(!old.exists() && inside.join("2024/old.png").exists());
+}
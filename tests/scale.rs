@@ -0,0 +1,40 @@
+use screenshot_tool::capture::scale::{resample, ScaleMode};
+
+#[test]
+fn scale_modes_parse() {
+    assert_eq!("physical".parse::<ScaleMode>().unwrap(), ScaleMode::Physical);
+    assert_eq!(" Logical ".parse::<ScaleMode>().unwrap(), ScaleMode::Logical);
+    assert_eq!("0.5".parse::<ScaleMode>().unwrap(), ScaleMode::Factor(0.5));
+    assert_eq!("2x".parse::<ScaleMode>().unwrap(), ScaleMode::Factor(2.0));
+    assert_eq!("144dpi".parse::<ScaleMode>().unwrap(), ScaleMode::Dpi(144));
+    for invalid in ["0", "-1", "0dpi", "dpi", "nan", "big"] {
+        assert!(invalid.parse::<ScaleMode>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn factors_and_dpi_follow_the_display_scale() {
+    // A 2x display
+    assert_eq!(ScaleMode::Physical.resample_factor(2.0), 1.0);
+    assert_eq!(ScaleMode::Physical.output_dpi(2.0), 192);
+    assert_eq!(ScaleMode::Logical.resample_factor(2.0), 0.5);
+    assert_eq!(ScaleMode::Logical.output_dpi(2.0), 96);
+    assert_eq!(ScaleMode::Factor(0.75).output_dpi(2.0), 144);
+    assert_eq!(ScaleMode::Dpi(144).resample_factor(2.0), 0.75);
+    assert_eq!(ScaleMode::Dpi(144).output_dpi(2.0), 144);
+
+    // An unknown scale counts as 1x
+    assert_eq!(ScaleMode::Logical.resample_factor(0.0), 1.0);
+    assert_eq!(ScaleMode::Physical.output_dpi(0.0), 96);
+}
+
+#[test]
+fn resample_borrows_at_factor_one() {
+    let pixels = vec![255u8; 4 * 4 * 4];
+    let (data, width, height) = resample(&pixels, 4, 4, 1.0).unwrap();
+    assert!(matches!(data, std::borrow::Cow::Borrowed(_)));
+    assert_eq!((width, height), (4, 4));
+
+    let (data, width, height) = resample(&pixels, 4, 4, 0.5).unwrap();
+    assert_eq!((width, height, data.len()), (2, 2, 16));
+}
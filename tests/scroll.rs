@@ -0,0 +1,110 @@
+use image::{Rgba, RgbaImage};
+use screenshot_tool::capture::scroll::{capture, find_scroll_offset, row_hashes, stitch, ScrollOptions, Stitcher};
+use screenshot_tool::capture::source::MockFrameSource;
+use screenshot_tool::capture::{CaptureInfo, CapturedImage};
+use screenshot_tool::config::Config;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+const WIDTH: u32 = 24;
+const VIEW: u32 = 60;
+
+/// A tall synthetic page: every row gets its own colour, with a blank band
+/// of identical rows every 50 rows.
+fn page(height: u32) -> RgbaImage {
+    RgbaImage::from_fn(WIDTH, height, |x, y| {
+        if y % 50 < 10 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            let v = y.wrapping_mul(2_654_435_761).wrapping_add(x * 7);
+            Rgba([(v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8, 255])
+        }
+    })
+}
+
+/// What the viewport shows when the page is scrolled down by `offset` rows.
+fn view(page: &RgbaImage, offset: u32) -> RgbaImage {
+    image::imageops::crop_imm(page, 0, offset, WIDTH, VIEW).to_image()
+}
+
+#[test]
+fn offset_is_found_between_scrolled_frames() {
+    let page = page(400);
+    let a = row_hashes(&view(&page, 0));
+    let b = row_hashes(&view(&page, 17));
+
+    assert_eq!(find_scroll_offset(&a, &b), Some(17));
+    assert_eq!(find_scroll_offset(&a, &a), Some(0));
+}
+
+#[test]
+fn unrelated_frames_do_not_match() {
+    let page = page(400);
+    let a = row_hashes(&view(&page, 0));
+    let b = row_hashes(&view(&page, 200));
+
+    assert_eq!(find_scroll_offset(&a, &b), None);
+}
+
+#[test]
+fn frames_stitch_back_into_the_page() {
+    let page = page(400);
+    let frames: Vec<RgbaImage> = [0, 23, 23, 40, 65, 100, 101, 130, 160]
+        .iter()
+        .map(|&offset| view(&page, offset))
+        .collect();
+
+    let stitched = stitch(&frames).unwrap();
+
+    assert_eq!(stitched.dimensions(), (WIDTH, 160 + VIEW));
+    assert_eq!(stitched.as_raw(), view_of_height(&page, 160 + VIEW).as_raw());
+}
+
+#[test]
+fn blank_bands_do_not_confuse_the_match() {
+    // Both frames contain the same blank band, which lines up with itself
+    // at many wrong offsets.
+    let page = page(400);
+    let mut stitcher = Stitcher::new(&view(&page, 40));
+
+    assert_eq!(stitcher.push(&view(&page, 45)).unwrap(), 5);
+    assert_eq!(stitcher.push(&view(&page, 75)).unwrap(), 30);
+    assert_eq!(stitcher.height(), VIEW + 35);
+}
+
+#[test]
+fn frame_without_overlap_is_appended_whole() {
+    let page = page(400);
+    let mut stitcher = Stitcher::new(&view(&page, 0));
+
+    assert_eq!(stitcher.push(&view(&page, 300)).unwrap(), VIEW);
+    assert_eq!(stitcher.height(), VIEW * 2);
+}
+
+#[test]
+fn width_mismatch_is_an_error() {
+    let mut stitcher = Stitcher::new(&RgbaImage::new(10, 10));
+    assert!(stitcher.push(&RgbaImage::new(12, 10)).is_err());
+}
+
+fn view_of_height(page: &RgbaImage, height: u32) -> RgbaImage {
+    image::imageops::crop_imm(page, 0, 0, WIDTH, height).to_image()
+}
+
+#[test]
+fn an_interval_too_long_to_schedule_saves_what_was_captured() {
+    let directory = tempfile::tempdir().unwrap();
+    let config = Config { output_directory: directory.path().to_path_buf(), history: false, ..Config::default() };
+    let options = ScrollOptions {
+        interval: Duration::from_secs(u64::MAX),
+        max_frames: 5,
+        idle: Duration::from_secs(2),
+        auto_scroll: None,
+    };
+    let first = view(&page(400), 0);
+    let frame = CapturedImage { data: first.as_raw().clone(), width: WIDTH, height: VIEW, info: CaptureInfo::default() };
+
+    let mut source = MockFrameSource::new(vec![frame]);
+    let path = capture(&mut source, &options, &config, &AtomicBool::new(false)).unwrap();
+    assert_eq!(image::image_dimensions(path).unwrap(), (WIDTH, VIEW));
+}
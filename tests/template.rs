@@ -0,0 +1,58 @@
+use chrono::{Local, TimeZone};
+use screenshot_tool::config::Config;
+use screenshot_tool::utils::path::generate_filename;
+use screenshot_tool::utils::template::{expand_filename, validate_filename_template, FilenameValues};
+
+fn values() -> FilenameValues<'static> {
+    FilenameValues {
+        mode: "window",
+        screen: Some(1),
+        width: 1280,
+        height: 720,
+        window_title: Some("main.rs: ~/src/app"),
+        window_class: Some("Code"),
+        hash: Some(0x0123_4567_89ab_cdef),
+    }
+}
+
+#[test]
+fn tokens_are_expanded_and_sanitized() {
+    let now = Local.with_ymd_and_hms(2024, 5, 1, 14, 30, 0).unwrap();
+
+    let name = expand_filename("{mode}_{window_class}_%Y%m%d_{width}x{height}_{hash8}", &values(), 1, &now).unwrap();
+    assert_eq!(name, "window_Code_20240501_1280x720_01234567");
+
+    // Values are never read as strftime specifiers
+    let title = FilenameValues { window_title: Some("100% done"), ..values() };
+    assert_eq!(expand_filename("{window_title}", &title, 1, &now).unwrap(), "100% done");
+    assert_eq!(expand_filename("{window_title}", &values(), 1, &now).unwrap(), "main.rs_ ~_src_app");
+
+    assert_eq!(expand_filename("s{screen:02}_{counter:04}", &values(), 7, &now).unwrap(), "s01_0007");
+    assert_eq!(expand_filename("{date}_{window_title}", &FilenameValues::default(), 1, &now).unwrap(), "2024-05-01_");
+}
+
+#[test]
+fn bad_templates_are_reported() {
+    assert!(validate_filename_template("screenshot_%Y%m%d_%H%M%S").is_ok());
+    assert!(validate_filename_template("{mode}_{uuid}").is_ok());
+
+    for template in ["{nope}", "{mode", "{mode:04}", "{counter:x}", "shot_%Q"] {
+        let error = validate_filename_template(template).unwrap_err().to_string();
+        assert!(error.contains(template), "{}", error);
+    }
+}
+
+#[test]
+fn counter_skips_names_that_exist() {
+    let temp = tempfile::tempdir().unwrap();
+    let directory = temp.path();
+    let config = Config { filename_template: "{mode}_{counter:03}".to_string(), ..Config::default() };
+
+    std::fs::write(directory.join("window_001.png"), b"").unwrap();
+    std::fs::write(directory.join("window_002.png"), b"").unwrap();
+    assert_eq!(generate_filename(&config, "window", &values(), directory).unwrap(), "window_003.png");
+
+    // Templates without tokens keep the mode prefix
+    let plain = Config { filename_template: "%Y".to_string(), ..Config::default() };
+    assert!(generate_filename(&plain, "window", &values(), directory).unwrap().starts_with("window_"));
+}
@@ -0,0 +1,38 @@
+use screenshot_tool::capture::save_thumbnail;
+use screenshot_tool::config::ThumbnailConfig;
+
+fn thumbnail(max_edge: u32, format: &str) -> ThumbnailConfig {
+    ThumbnailConfig { max_edge, format: format.to_string(), quality: 80 }
+}
+
+#[test]
+fn thumbnails_fit_the_max_edge_and_take_the_format_extension() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("shot.png");
+    let pixels = vec![200u8; 400 * 200 * 4];
+
+    let thumb = save_thumbnail(&path, &pixels, 400, 200, &thumbnail(100, "JPEG")).unwrap();
+    assert_eq!(thumb, directory.path().join("shot.thumb.jpg"));
+    assert_eq!(image::image_dimensions(&thumb).unwrap(), (100, 50));
+
+    // Smaller than max_edge: kept at its own size
+    let thumb = save_thumbnail(&path, &pixels, 400, 200, &thumbnail(1000, "png")).unwrap();
+    assert_eq!(thumb, directory.path().join("shot.thumb.png"));
+    assert_eq!(image::image_dimensions(&thumb).unwrap(), (400, 200));
+}
+
+#[test]
+fn other_thumbnail_formats_are_errors_not_panics() {
+    let directory = tempfile::tempdir().unwrap();
+    let pixels = vec![200u8; 4 * 4 * 4];
+    let error = save_thumbnail(&directory.path().join("shot.png"), &pixels, 4, 4, &thumbnail(2, "webp")).unwrap_err();
+    assert!(error.to_string().contains("webp"), "{}", error);
+    assert!(std::fs::read_dir(directory.path()).unwrap().next().is_none());
+}
+
+#[test]
+fn webp_thumbnails_are_rejected_up_front() {
+    assert!(thumbnail(100, "webp").validate().is_err());
+    assert!(thumbnail(100, "jpg").validate().is_ok());
+    assert!(thumbnail(0, "png").validate().is_err());
+}
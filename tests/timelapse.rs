@@ -0,0 +1,81 @@
+use chrono::{Local, TimeZone, Timelike};
+use screenshot_tool::capture::timelapse::{next_slot, run, slot_deadline, TimelapseOptions};
+use screenshot_tool::config::Config;
+use screenshot_tool::utils::time::{parse_duration, parse_until};
+use screenshot_tool::ScreenshotError;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn durations_take_a_unit() {
+    assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+    assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+    assert_eq!(parse_duration(" 1.5m ").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+    assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(7 * 86400));
+
+    for bad in ["", "0s", "-5s", "5 parsecs", "s", "1e3s"] {
+        assert!(parse_duration(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn until_is_the_next_matching_time() {
+    let now = Local.with_ymd_and_hms(2024, 5, 1, 14, 30, 0).unwrap();
+
+    let later = parse_until("18:00", &now).unwrap();
+    assert_eq!(later, Local.with_ymd_and_hms(2024, 5, 1, 18, 0, 0).unwrap());
+
+    // Already past today: tomorrow
+    let tomorrow = parse_until("09:15:30", &now).unwrap();
+    assert_eq!(tomorrow, Local.with_ymd_and_hms(2024, 5, 2, 9, 15, 30).unwrap());
+    assert_eq!(parse_until("14:30", &now).unwrap().hour(), 14);
+    assert!(parse_until("14:30", &now).unwrap() > now);
+
+    assert!(parse_until("25:00", &now).is_err());
+    assert!(parse_until("soon", &now).is_err());
+}
+
+#[test]
+fn slots_are_kept_on_the_original_schedule() {
+    let interval = Duration::from_secs(10);
+
+    // A quick capture waits for the next slot
+    assert_eq!(next_slot(0, Duration::from_millis(300), interval), (1, 0));
+    assert_eq!(next_slot(4, Duration::from_millis(40_500), interval), (5, 0));
+
+    // A capture that overran slots 2 and 3 resumes at slot 4, not 11s after it ended
+    assert_eq!(next_slot(1, Duration::from_millis(35_000), interval), (4, 2));
+
+    // Running early never moves the schedule back
+    assert_eq!(next_slot(6, Duration::from_secs(12), interval), (7, 0));
+}
+
+#[test]
+fn slots_too_far_ahead_have_no_deadline() {
+    let start = Instant::now();
+    let huge = parse_duration("18000000000000000000s").unwrap();
+    assert_eq!(slot_deadline(start, Duration::from_secs(10), 3), Some(start + Duration::from_secs(30)));
+    assert_eq!(slot_deadline(start, huge, 0), Some(start));
+    assert_eq!(slot_deadline(start, huge, 1), None);
+    assert_eq!(slot_deadline(start, Duration::from_secs(u64::MAX / 2), 3), None);
+}
+
+#[test]
+fn a_timelapse_outside_the_allowed_roots_fails_before_capturing() {
+    let directory = tempfile::tempdir().unwrap();
+    let config = Config {
+        output_directory: directory.path().join("elsewhere"),
+        allowed_roots: vec![directory.path().join("shots")],
+        ..Config::default()
+    };
+    let options = TimelapseOptions { interval: ms(10), count: Some(1), until: None, screen: 0, region: None };
+
+    let error = run(&options, &config, &AtomicBool::new(false)).unwrap_err();
+    assert!(matches!(error, ScreenshotError::PermissionDenied(_)), "{}", error);
+    assert!(!directory.path().join("elsewhere").exists());
+}
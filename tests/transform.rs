@@ -0,0 +1,98 @@
+use image::{Rgba, RgbaImage};
+use screenshot_tool::capture::file::process_all;
+use screenshot_tool::capture::region::Region;
+use screenshot_tool::capture::transform::{redact, trim};
+use screenshot_tool::config::Config;
+
+#[test]
+fn regions_parse_as_x_y_w_h() {
+    let region: Region = "10, -5,200x40".parse().unwrap();
+    assert_eq!((region.x, region.y, region.width, region.height), (10, -5, 200, 40));
+    assert_eq!(region.to_string(), "10,-5,200x40");
+
+    for bad in ["10,10,200", "a,0,1x1", "0,0,0x10", "0,0,10x-1", ""] {
+        assert!(bad.parse::<Region>().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn redact_blacks_out_the_clamped_region() {
+    let mut image = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 255]));
+    redact(&mut image, &["-1,2,2x5".parse().unwrap(), "10,10,1x1".parse().unwrap()]);
+
+    assert_eq!(*image.get_pixel(0, 3), Rgba([0, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(0, 2), Rgba([0, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(1, 2), Rgba([255, 255, 255, 255]));
+    assert_eq!(*image.get_pixel(0, 1), Rgba([255, 255, 255, 255]));
+}
+
+#[test]
+fn trim_crops_a_uniform_border() {
+    let mut image = RgbaImage::from_pixel(6, 5, Rgba([250, 250, 250, 255]));
+    // Within the tolerance, still border
+    image.put_pixel(0, 4, Rgba([245, 250, 250, 255]));
+    image.put_pixel(2, 1, Rgba([0, 0, 0, 255]));
+    image.put_pixel(3, 2, Rgba([0, 0, 0, 255]));
+
+    let trimmed = trim(&image).unwrap();
+    assert_eq!(trimmed.dimensions(), (2, 2));
+    assert_eq!(*trimmed.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+
+    // Nothing to trim, or nothing but border
+    assert!(trim(&trimmed).is_none());
+    assert!(trim(&RgbaImage::from_pixel(3, 3, Rgba([1, 2, 3, 255]))).is_none());
+}
+
+#[test]
+fn inputs_sharing_a_stem_get_distinct_outputs() {
+    let directory = tempfile::tempdir().unwrap();
+    let output = directory.path().join("out");
+    let mut inputs = Vec::new();
+    for (folder, shade) in [("a", 10u8), ("b", 20)] {
+        std::fs::create_dir(directory.path().join(folder)).unwrap();
+        let input = directory.path().join(folder).join("shot.png");
+        RgbaImage::from_pixel(2, 2, Rgba([shade, shade, shade, 255])).save(&input).unwrap();
+        inputs.push(input);
+    }
+
+    let config = Config {
+        output_directory: output,
+        filename_template: "x".to_string(),
+        on_collision: Some("overwrite".to_string()),
+        write_metadata: false,
+        history: false,
+        ..Config::default()
+    };
+    let results = process_all(&inputs, &config);
+
+    let names: Vec<String> = results
+        .iter()
+        .map(|(_, result)| result.as_ref().unwrap().file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, vec!["shot_x.png", "shot_2_x.png"]);
+    let shade = |i: usize| image::open(results[i].1.as_ref().unwrap()).unwrap().to_rgba8().get_pixel(0, 0)[0];
+    assert_eq!((shade(0), shade(1)), (10, 20));
+}
+
+#[test]
+fn process_encodes_avif_and_rejects_webp_up_front() {
+    let directory = tempfile::tempdir().unwrap();
+    let input = directory.path().join("shot.png");
+    RgbaImage::from_pixel(16, 8, Rgba([40, 80, 120, 255])).save(&input).unwrap();
+
+    let config = Config {
+        output_directory: directory.path().join("out"),
+        default_format: "avif".to_string(),
+        history: false,
+        ..Config::default()
+    };
+    assert!(config.validate().is_ok());
+    let results = process_all(&[input], &config);
+    let path = results[0].1.as_ref().unwrap();
+    assert_eq!(path.extension().unwrap(), "avif");
+    let avif = std::fs::read(path).unwrap();
+    assert_eq!(&avif[4..12], b"ftypavif");
+
+    let webp = Config { default_format: "WebP".to_string(), ..Config::default() };
+    assert!(webp.validate().unwrap_err().to_string().contains("cannot be encoded"));
+}
@@ -0,0 +1,125 @@
+use screenshot_tool::capture::source::{FrameSource, MockFrameSource};
+use screenshot_tool::capture::watch::{self, ChangeDetector, WatchOptions};
+use screenshot_tool::capture::{CaptureInfo, CapturedImage};
+use screenshot_tool::config::Config;
+use screenshot_tool::diff::{changed_fraction, parse_threshold};
+use screenshot_tool::utils::time::parse_duration;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+/// A 10x10 grey frame with the first `changed` pixels turned white.
+fn frame(changed: usize) -> CapturedImage {
+    let mut data = vec![128u8; 10 * 10 * 4];
+    for pixel in data.chunks_exact_mut(4).take(changed) {
+        pixel.copy_from_slice(&[255, 255, 255, 255]);
+    }
+    CapturedImage { data, width: 10, height: 10, info: CaptureInfo::default() }
+}
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn changed_fraction_counts_pixels_over_tolerance() {
+    let a = frame(0);
+    let b = frame(5);
+    assert_eq!(changed_fraction(&a.data, (10, 10), &b.data, (10, 10), 16), 0.05);
+    assert_eq!(changed_fraction(&a.data, (10, 10), &a.data, (10, 10), 0), 0.0);
+
+    // Small noise stays under the tolerance.
+    let mut noisy = a.data.clone();
+    noisy[0] += 10;
+    assert_eq!(changed_fraction(&a.data, (10, 10), &noisy, (10, 10), 16), 0.0);
+    assert_eq!(changed_fraction(&a.data, (10, 10), &noisy, (10, 10), 0), 0.01);
+}
+
+#[test]
+fn changed_fraction_treats_size_change_as_full_change() {
+    let a = frame(0);
+    assert_eq!(changed_fraction(&a.data, (10, 10), &a.data, (20, 5), 16), 1.0);
+}
+
+#[test]
+fn threshold_accepts_percent_and_fraction() {
+    assert_eq!(parse_threshold("2%").unwrap(), 0.02);
+    assert_eq!(parse_threshold("0.5").unwrap(), 0.5);
+    assert!(parse_threshold("150%").is_err());
+    assert!(parse_threshold("lots").is_err());
+}
+
+#[test]
+fn first_frame_is_saved_and_small_changes_are_ignored() {
+    let mut detector = ChangeDetector::new(0.02, Duration::ZERO, Duration::ZERO);
+    let mut source = MockFrameSource::new(vec![frame(0), frame(1), frame(2), frame(3)]);
+    let start = Instant::now();
+
+    assert!(detector.observe(source.grab().unwrap(), start).is_some());
+    assert!(detector.observe(source.grab().unwrap(), start + ms(100)).is_none());
+    assert!(detector.observe(source.grab().unwrap(), start + ms(200)).is_none());
+    assert!(detector.observe(source.grab().unwrap(), start + ms(300)).is_some());
+}
+
+#[test]
+fn change_is_saved_only_after_settling() {
+    let mut detector = ChangeDetector::new(0.02, Duration::ZERO, ms(300));
+    let mut source = MockFrameSource::new(vec![frame(0), frame(10), frame(40), frame(40)]);
+    let start = Instant::now();
+
+    assert!(detector.observe(source.grab().unwrap(), start).is_some());
+    // Still changing: 10 pixels, then 40.
+    assert!(detector.observe(source.grab().unwrap(), start + ms(100)).is_none());
+    assert!(detector.observe(source.grab().unwrap(), start + ms(200)).is_none());
+    // Unchanged since 200ms, but not yet for 300ms.
+    assert!(detector.observe(source.grab().unwrap(), start + ms(400)).is_none());
+    // The mock repeats its last frame.
+    let saved = detector.observe(source.grab().unwrap(), start + ms(500)).unwrap();
+    assert_eq!(saved.data, frame(40).data);
+}
+
+#[test]
+fn change_reverting_before_settling_is_dropped() {
+    let mut detector = ChangeDetector::new(0.02, Duration::ZERO, ms(300));
+    let start = Instant::now();
+
+    assert!(detector.observe(frame(0), start).is_some());
+    assert!(detector.observe(frame(50), start + ms(100)).is_none());
+    assert!(detector.observe(frame(0), start + ms(200)).is_none());
+    assert!(detector.observe(frame(0), start + ms(1000)).is_none());
+}
+
+#[test]
+fn min_interval_delays_the_next_save() {
+    let mut detector = ChangeDetector::new(0.02, ms(1000), Duration::ZERO);
+    let start = Instant::now();
+
+    assert!(detector.observe(frame(0), start).is_some());
+    assert!(detector.observe(frame(50), start + ms(200)).is_none());
+    assert!(detector.observe(frame(50), start + ms(600)).is_none());
+    assert!(detector.observe(frame(50), start + ms(1000)).is_some());
+    // Identical to the last save now.
+    assert!(detector.observe(frame(50), start + ms(2500)).is_none());
+}
+
+#[test]
+fn durations_of_2_pow_64_seconds_are_rejected() {
+    assert!(parse_duration("18446744073709551616").is_err());
+    assert!(parse_duration("213503982334602d").is_err());
+}
+
+#[test]
+fn a_poll_too_long_to_schedule_stops_after_the_first_frame() {
+    let directory = tempfile::tempdir().unwrap();
+    let config = Config { output_directory: directory.path().to_path_buf(), history: false, ..Config::default() };
+    let options = WatchOptions {
+        poll: parse_duration("18000000000000000000s").unwrap(),
+        threshold: 0.02,
+        min_interval: Duration::ZERO,
+        settle: Duration::ZERO,
+        count: None,
+    };
+
+    let mut source = MockFrameSource::new(vec![frame(0)]);
+    let summary = watch::run(&mut source, &options, &config, &AtomicBool::new(false)).unwrap();
+    assert_eq!((summary.polled, summary.captured), (1, 1));
+}
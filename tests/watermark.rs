@@ -0,0 +1,76 @@
+use chrono::{Local, TimeZone};
+use image::{Rgba, RgbaImage};
+use screenshot_tool::capture::watermark::{apply, parse_color};
+use screenshot_tool::config::WatermarkConfig;
+use screenshot_tool::utils::template::expand_text;
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+/// A watermark of a 2x2 red logo, 1px from the edges.
+fn logo_watermark(directory: &tempfile::TempDir) -> WatermarkConfig {
+    let logo = directory.path().join("logo.png");
+    RgbaImage::from_pixel(2, 2, RED).save(&logo).unwrap();
+    WatermarkConfig { image: Some(logo), opacity: 1.0, margin: 1, ..WatermarkConfig::default() }
+}
+
+fn red_pixels(image: &RgbaImage) -> Vec<(u32, u32)> {
+    image.enumerate_pixels().filter(|(_, _, pixel)| pixel[0] > 0).map(|(x, y, _)| (x, y)).collect()
+}
+
+#[test]
+fn colors_parse_with_and_without_alpha() {
+    assert_eq!(parse_color("#ff8000").unwrap(), Rgba([255, 128, 0, 255]));
+    assert_eq!(parse_color("00000080").unwrap(), Rgba([0, 0, 0, 128]));
+    for invalid in ["#fff", "#gg0000", "#ff00000", "#ffé000"] {
+        assert!(parse_color(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn stamps_are_placed_by_position_and_margin() {
+    let directory = tempfile::tempdir().unwrap();
+    let now = Local::now();
+
+    let mut canvas = RgbaImage::from_pixel(10, 10, BLACK);
+    let watermark = WatermarkConfig { position: "bottom-right".to_string(), ..logo_watermark(&directory) };
+    apply(&mut canvas, &watermark, &now).unwrap();
+    assert_eq!(red_pixels(&canvas), vec![(7, 7), (8, 7), (7, 8), (8, 8)]);
+
+    let mut canvas = RgbaImage::from_pixel(10, 10, BLACK);
+    let watermark = WatermarkConfig { position: "center".to_string(), ..logo_watermark(&directory) };
+    apply(&mut canvas, &watermark, &now).unwrap();
+    assert_eq!(red_pixels(&canvas), vec![(4, 4), (5, 4), (4, 5), (5, 5)]);
+
+    let watermark = WatermarkConfig { position: "middle".to_string(), ..logo_watermark(&directory) };
+    assert!(apply(&mut canvas, &watermark, &now).is_err());
+}
+
+#[test]
+fn tiles_repeat_and_opacity_blends() {
+    let directory = tempfile::tempdir().unwrap();
+    let now = Local::now();
+
+    let mut canvas = RgbaImage::from_pixel(8, 8, BLACK);
+    let watermark = WatermarkConfig { tile: true, ..logo_watermark(&directory) };
+    apply(&mut canvas, &watermark, &now).unwrap();
+    // Stamps at 1 and 4 on each axis, the last one clipped at 7
+    for (x, y) in [(1, 1), (4, 1), (1, 4), (4, 4), (7, 7)] {
+        assert_eq!(*canvas.get_pixel(x, y), RED, "({}, {})", x, y);
+    }
+    assert_eq!(*canvas.get_pixel(3, 3), BLACK);
+
+    let mut canvas = RgbaImage::from_pixel(4, 4, BLACK);
+    let watermark = WatermarkConfig { opacity: 0.5, position: "top-left".to_string(), ..logo_watermark(&directory) };
+    apply(&mut canvas, &watermark, &now).unwrap();
+    let red = canvas.get_pixel(1, 1)[0];
+    assert!((120..=136).contains(&red), "{}", red);
+}
+
+#[test]
+fn a_percent_in_the_user_name_is_kept() {
+    let now = Local.with_ymd_and_hms(2024, 5, 1, 14, 30, 0).unwrap();
+    std::env::set_var("USER", "100%s");
+    assert_eq!(expand_text("{user} {date} %H:%M", &now).unwrap(), "100%s 2024-05-01 14:30");
+    assert!(expand_text("%Q", &now).is_err());
+}